@@ -14,6 +14,22 @@ use codespan_reporting::{
 use ir_to_bytecode_syntax::syntax::{self, ParseError};
 use libra_types::account_address::AccountAddress;
 use move_ir_types::{ast, location::*};
+use std::fmt;
+
+/// The error returned when the parser finds a token it can't parse, along with where it is in
+/// the source.
+#[derive(Clone, Copy, Debug)]
+pub struct InvalidTokenError {
+    pub location: Loc,
+}
+
+impl fmt::Display for InvalidTokenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ParserError: Invalid Token")
+    }
+}
+
+impl std::error::Error for InvalidTokenError {}
 
 /// Determine if a character is an allowed eye-visible (printable) character.
 ///
@@ -119,7 +135,7 @@ pub fn parse_cmd_(
 }
 
 fn handle_error<T>(e: syntax::ParseError<Loc, anyhow::Error>, code_str: &str) -> Result<T> {
-    let msg = match &e {
+    match e {
         ParseError::InvalidToken { location } => {
             let mut files = Files::new();
             let id = files.add(location.file(), code_str.to_string());
@@ -127,14 +143,13 @@ fn handle_error<T>(e: syntax::ParseError<Loc, anyhow::Error>, code_str: &str) ->
             let error = Diagnostic::new_error("Parser Error", lbl);
             let writer = &mut StandardStream::stderr(ColorChoice::Auto);
             emit(writer, &Config::default(), &files, &error).unwrap();
-            "Invalid Token".to_string()
+            Err(InvalidTokenError { location }.into())
         }
         ParseError::User { error } => {
             println!("{}", error);
-            format!("{}", error)
+            bail!("ParserError: {}", error)
         }
-    };
-    bail!("ParserError: {}", msg)
+    }
 }
 
 #[cfg(test)]
@@ -3,9 +3,13 @@
 
 //! Test infrastructure for modeling Libra accounts.
 
-use crate::{gas_costs, keygen::KeyGen};
+use crate::{common_transactions::AccountTxnBuilder, gas_costs, keygen::KeyGen};
 use anyhow::{Error, Result};
-use libra_crypto::ed25519::*;
+use libra_crypto::{
+    ed25519::*,
+    multi_ed25519::{MultiEd25519PublicKey, MultiEd25519Signature},
+    SigningKey,
+};
 use libra_types::{
     access_path::AccessPath,
     account_address::AccountAddress,
@@ -45,6 +49,34 @@ pub fn coin2_currency_code() -> Identifier {
     from_currency_code_string(COIN2_NAME).unwrap()
 }
 
+/// Returns the address of the account authenticated by `auth_key`: its last
+/// [`AccountAddress::LENGTH`] bytes, the rest being the prefix passed to the account creation
+/// scripts. This is how the VM derives the address of a signer from its authentication key.
+pub fn derive_address(auth_key: &AuthenticationKey) -> AccountAddress {
+    auth_key.derived_address()
+}
+
+/// The seed [`gen_accounts`] derives account keys from.
+pub const GEN_ACCOUNTS_SEED: [u8; 32] = [42u8; 32];
+
+/// Returns `n` accounts whose keys are derived from [`GEN_ACCOUNTS_SEED`], so that every run gets
+/// the same keys and addresses.
+pub fn gen_accounts(n: usize) -> Vec<Account> {
+    gen_accounts_from_seed(GEN_ACCOUNTS_SEED, n)
+}
+
+/// Returns `n` accounts whose keys are derived from `seed`. The same seed always gives the same
+/// accounts, in the same order.
+pub fn gen_accounts_from_seed(seed: [u8; 32], n: usize) -> Vec<Account> {
+    let mut keygen = KeyGen::from_seed(seed);
+    (0..n)
+        .map(|_| {
+            let (privkey, pubkey) = keygen.generate_keypair();
+            Account::with_keypair(privkey, pubkey)
+        })
+        .collect()
+}
+
 /// Details about a Libra account.
 ///
 /// Tests will typically create a set of `Account` instances to run transactions on. This type
@@ -76,7 +108,7 @@ impl Account {
     /// Like with [`Account::new`], the account returned by this constructor is a purely logical
     /// entity.
     pub fn with_keypair(privkey: Ed25519PrivateKey, pubkey: Ed25519PublicKey) -> Self {
-        let addr = libra_types::account_address::from_public_key(&pubkey);
+        let addr = derive_address(&AuthenticationKey::ed25519(&pubkey));
         Account {
             addr,
             privkey,
@@ -110,6 +142,12 @@ impl Account {
         Self::new_genesis_account(account_config::treasury_compliance_account_address())
     }
 
+    /// Creates a new account in memory with `num_keys` fresh keys, any `threshold` of which can
+    /// sign for it. See [`MultisigAccount`].
+    pub fn new_multisig(threshold: u8, num_keys: u8) -> MultisigAccount {
+        MultisigAccount::new(threshold, num_keys)
+    }
+
     /// Returns the address of the account. This is a hash of the public key the account was created
     /// with.
     ///
@@ -343,6 +381,61 @@ impl Account {
         .into_inner()
     }
 
+    /// Returns a [`SignedTransaction`] from this account that expires at `expiration_secs`, in
+    /// seconds since the Unix epoch.
+    ///
+    /// The prologue rejects it with `TRANSACTION_EXPIRED` once the on-chain time, which follows
+    /// the block time of the last block prologue, reaches the expiration time.
+    pub fn create_signed_txn_with_expiration(
+        &self,
+        program: TransactionPayload,
+        sequence_number: u64,
+        max_gas_amount: u64,
+        gas_unit_price: u64,
+        gas_currency_code: String,
+        expiration_secs: u64,
+    ) -> SignedTransaction {
+        RawTransaction::new(
+            *self.address(),
+            sequence_number,
+            program,
+            max_gas_amount,
+            gas_unit_price,
+            gas_currency_code,
+            Duration::from_secs(expiration_secs),
+        )
+        .sign(&self.privkey, self.pubkey.clone())
+        .unwrap()
+        .into_inner()
+    }
+
+    /// Returns a [`SignedTransaction`] from this account signed with `wrong_privkey` instead of
+    /// this account's key, for negative tests.
+    ///
+    /// The transaction still carries this account's address and public key, so the prologue
+    /// rejects it with `INVALID_SIGNATURE` rather than `INVALID_AUTH_KEY`.
+    pub fn create_signed_txn_with_wrong_key(
+        &self,
+        program: TransactionPayload,
+        sequence_number: u64,
+        max_gas_amount: u64,
+        gas_unit_price: u64,
+        gas_currency_code: String,
+        wrong_privkey: &Ed25519PrivateKey,
+    ) -> SignedTransaction {
+        Self::create_raw_txn_impl(
+            *self.address(),
+            program,
+            sequence_number,
+            max_gas_amount,
+            gas_unit_price,
+            gas_currency_code,
+        )
+        .sign(wrong_privkey, self.pubkey.clone())
+        .unwrap()
+        .into_inner()
+    }
+
     /// Create a transaction containing `script` signed by `sender` with default values for gas
     /// cost, gas price, expiration time, and currency type.
     pub fn signed_script_txn(&self, script: Script, sequence_number: u64) -> SignedTransaction {
@@ -379,6 +472,12 @@ impl Account {
     pub fn transaction(&self) -> TransactionBuilder {
         TransactionBuilder::new(self.clone())
     }
+
+    /// Returns a builder for a sequence of transactions from this account, the first of which has
+    /// sequence number `starting_seq`.
+    pub fn txn_builder(&self, starting_seq: u64) -> AccountTxnBuilder {
+        AccountTxnBuilder::new(self, starting_seq)
+    }
 }
 
 impl Default for Account {
@@ -430,6 +529,11 @@ impl TransactionBuilder {
         self
     }
 
+    pub fn payload(mut self, payload: TransactionPayload) -> Self {
+        self.program = Some(payload);
+        self
+    }
+
     pub fn max_gas_amount(mut self, max_gas_amount: u64) -> Self {
         self.max_gas_amount = Some(max_gas_amount);
         self
@@ -469,6 +573,85 @@ impl TransactionBuilder {
     }
 }
 
+//---------------------------------------------------------------------------
+// Multisig account representation
+//---------------------------------------------------------------------------
+
+/// Details about a Libra account authenticated by a K-of-N MultiEd25519 policy.
+///
+/// Like [`Account`], this is a purely logical entity. Its address is derived from the
+/// authentication key of the policy, the same way the VM derives it.
+#[derive(Debug)]
+pub struct MultisigAccount {
+    addr: AccountAddress,
+    privkeys: Vec<Ed25519PrivateKey>,
+    pubkey: MultiEd25519PublicKey,
+}
+
+impl MultisigAccount {
+    /// Creates a new account in memory with `num_keys` fresh keys, any `threshold` of which can
+    /// sign for it.
+    pub fn new(threshold: u8, num_keys: u8) -> Self {
+        let mut keygen = KeyGen::from_os_rng();
+        let (privkeys, pubkeys): (Vec<_>, Vec<_>) =
+            (0..num_keys).map(|_| keygen.generate_keypair()).unzip();
+        let pubkey = MultiEd25519PublicKey::new(pubkeys, threshold)
+            .expect("threshold must be between 1 and the number of keys");
+        let addr = derive_address(&AuthenticationKey::multi_ed25519(&pubkey));
+        Self {
+            addr,
+            privkeys,
+            pubkey,
+        }
+    }
+
+    /// Returns the address of the account.
+    pub fn address(&self) -> &AccountAddress {
+        &self.addr
+    }
+
+    /// Returns the K-of-N public key of the account.
+    pub fn public_key(&self) -> &MultiEd25519PublicKey {
+        &self.pubkey
+    }
+
+    /// Computes the authentication key for this account, as stored on the chain.
+    pub fn auth_key(&self) -> Vec<u8> {
+        AuthenticationKey::multi_ed25519(&self.pubkey).to_vec()
+    }
+
+    /// Signs `raw_txn` with the keys at the indices in `signers`.
+    ///
+    /// The transaction only passes signature checking if there are at least as many signers as the
+    /// threshold.
+    pub fn create_multisig_signed_txn(
+        &self,
+        raw_txn: RawTransaction,
+        signers: &[u8],
+    ) -> SignedTransaction {
+        let positions: Vec<_> = signers.iter().map(|idx| (*idx, *idx)).collect();
+        self.create_multisig_signed_txn_with_positions(raw_txn, &positions)
+    }
+
+    /// Signs `raw_txn` with the keys given as `(key index, bitmap position)` pairs.
+    ///
+    /// A signature is only valid at the position of the key that made it, so this allows building
+    /// signatures whose bitmap doesn't match the signing keys.
+    pub fn create_multisig_signed_txn_with_positions(
+        &self,
+        raw_txn: RawTransaction,
+        signers: &[(u8, u8)],
+    ) -> SignedTransaction {
+        let signatures = signers
+            .iter()
+            .map(|(key_idx, position)| (self.privkeys[*key_idx as usize].sign(&raw_txn), *position))
+            .collect();
+        let signature = MultiEd25519Signature::new(signatures)
+            .expect("signers must be non-empty and at distinct positions");
+        SignedTransaction::new_multisig(raw_txn, self.pubkey.clone(), signature)
+    }
+}
+
 //---------------------------------------------------------------------------
 // Balance resource represenation
 //---------------------------------------------------------------------------
@@ -531,6 +714,22 @@ impl AccountRoleSpecifier {
         }
     }
 
+    /// Returns the role specifier for the on-chain role id `id`, or `None` if `id` isn't one of the
+    /// standard roles.
+    pub fn from_id(id: u64) -> Option<Self> {
+        match id {
+            0 => Some(Self::LibraRoot),
+            1 => Some(Self::TreasuryCompliance),
+            2 => Some(Self::DesignatedDealer),
+            3 => Some(Self::Validator),
+            4 => Some(Self::ValidatorOperator),
+            5 => Some(Self::ParentVASP),
+            6 => Some(Self::ChildVASP),
+            7 => Some(Self::Unhosted),
+            _ => None,
+        }
+    }
+
     pub fn layout() -> MoveStructLayout {
         MoveStructLayout::new(vec![MoveTypeLayout::U64])
     }
@@ -571,6 +770,23 @@ impl Default for AccountRoleSpecifier {
     }
 }
 
+/// A role as published on chain in a `Roles::RoleId` resource.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Role {
+    /// One of the standard roles.
+    Known(AccountRoleSpecifier),
+    /// A role id that doesn't correspond to any of the standard roles.
+    Unknown(u64),
+}
+
+/// Decodes a serialized `Roles::RoleId` resource into a [`Role`].
+pub fn decode_role(bytes: &[u8]) -> Role {
+    let role_id = lcs::from_bytes::<RoleId>(bytes)
+        .expect("Failure decoding role id resource")
+        .role_id();
+    AccountRoleSpecifier::from_id(role_id).map_or(Role::Unknown(role_id), Role::Known)
+}
+
 //---------------------------------------------------------------------------
 // Account type resource represenation
 //---------------------------------------------------------------------------
@@ -663,6 +879,44 @@ impl AccountData {
         )
     }
 
+    /// Returns a builder for an `AccountData`, to set up accounts with several balances, another
+    /// role or existing events without going through the positional constructors.
+    pub fn builder() -> AccountDataBuilder {
+        AccountDataBuilder::new()
+    }
+
+    /// Creates a new `AccountData` with a new account holding `balance` in the currency
+    /// `currency_code` instead of LBR.
+    pub fn with_balance_for_currency(
+        balance: u64,
+        currency_code: &str,
+        sequence_number: u64,
+    ) -> Self {
+        Self::with_account(
+            Account::new(),
+            balance,
+            from_currency_code_string(currency_code).unwrap(),
+            sequence_number,
+            AccountRoleSpecifier::ParentVASP,
+        )
+    }
+
+    /// Creates a new `AccountData` with a new account holding a balance in each of the given
+    /// `(balance, currency_code)` pairs.
+    pub fn new_multi(balances: &[(u64, &str)], sequence_number: u64) -> Self {
+        let (first, rest) = balances
+            .split_first()
+            .expect("an account needs at least one balance");
+        let mut account_data = Self::with_balance_for_currency(first.0, first.1, sequence_number);
+        for (balance, currency_code) in rest {
+            account_data.balances.insert(
+                from_currency_code_string(currency_code).unwrap(),
+                Balance::new(*balance),
+            );
+        }
+        account_data
+    }
+
     pub fn new_libra_root() -> Self {
         Self::with_account(
             Account::new(),
@@ -811,8 +1065,7 @@ impl AccountData {
         let role_id = self.account_role.account_specifier.to_value();
         let account = Value::struct_(Struct::pack(
             vec![
-                // TODO: this needs to compute the auth key instead
-                Value::vector_u8(AuthenticationKey::ed25519(&self.account.pubkey).to_vec()),
+                Value::vector_u8(self.account.auth_key()),
                 self.withdrawal_capability.as_ref().unwrap().value(),
                 self.key_rotation_capability.as_ref().unwrap().value(),
                 Value::struct_(Struct::pack(
@@ -934,11 +1187,32 @@ impl AccountData {
         self.balances.get(currency_code).unwrap().coin()
     }
 
+    /// Returns the initial balance in the currency `currency_code`, e.g. `COIN1_NAME`, or `None`
+    /// if this account holds none.
+    pub fn balance_of(&self, currency_code: &str) -> Option<u64> {
+        let currency_code = from_currency_code_string(currency_code).ok()?;
+        self.balances.get(&currency_code).map(Balance::coin)
+    }
+
+    /// Returns the currencies this account holds a balance in, with their initial balances.
+    pub fn balances(&self) -> BTreeMap<String, u64> {
+        self.balances
+            .iter()
+            .map(|(currency_code, balance)| (currency_code.to_string(), balance.coin()))
+            .collect()
+    }
+
     /// Returns the initial sequence number.
     pub fn sequence_number(&self) -> u64 {
         self.sequence_number
     }
 
+    /// Returns the sequence number this account should have once `applied` of its transactions
+    /// have been kept. Discarded transactions don't bump the sequence number.
+    pub fn expected_sequence_after(&self, applied: usize) -> u64 {
+        self.sequence_number + applied as u64
+    }
+
     /// Returns the unique key for this sent events stream.
     pub fn sent_events_key(&self) -> &[u8] {
         self.sent_events.key().as_bytes()
@@ -960,6 +1234,91 @@ impl AccountData {
     }
 }
 
+/// Builds an [`AccountData`]. Returned by [`AccountData::builder`].
+///
+/// Unless set otherwise, the account is new, is a parent VASP with sequence number 0, has no
+/// events and holds no coins in LBR.
+pub struct AccountDataBuilder {
+    account: Option<Account>,
+    balances: BTreeMap<Identifier, u64>,
+    role: AccountRoleSpecifier,
+    sequence_number: u64,
+    sent_events_count: u64,
+    received_events_count: u64,
+}
+
+impl AccountDataBuilder {
+    pub fn new() -> Self {
+        Self {
+            account: None,
+            balances: BTreeMap::new(),
+            role: AccountRoleSpecifier::ParentVASP,
+            sequence_number: 0,
+            sent_events_count: 0,
+            received_events_count: 0,
+        }
+    }
+
+    /// Uses `account` instead of a new one.
+    pub fn account(mut self, account: Account) -> Self {
+        self.account = Some(account);
+        self
+    }
+
+    /// Holds `balance` in the currency `currency_code`. Can be called once per currency.
+    pub fn balance_in(mut self, currency_code: &str, balance: u64) -> Self {
+        self.balances
+            .insert(from_currency_code_string(currency_code).unwrap(), balance);
+        self
+    }
+
+    pub fn role(mut self, role: AccountRoleSpecifier) -> Self {
+        self.role = role;
+        self
+    }
+
+    pub fn sequence_number(mut self, sequence_number: u64) -> Self {
+        self.sequence_number = sequence_number;
+        self
+    }
+
+    pub fn sent_events_count(mut self, count: u64) -> Self {
+        self.sent_events_count = count;
+        self
+    }
+
+    pub fn received_events_count(mut self, count: u64) -> Self {
+        self.received_events_count = count;
+        self
+    }
+
+    pub fn build(self) -> AccountData {
+        let mut balances = self.balances.into_iter();
+        let (currency_code, balance) = balances.next().unwrap_or_else(|| (lbr_currency_code(), 0));
+        let mut account_data = AccountData::with_account_and_event_counts(
+            self.account.unwrap_or_else(Account::new),
+            balance,
+            currency_code,
+            self.sequence_number,
+            self.sent_events_count,
+            self.received_events_count,
+            self.role,
+        );
+        for (currency_code, balance) in balances {
+            account_data
+                .balances
+                .insert(currency_code, Balance::new(balance));
+        }
+        account_data
+    }
+}
+
+impl Default for AccountDataBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct WithdrawCapability {
     account_address: AccountAddress,
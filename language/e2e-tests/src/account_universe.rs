@@ -13,28 +13,42 @@
 
 mod bad_transaction;
 mod create_account;
+mod failure;
+mod gas_boundary;
 mod peer_to_peer;
+mod publish_module;
 mod rotate_key;
 mod universe;
 pub use bad_transaction::*;
 pub use create_account::*;
+pub use failure::*;
+pub use gas_boundary::*;
 pub use peer_to_peer::*;
+pub use publish_module::*;
 pub use rotate_key::*;
 pub use universe::*;
 
 use crate::{
-    account::{self, lbr_currency_code, Account, AccountData},
+    account::{lbr_currency_code, Account, AccountData},
     executor::FakeExecutor,
     gas_costs, transaction_status_eq,
 };
 use libra_crypto::ed25519::{Ed25519PrivateKey, Ed25519PublicKey};
 use libra_types::{
+    account_config::{from_currency_code_string, AccountResource, LBR_NAME},
     transaction::{SignedTransaction, TransactionStatus},
     vm_status::{AbortLocation, StatusCode, VMStatus},
+    write_set::{WriteOp, WriteSet, WriteSetMut},
 };
+use move_core_types::identifier::{IdentStr, Identifier};
 use once_cell::sync::Lazy;
 use proptest::{prelude::*, strategy::Union};
-use std::{fmt, sync::Arc};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+};
 
 static UNIVERSE_SIZE: Lazy<usize> = Lazy::new(|| {
     use std::{env, process::abort};
@@ -113,27 +127,42 @@ impl AUTransactionGen for Arc<dyn AUTransactionGen> {
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct AccountCurrent {
     initial_data: AccountData,
+    // The balance in LBR, which most transactions in a universe transfer and pay gas in. This is 0
+    // if the account holds no LBR.
     balance: u64,
+    // The balances in every other currency the account holds.
+    other_balances: BTreeMap<Identifier, u64>,
     sequence_number: u64,
     sent_events_count: u64,
     received_events_count: u64,
     // creation of event counter affects gas usage in create account. This tracks it
     event_counter_created: bool,
+    // The names of the modules published under this account so far.
+    published_modules: BTreeSet<Identifier>,
 }
 
 impl AccountCurrent {
     fn new(initial_data: AccountData) -> Self {
-        let balance = initial_data.balance(&lbr_currency_code());
+        let mut other_balances: BTreeMap<_, _> = initial_data
+            .balances()
+            .into_iter()
+            .map(|(currency_code, balance)| {
+                (from_currency_code_string(&currency_code).unwrap(), balance)
+            })
+            .collect();
+        let balance = other_balances.remove(&lbr_currency_code()).unwrap_or(0);
         let sequence_number = initial_data.sequence_number();
         let sent_events_count = initial_data.sent_events_count();
         let received_events_count = initial_data.received_events_count();
         Self {
             initial_data,
             balance,
+            other_balances,
             sequence_number,
             sent_events_count,
             received_events_count,
             event_counter_created: false,
+            published_modules: BTreeSet::new(),
         }
     }
 
@@ -153,6 +182,48 @@ impl AccountCurrent {
         self.balance
     }
 
+    /// Returns the current balance for this account in `currency_code`, assuming all transactions
+    /// seen so far are applied, or `None` if the account holds no balance in that currency.
+    pub fn balance_in(&self, currency_code: &IdentStr) -> Option<u64> {
+        if currency_code == lbr_currency_code().as_ident_str() {
+            if self.holds_lbr() {
+                Some(self.balance)
+            } else {
+                None
+            }
+        } else {
+            self.other_balances.get(currency_code).copied()
+        }
+    }
+
+    fn balance_in_mut(&mut self, currency_code: &IdentStr) -> Option<&mut u64> {
+        if currency_code == lbr_currency_code().as_ident_str() {
+            if self.holds_lbr() {
+                Some(&mut self.balance)
+            } else {
+                None
+            }
+        } else {
+            self.other_balances.get_mut(currency_code)
+        }
+    }
+
+    /// Returns the currencies this account holds a balance in.
+    pub fn currencies(&self) -> Vec<Identifier> {
+        let lbr = if self.holds_lbr() {
+            Some(lbr_currency_code())
+        } else {
+            None
+        };
+        lbr.into_iter()
+            .chain(self.other_balances.keys().cloned())
+            .collect()
+    }
+
+    fn holds_lbr(&self) -> bool {
+        self.initial_data.balance_of(LBR_NAME).is_some()
+    }
+
     /// Returns the current sequence number for this account, assuming all transactions seen so far
     /// are applied.
     pub fn sequence_number(&self) -> u64 {
@@ -368,26 +439,141 @@ pub fn run_and_assert_universe(
     universe: AccountUniverseGen,
     transaction_gens: Vec<impl AUTransactionGen + Clone>,
 ) -> Result<(), TestCaseError> {
-    let mut executor = FakeExecutor::from_genesis_file();
+    run_and_assert_universe_on(
+        FakeExecutor::from_genesis_file(),
+        universe,
+        transaction_gens,
+    )
+}
+
+/// Like [`run_and_assert_universe`], but sets the universe up on `executor` instead of one started
+/// from the default genesis, e.g. to run with other on-chain configs.
+pub fn run_and_assert_universe_on(
+    mut executor: FakeExecutor,
+    universe: AccountUniverseGen,
+    transaction_gens: Vec<impl AUTransactionGen + Clone>,
+) -> Result<(), TestCaseError> {
     let mut universe = universe.setup(&mut executor);
     let (transactions, expected_values): (Vec<_>, Vec<_>) = transaction_gens
         .iter()
         .map(|transaction_gen| transaction_gen.clone().apply(&mut universe))
         .unzip();
+    let expected_statuses: Vec<_> = expected_values
+        .into_iter()
+        .map(|(status, _)| status)
+        .collect();
+    let expected_accounts = expected_accounts(&universe);
+
+    // Status mismatches panic rather than fail the case, so catch those too to save the run.
+    let mut undo = vec![];
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        run_and_assert_block(
+            &mut executor,
+            transactions.clone(),
+            &expected_statuses,
+            &expected_accounts,
+            &mut undo,
+        )
+    }));
+    if let Ok(Ok(())) = result {
+        return Ok(());
+    }
+
+    // Roll the block back to get the state the run started from.
+    for write_set in undo.into_iter().rev() {
+        executor.apply_write_set(&write_set);
+    }
+    let failure = UniverseFailure {
+        initial_state: executor.state_bytes(),
+        transactions,
+        expected_statuses,
+        expected_accounts,
+    };
+    let saved = format!(
+        "this account universe run was saved to {:?}, replay it with replay_universe_failure",
+        failure.save_for_current_test()
+    );
+    match result {
+        Ok(Err(TestCaseError::Fail(reason))) => Err(TestCaseError::fail(format!(
+            "{}\n{}",
+            reason.message(),
+            saved
+        ))),
+        Ok(result) => result,
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<String>()
+                .map(String::as_str)
+                .or_else(|| panic.downcast_ref::<&str>().copied())
+                .unwrap_or("account universe run panicked");
+            panic!("{}\n{}", message, saved)
+        }
+    }
+}
+
+/// Executes `transactions` as a block, applying their outputs, and checks the statuses and the
+/// resulting accounts against the model's.
+///
+/// For each output applied, a write set undoing it is pushed to `undo`, so that the caller can
+/// get back to the initial state if the block fails.
+fn run_and_assert_block(
+    executor: &mut FakeExecutor,
+    transactions: Vec<SignedTransaction>,
+    expected_statuses: &[TransactionStatus],
+    expected_accounts: &[ExpectedAccount],
+    undo: &mut Vec<WriteSet>,
+) -> Result<(), TestCaseError> {
     let outputs = executor.execute_block(transactions).unwrap();
 
-    prop_assert_eq!(outputs.len(), expected_values.len());
+    prop_assert_eq!(outputs.len(), expected_statuses.len());
 
-    for (idx, (output, expected)) in outputs.iter().zip(&expected_values).enumerate() {
+    for (idx, (output, expected)) in outputs.iter().zip(expected_statuses).enumerate() {
         prop_assert!(
-            transaction_status_eq(output.status(), &expected.0),
+            transaction_status_eq(output.status(), expected),
             "unexpected status for transaction {}",
             idx
         );
+        let previous = output
+            .write_set()
+            .iter()
+            .map(
+                |(access_path, _)| match executor.read_from_access_path(access_path) {
+                    Some(blob) => (access_path.clone(), WriteOp::Value(blob)),
+                    None => (access_path.clone(), WriteOp::Deletion),
+                },
+            )
+            .collect();
+        undo.push(
+            WriteSetMut::new(previous)
+                .freeze()
+                .expect("a write set undoing a valid one must be valid"),
+        );
         executor.apply_write_set(output.write_set());
     }
 
-    assert_accounts_match(&universe, &executor)
+    assert_expected_accounts_match(expected_accounts, executor)
+}
+
+/// Returns what the model expects each account in `universe` to look like on chain, assuming all
+/// transactions seen so far are applied.
+pub fn expected_accounts(universe: &AccountUniverse) -> Vec<ExpectedAccount> {
+    universe
+        .accounts()
+        .iter()
+        .map(|account| ExpectedAccount {
+            address: *account.account().address(),
+            auth_key: account.account().auth_key(),
+            sequence_number: account.sequence_number(),
+            balances: account
+                .currencies()
+                .into_iter()
+                .map(|currency_code| {
+                    let balance = account.balance_in(&currency_code).unwrap();
+                    (currency_code.to_string(), balance)
+                })
+                .collect(),
+        })
+        .collect()
 }
 
 /// Verify that the account information in the universe matches the information in the executor.
@@ -395,26 +581,34 @@ pub fn assert_accounts_match(
     universe: &AccountUniverse,
     executor: &FakeExecutor,
 ) -> Result<(), TestCaseError> {
-    for (idx, account) in universe.accounts().iter().enumerate() {
+    assert_expected_accounts_match(&expected_accounts(universe), executor)
+}
+
+/// Verify that the accounts the model expects match the information in the executor.
+pub fn assert_expected_accounts_match(
+    expected_accounts: &[ExpectedAccount],
+    executor: &FakeExecutor,
+) -> Result<(), TestCaseError> {
+    for (idx, account) in expected_accounts.iter().enumerate() {
         let resource = executor
-            .read_account_resource(&account.account())
+            .read_resource::<AccountResource>(&account.address)
             .expect("account resource must exist");
-        let resource_balance = executor
-            .read_balance_resource(account.account(), account::lbr_currency_code())
-            .expect("account balance resource must exist");
-        let auth_key = account.account().auth_key();
         prop_assert_eq!(
-            auth_key.as_slice(),
+            account.auth_key.as_slice(),
             resource.authentication_key(),
             "account {} should have correct auth key",
             idx
         );
-        prop_assert_eq!(
-            account.balance(),
-            resource_balance.coin(),
-            "account {} should have correct balance",
-            idx
-        );
+        let balances = executor.balances(&account.address);
+        for (currency_code, balance) in &account.balances {
+            prop_assert_eq!(
+                Some(balance),
+                balances.get(currency_code),
+                "account {} should have correct balance in {}",
+                idx,
+                currency_code
+            );
+        }
         // XXX These two don't work at the moment because the VM doesn't bump up event counts.
         //        prop_assert_eq!(
         //            account.received_events_count(),
@@ -429,7 +623,7 @@ pub fn assert_accounts_match(
         //            idx
         //        );
         prop_assert_eq!(
-            account.sequence_number(),
+            account.sequence_number,
             resource.sequence_number(),
             "account {} should have correct sequence number",
             idx
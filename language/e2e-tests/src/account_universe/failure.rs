@@ -0,0 +1,144 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Reproductions of failed account universe runs, to turn them into regression tests.
+//!
+//! Proptest persists the seed of a failing case, but a seed alone can't bring the case back: the
+//! accounts in a universe get their keys from the OS. So the run itself is saved instead, with the
+//! state it started from, the signed transactions and what the model expected of them.
+
+use crate::{account_universe::run_and_assert_block, executor::FakeExecutor};
+use libra_types::{
+    account_address::AccountAddress,
+    transaction::{SignedTransaction, TransactionStatus},
+};
+use proptest::test_runner::TestCaseError;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    thread,
+};
+
+/// The version of the format written by [`UniverseFailure::save`]. Bump it whenever that format
+/// changes, so that older reproductions fail to load.
+pub const UNIVERSE_FAILURE_VERSION: u32 = 1;
+
+/// The environment variable naming the directory failed runs are saved to. Defaults to an
+/// `account-universe-failures` directory in the system's temporary directory.
+pub const UNIVERSE_FAILURE_DIR: &str = "UNIVERSE_FAILURE_DIR";
+
+/// What the model expects an account in a universe to look like once a run is over.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ExpectedAccount {
+    pub address: AccountAddress,
+    pub auth_key: Vec<u8>,
+    pub sequence_number: u64,
+    /// The balance in each currency the account holds, by currency code.
+    pub balances: BTreeMap<String, u64>,
+}
+
+/// A block of transactions run against an account universe, along with the state it started from
+/// and what the model expected of it.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct UniverseFailure {
+    /// The state of the executor, universe accounts included, as returned by
+    /// [`FakeExecutor::state_bytes`].
+    pub initial_state: Vec<u8>,
+    pub transactions: Vec<SignedTransaction>,
+    pub expected_statuses: Vec<TransactionStatus>,
+    pub expected_accounts: Vec<ExpectedAccount>,
+}
+
+impl UniverseFailure {
+    /// Runs the transactions again from the saved state and checks them against the model's
+    /// expectations, the same way the property test did.
+    pub fn replay(&self) -> Result<(), TestCaseError> {
+        let mut executor = FakeExecutor::try_from_state_bytes(&self.initial_state)
+            .expect("the saved state must load");
+        run_and_assert_block(
+            &mut executor,
+            self.transactions.clone(),
+            &self.expected_statuses,
+            &self.expected_accounts,
+            &mut vec![],
+        )
+    }
+
+    /// Reads a reproduction written by [`UniverseFailure::save`]. Panics if the file can't be read
+    /// or was written in another version of the format.
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        Self::try_load(path).unwrap_or_else(|err| {
+            panic!(
+                "unable to load account universe failure {:?}: {:#}",
+                path, err
+            )
+        })
+    }
+
+    /// Reads a reproduction written by [`UniverseFailure::save`], returning an error if the file
+    /// can't be read or was written in another version of the format.
+    pub fn try_load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let bytes = fs::read(path)?;
+        // The version is encoded on its own so it can be checked before decoding the rest.
+        let (version, failure): (u32, Vec<u8>) = lcs::from_bytes(&bytes)?;
+        anyhow::ensure!(
+            version == UNIVERSE_FAILURE_VERSION,
+            "account universe failure has version {}, expected {}",
+            version,
+            UNIVERSE_FAILURE_VERSION
+        );
+        Ok(lcs::from_bytes(&failure)?)
+    }
+
+    /// Writes this reproduction to `path`.
+    pub fn save(&self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        let failure = lcs::to_bytes(self).expect("account universe failure must serialize");
+        let bytes = lcs::to_bytes(&(UNIVERSE_FAILURE_VERSION, failure))
+            .expect("account universe failure file must serialize");
+        fs::write(path, bytes).unwrap_or_else(|err| {
+            panic!(
+                "unable to write account universe failure {:?}: {}",
+                path, err
+            )
+        });
+    }
+
+    /// Saves this reproduction under [`UNIVERSE_FAILURE_DIR`], in a file named after the test
+    /// running on the current thread, and returns its path.
+    ///
+    /// Each failing case overwrites the file. Proptest shrinks a failure until no smaller case
+    /// fails, so the file ends up holding the minimal case it reports.
+    pub fn save_for_current_test(&self) -> PathBuf {
+        let dir = env::var_os(UNIVERSE_FAILURE_DIR)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| env::temp_dir().join("account-universe-failures"));
+        fs::create_dir_all(&dir).unwrap_or_else(|err| {
+            panic!(
+                "unable to create account universe failure directory {:?}: {}",
+                dir, err
+            )
+        });
+        let test_name = thread::current()
+            .name()
+            .unwrap_or("account_universe")
+            .replace("::", ".");
+        let path = dir.join(format!("{}.universe", test_name));
+        self.save(&path);
+        path
+    }
+}
+
+/// Replays the account universe run saved at `path` by a failed property test, panicking with the
+/// same error if it still fails.
+///
+/// Copy the file into the repository and call this from a `#[test]` to keep the failure as a
+/// regression test.
+pub fn replay_universe_failure(path: impl AsRef<Path>) {
+    if let Err(err) = UniverseFailure::load(path).replay() {
+        panic!("{}", err);
+    }
+}
@@ -0,0 +1,118 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    account_universe::{AUTransactionGen, AccountPair, AccountPairGen, AccountUniverse},
+    common_transactions::peer_to_peer_txn_with_max_gas,
+};
+use libra_types::{
+    transaction::{SignedTransaction, TransactionStatus},
+    vm_status::{AbortLocation, StatusCode, VMStatus},
+};
+use move_core_types::gas_schedule::{GasAlgebra, GasConstants};
+use proptest::prelude::*;
+use proptest_derive::Arbitrary;
+use std::sync::Arc;
+
+/// The largest distance from the cost of execution that [`GasBoundaryP2PGen`] sets the gas limit
+/// to, in either direction.
+pub const GAS_BOUNDARY_MAX_DELTA: u64 = 32;
+
+/// Represents a peer-to-peer transaction whose `max_gas_amount` is set just around the gas it
+/// needs to execute.
+///
+/// The gas limit is `cost - delta` if `below_cost` is set, and `cost + delta` otherwise, so that
+/// transactions either just run out of gas or just make it. The parameters are the minimum and
+/// maximum balances to transfer.
+#[derive(Arbitrary, Clone, Debug)]
+#[proptest(params = "(u64, u64)")]
+pub struct GasBoundaryP2PGen {
+    sender_receiver: AccountPairGen,
+    #[proptest(strategy = "params.0 ..= params.1")]
+    amount: u64,
+    below_cost: bool,
+    #[proptest(strategy = "0 ..= GAS_BOUNDARY_MAX_DELTA")]
+    delta: u64,
+}
+
+impl AUTransactionGen for GasBoundaryP2PGen {
+    fn apply(
+        &self,
+        universe: &mut AccountUniverse,
+    ) -> (SignedTransaction, (TransactionStatus, u64)) {
+        let AccountPair {
+            account_1: sender,
+            account_2: receiver,
+            ..
+        } = self.sender_receiver.pick(universe);
+
+        // The transfer either goes through or aborts because the balance is too low, and the two
+        // paths cost different amounts of gas.
+        let enough_to_transfer = sender.balance >= self.amount;
+        let cost = if enough_to_transfer {
+            sender.peer_to_peer_gas_cost()
+        } else {
+            sender.peer_to_peer_too_low_gas_cost()
+        };
+        let max_gas_amount = if self.below_cost {
+            // Going below the minimum would get the transaction discarded before it runs.
+            let min_gas = GasConstants::default().min_transaction_gas_units.get();
+            cost.saturating_sub(self.delta.max(1)).max(min_gas)
+        } else {
+            cost + self.delta
+        };
+
+        let txn = peer_to_peer_txn_with_max_gas(
+            sender.account(),
+            receiver.account(),
+            sender.sequence_number,
+            self.amount,
+            max_gas_amount,
+        );
+
+        // A transaction that runs out of gas is kept and charged for all the gas it was given, but
+        // no other changes happen.
+        let status;
+        let gas_used;
+        if max_gas_amount < cost {
+            sender.sequence_number += 1;
+            gas_used = max_gas_amount;
+            sender.balance -= gas_used * txn.gas_unit_price();
+            status = TransactionStatus::Keep(VMStatus::Error(StatusCode::OUT_OF_GAS));
+        } else if enough_to_transfer && sender.balance >= self.amount + cost * txn.gas_unit_price()
+        {
+            sender.sequence_number += 1;
+            sender.sent_events_count += 1;
+            gas_used = cost;
+            sender.balance -= self.amount + gas_used * txn.gas_unit_price();
+
+            receiver.balance += self.amount;
+            receiver.received_events_count += 1;
+
+            status = TransactionStatus::Keep(VMStatus::Executed);
+        } else if enough_to_transfer {
+            // Enough to do the transfer, but not enough to pay for gas in the epilogue.
+            sender.sequence_number += 1;
+            gas_used = cost;
+            sender.balance -= gas_used * txn.gas_unit_price();
+            status = TransactionStatus::Keep(VMStatus::MoveAbort(AbortLocation::Script, 6));
+        } else {
+            // 5 means the balance was insufficient while trying to transfer.
+            sender.sequence_number += 1;
+            gas_used = cost;
+            sender.balance -= gas_used * txn.gas_unit_price();
+            status = TransactionStatus::Keep(VMStatus::MoveAbort(AbortLocation::Script, 5));
+        }
+
+        (txn, (status, gas_used))
+    }
+}
+
+/// Returns a [`Strategy`] that generates peer-to-peer transactions with gas limits clustered
+/// around the cost of executing them, transferring between `min` and `max`.
+pub fn gas_boundary_strategy(
+    min: u64,
+    max: u64,
+) -> impl Strategy<Value = Arc<dyn AUTransactionGen + 'static>> {
+    any_with::<GasBoundaryP2PGen>((min, max)).prop_map(GasBoundaryP2PGen::arced)
+}
@@ -3,8 +3,9 @@
 
 use crate::{
     account_universe::{AUTransactionGen, AccountPair, AccountPairGen, AccountUniverse},
-    common_transactions::peer_to_peer_txn,
+    common_transactions::{peer_to_peer_txn, peer_to_peer_txn_for_currency},
 };
+use libra_proptest_helpers::Index;
 use libra_types::{
     transaction::{SignedTransaction, TransactionStatus},
     vm_status::{AbortLocation, StatusCode, VMStatus},
@@ -103,6 +104,68 @@ impl AUTransactionGen for P2PTransferGen {
     }
 }
 
+/// Represents a peer-to-peer transaction in a currency picked from the ones the sender holds, in a
+/// universe set up with [`AccountUniverseGen::multi_currency_strategy`].
+///
+/// The parameters are the minimum and maximum balances to transfer. The gas these transfers use
+/// isn't modeled, so they aren't suitable for gas cost stability tests.
+///
+/// [`AccountUniverseGen::multi_currency_strategy`]:
+/// crate::account_universe::AccountUniverseGen::multi_currency_strategy
+#[derive(Arbitrary, Clone, Debug)]
+#[proptest(params = "(u64, u64)")]
+pub struct MultiCurrencyP2PTransferGen {
+    sender_receiver: AccountPairGen,
+    currency: Index,
+    #[proptest(strategy = "params.0 ..= params.1")]
+    amount: u64,
+}
+
+impl AUTransactionGen for MultiCurrencyP2PTransferGen {
+    fn apply(
+        &self,
+        universe: &mut AccountUniverse,
+    ) -> (SignedTransaction, (TransactionStatus, u64)) {
+        let AccountPair {
+            account_1: sender,
+            account_2: receiver,
+            ..
+        } = self.sender_receiver.pick(universe);
+
+        let currencies = sender.currencies();
+        let currency_code = &currencies[self.currency.index(currencies.len())];
+        let txn = peer_to_peer_txn_for_currency(
+            sender.account(),
+            receiver.account(),
+            sender.sequence_number,
+            self.amount,
+            currency_code,
+        );
+
+        // Gas is free for these transactions, so they always get through the prologue and the
+        // sender's sequence number is bumped whatever happens next.
+        sender.sequence_number += 1;
+        let sender_balance = sender
+            .balance_in_mut(currency_code)
+            .expect("the currency was picked from the ones the sender holds");
+        let status = if *sender_balance < self.amount {
+            // The withdrawal from the sender fails first.
+            TransactionStatus::Keep(VMStatus::MoveAbort(AbortLocation::Script, 5))
+        } else if let Some(receiver_balance) = receiver.balance_in_mut(currency_code) {
+            *sender_balance -= self.amount;
+            sender.sent_events_count += 1;
+            *receiver_balance += self.amount;
+            receiver.received_events_count += 1;
+            TransactionStatus::Keep(VMStatus::Executed)
+        } else {
+            // The receiver has no balance to deposit the coins into.
+            TransactionStatus::Keep(VMStatus::Error(StatusCode::MISSING_DATA))
+        };
+
+        (txn, (status, 0))
+    }
+}
+
 pub fn p2p_strategy(
     min: u64,
     max: u64,
@@ -111,3 +174,15 @@ pub fn p2p_strategy(
         3 => any_with::<P2PTransferGen>((min, max)).prop_map(P2PTransferGen::arced),
     ]
 }
+
+/// A strategy that returns peer-to-peer transfers in random currencies, for universes set up with
+/// [`AccountUniverseGen::multi_currency_strategy`].
+///
+/// [`AccountUniverseGen::multi_currency_strategy`]:
+/// crate::account_universe::AccountUniverseGen::multi_currency_strategy
+pub fn multi_currency_p2p_strategy(
+    min: u64,
+    max: u64,
+) -> impl Strategy<Value = Arc<dyn AUTransactionGen + 'static>> {
+    any_with::<MultiCurrencyP2PTransferGen>((min, max)).prop_map(MultiCurrencyP2PTransferGen::arced)
+}
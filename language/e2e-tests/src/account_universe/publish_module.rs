@@ -0,0 +1,111 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    account_universe::{AUTransactionGen, AccountUniverse},
+    compile::compile_module_with_address,
+    gas_costs,
+};
+use libra_proptest_helpers::Index;
+use libra_types::{
+    account_config::LBR_NAME,
+    transaction::{SignedTransaction, TransactionStatus},
+    vm_status::{StatusCode, VMStatus},
+};
+use move_core_types::identifier::Identifier;
+use proptest::{collection::vec, prelude::*, sample::select};
+use proptest_derive::Arbitrary;
+use std::sync::Arc;
+
+/// Represents a module publishing transaction performed in the account universe.
+///
+/// The module is generated from a small template: a struct with a few fields and a few public
+/// functions returning constants. Names are picked from a small pool, so that accounts regularly
+/// try to publish a module under a name they already used.
+///
+/// This needs an executor set up with `VMPublishingOption::open()`. The gas these transactions
+/// use isn't modeled, so they aren't suitable for gas cost stability tests.
+#[derive(Arbitrary, Clone, Debug)]
+#[proptest(no_params)]
+pub struct PublishModuleGen {
+    sender: Index,
+    #[proptest(strategy = "0u8..4")]
+    name: u8,
+    #[proptest(strategy = "vec(field_type_strategy(), 1..4)")]
+    field_types: Vec<&'static str>,
+    #[proptest(strategy = "vec(any::<u64>(), 0..4)")]
+    constants: Vec<u64>,
+}
+
+fn field_type_strategy() -> impl Strategy<Value = &'static str> {
+    select(&["u64", "bool", "address", "vector<u8>"][..])
+}
+
+impl PublishModuleGen {
+    fn module_name(&self) -> Identifier {
+        Identifier::new(format!("M{}", self.name)).unwrap()
+    }
+
+    fn module_code(&self) -> String {
+        let fields: Vec<_> = self
+            .field_types
+            .iter()
+            .enumerate()
+            .map(|(idx, field_type)| format!("f{}: {}", idx, field_type))
+            .collect();
+        let functions: String = self
+            .constants
+            .iter()
+            .enumerate()
+            .map(|(idx, constant)| format!("public c{}(): u64 {{ return {}; }}\n", idx, constant))
+            .collect();
+        format!(
+            "module {} {{\nstruct S {{ {} }}\n{}}}",
+            self.module_name(),
+            fields.join(", "),
+            functions,
+        )
+    }
+}
+
+impl AUTransactionGen for PublishModuleGen {
+    fn apply(
+        &self,
+        universe: &mut AccountUniverse,
+    ) -> (SignedTransaction, (TransactionStatus, u64)) {
+        let sender = universe.pick(self.sender).1;
+
+        let module = compile_module_with_address(
+            sender.account().address(),
+            "file_name",
+            &self.module_code(),
+        );
+        let txn = sender.account().create_signed_txn_impl(
+            *sender.account().address(),
+            module,
+            sender.sequence_number,
+            gas_costs::TXN_RESERVED,
+            0,
+            LBR_NAME.to_owned(),
+        );
+
+        // Gas is free for these transactions, so they always get through the prologue. The
+        // transaction is kept even if the module can't be published.
+        sender.sequence_number += 1;
+        let status = if sender.published_modules.insert(self.module_name()) {
+            TransactionStatus::Keep(VMStatus::Executed)
+        } else {
+            TransactionStatus::Keep(VMStatus::Error(StatusCode::DUPLICATE_MODULE_NAME))
+        };
+
+        (txn, (status, 0))
+    }
+}
+
+/// A strategy that returns module publishing transactions. The universe must be run on an
+/// executor that allows publishing, e.g. through [`run_and_assert_universe_on`].
+///
+/// [`run_and_assert_universe_on`]: crate::account_universe::run_and_assert_universe_on
+pub fn publish_module_strategy() -> impl Strategy<Value = Arc<dyn AUTransactionGen + 'static>> {
+    any::<PublishModuleGen>().prop_map(PublishModuleGen::arced)
+}
@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    account_universe::{AUTransactionGen, AccountUniverse},
+    account_universe::{p2p_strategy, AUTransactionGen, AccountUniverse},
     common_transactions::rotate_key_txn,
     gas_costs,
 };
@@ -17,6 +17,7 @@ use libra_types::{
 };
 use proptest::prelude::*;
 use proptest_derive::Arbitrary;
+use std::sync::Arc;
 
 /// Represents a rotate-key transaction performed in the account universe.
 #[derive(Arbitrary, Clone, Debug)]
@@ -59,3 +60,17 @@ impl AUTransactionGen for RotateKeyGen {
         (txn, (status, gas_used))
     }
 }
+
+/// A strategy that interleaves key rotations with peer-to-peer transfers.
+///
+/// Transfers sent after a rotation are signed with the sender's new key, so this checks that the
+/// rotated key is the one the VM accepts from then on.
+pub fn rotate_key_strategy(
+    min: u64,
+    max: u64,
+) -> impl Strategy<Value = Arc<dyn AUTransactionGen + 'static>> {
+    prop_oneof![
+        1 => any::<RotateKeyGen>().prop_map(RotateKeyGen::arced),
+        1 => p2p_strategy(min, max),
+    ]
+}
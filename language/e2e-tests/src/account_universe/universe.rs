@@ -71,6 +71,26 @@ impl AccountUniverseGen {
         })
     }
 
+    /// Returns a [`Strategy`] that generates a universe of accounts which each hold a random mix of
+    /// currencies, with pre-populated initial balances in each of them.
+    ///
+    /// Transactions in such a universe should pick their currency per transaction, e.g. through
+    /// [`multi_currency_p2p_strategy`][crate::account_universe::multi_currency_p2p_strategy]:
+    /// generators that assume every account holds LBR don't model it correctly.
+    pub fn multi_currency_strategy(
+        num_accounts: impl Into<SizeRange>,
+        balance_strategy: impl Strategy<Value = u64>,
+    ) -> impl Strategy<Value = Self> {
+        vec(
+            AccountData::multi_currency_strategy(balance_strategy),
+            num_accounts,
+        )
+        .prop_map(|accounts| Self {
+            accounts,
+            pick_style: AccountPickStyle::Unlimited,
+        })
+    }
+
     /// Returns a [`Strategy`] that generates a universe of accounts that's guaranteed to succeed,
     /// assuming that any transfers out of accounts will be 100_000 or below.
     pub fn success_strategy(min_accounts: usize) -> impl Strategy<Value = Self> {
@@ -3,17 +3,32 @@
 
 //! Support for encoding transactions for common situations.
 
-use crate::{account::Account, gas_costs};
+use crate::{
+    account::Account,
+    compile::{try_compile_module_with_address, try_script_abi},
+    gas_costs,
+};
 use compiled_stdlib::transaction_scripts::StdlibScript;
 use compiler::Compiler;
+use libra_crypto::{ed25519::Ed25519PrivateKey, traits::SigningKey, HashValue};
 use libra_types::{
     account_address::AccountAddress,
     account_config,
     account_config::{lbr_type_tag, LBR_NAME},
-    transaction::{RawTransaction, SignedTransaction, TransactionArgument},
+    on_chain_config::VMPublishingOption,
+    transaction::{
+        ChangeSet, Module, RawTransaction, Script, SignedTransaction, TransactionArgument,
+        TransactionPayload, SCRIPT_HASH_LENGTH,
+    },
+    write_set::WriteSet,
 };
-use move_core_types::language_storage::TypeTag;
+use move_core_types::{identifier::IdentStr, language_storage::TypeTag};
 use once_cell::sync::Lazy;
+use std::collections::HashSet;
+use transaction_builder::{
+    encode_modify_publishing_option_script, encode_peer_to_peer_with_metadata_script,
+};
+use vm::file_format::CompiledScript;
 
 pub static CREATE_ACCOUNT_SCRIPT: Lazy<Vec<u8>> = Lazy::new(|| {
     let code = "
@@ -197,6 +212,24 @@ pub fn peer_to_peer_txn(
     receiver: &Account,
     seq_num: u64,
     transfer_amount: u64,
+) -> SignedTransaction {
+    peer_to_peer_txn_with_max_gas(
+        sender,
+        receiver,
+        seq_num,
+        transfer_amount,
+        gas_costs::TXN_RESERVED,
+    )
+}
+
+/// Returns a transaction to transfer coin from one account to another (possibly new) one, with
+/// the given gas limit instead of the default one.
+pub fn peer_to_peer_txn_with_max_gas(
+    sender: &Account,
+    receiver: &Account,
+    seq_num: u64,
+    transfer_amount: u64,
+    max_gas_amount: u64,
 ) -> SignedTransaction {
     let mut args: Vec<TransactionArgument> = Vec::new();
     args.push(TransactionArgument::Address(*receiver.address()));
@@ -212,12 +245,418 @@ pub fn peer_to_peer_txn(
         vec![lbr_type_tag()],
         args,
         seq_num,
-        gas_costs::TXN_RESERVED, // this is a default for gas
-        0,                       // this is a default for gas
+        max_gas_amount,
+        0, // this is a default for gas
+        LBR_NAME.to_owned(),
+    )
+}
+
+/// Returns a transaction to transfer `transfer_amount` of the currency `currency_code` from one
+/// account to another. Gas is also paid in that currency, so the sender doesn't need to hold LBR.
+pub fn peer_to_peer_txn_for_currency(
+    sender: &Account,
+    receiver: &Account,
+    seq_num: u64,
+    transfer_amount: u64,
+    currency_code: &IdentStr,
+) -> SignedTransaction {
+    let args = vec![
+        TransactionArgument::Address(*receiver.address()),
+        TransactionArgument::U64(transfer_amount),
+        TransactionArgument::U8Vector(vec![]),
+        TransactionArgument::U8Vector(vec![]),
+    ];
+
+    sender.create_signed_txn_with_args(
+        StdlibScript::PeerToPeerWithMetadata
+            .compiled_bytes()
+            .into_vec(),
+        vec![account_config::type_tag_for_currency_code(
+            currency_code.to_owned(),
+        )],
+        args,
+        seq_num,
+        gas_costs::TXN_RESERVED,
+        0, // this is a default for gas
+        currency_code.to_string(),
+    )
+}
+
+/// The domain separator used in dual attestation messages, as defined in `DualAttestation.move`.
+const DUAL_ATTESTATION_DOMAIN_SEPARATOR: &[u8] = b"@@$$LIBRA_ATTEST$$@@";
+
+/// Returns the message a payee must sign for a payment to pass dual attestation, i.e.
+/// `metadata | lcs(payer) | lcs(amount) | DOMAIN_SEPARATOR`.
+pub fn dual_attestation_message(payer: &AccountAddress, metadata: &[u8], amount: u64) -> Vec<u8> {
+    let mut message = metadata.to_vec();
+    message.append(&mut lcs::to_bytes(payer).unwrap());
+    message.append(&mut lcs::to_bytes(&amount).unwrap());
+    message.extend_from_slice(DUAL_ATTESTATION_DOMAIN_SEPARATOR);
+    message
+}
+
+/// Returns a transaction to transfer Coin1 from one account to another with the given metadata,
+/// attested with a signature from the receiver's compliance key.
+pub fn p2p_with_metadata_and_signature(
+    sender: &Account,
+    receiver: &Account,
+    seq_num: u64,
+    amount: u64,
+    metadata: Vec<u8>,
+    compliance_private_key: &Ed25519PrivateKey,
+) -> SignedTransaction {
+    let message = dual_attestation_message(sender.address(), &metadata, amount);
+    p2p_with_metadata_and_signed_message(
+        sender,
+        receiver,
+        seq_num,
+        amount,
+        metadata,
+        compliance_private_key,
+        &message,
+    )
+}
+
+/// Like [`p2p_with_metadata_and_signature`], but the signature attests to a different amount. The
+/// signature is well formed but doesn't match the payment, so the transfer should abort.
+pub fn p2p_with_metadata_and_wrong_signature(
+    sender: &Account,
+    receiver: &Account,
+    seq_num: u64,
+    amount: u64,
+    metadata: Vec<u8>,
+    compliance_private_key: &Ed25519PrivateKey,
+) -> SignedTransaction {
+    let message = dual_attestation_message(sender.address(), &metadata, amount.wrapping_add(1));
+    p2p_with_metadata_and_signed_message(
+        sender,
+        receiver,
+        seq_num,
+        amount,
+        metadata,
+        compliance_private_key,
+        &message,
+    )
+}
+
+fn p2p_with_metadata_and_signed_message(
+    sender: &Account,
+    receiver: &Account,
+    seq_num: u64,
+    amount: u64,
+    metadata: Vec<u8>,
+    compliance_private_key: &Ed25519PrivateKey,
+    message: &[u8],
+) -> SignedTransaction {
+    let signature = compliance_private_key.sign_arbitrary_message(message);
+    peer_to_peer_with_metadata_txn(
+        sender,
+        receiver,
+        seq_num,
+        amount,
+        metadata,
+        signature.to_bytes().to_vec(),
+    )
+}
+
+/// Returns a transaction to transfer Coin1 from one account to another with the given metadata
+/// and raw metadata signature, which is passed to the script as is.
+///
+/// Use this to build payments with signatures that [`p2p_with_metadata_and_signature`] wouldn't
+/// produce, e.g. empty or truncated ones.
+pub fn peer_to_peer_with_metadata_txn(
+    sender: &Account,
+    receiver: &Account,
+    seq_num: u64,
+    amount: u64,
+    metadata: Vec<u8>,
+    metadata_signature: Vec<u8>,
+) -> SignedTransaction {
+    sender.signed_script_txn(
+        encode_peer_to_peer_with_metadata_script(
+            account_config::coin1_tag(),
+            *receiver.address(),
+            amount,
+            metadata,
+            metadata_signature,
+        ),
+        seq_num,
+    )
+}
+
+/// Returns a transaction from `sender` running `script` with the given type arguments and
+/// arguments. Panics if they don't match what the script takes, see [`try_script_txn`].
+pub fn script_txn(
+    sender: &Account,
+    script: Vec<u8>,
+    ty_args: Vec<TypeTag>,
+    args: Vec<TransactionArgument>,
+    seq_num: u64,
+) -> SignedTransaction {
+    try_script_txn(sender, script, ty_args, args, seq_num)
+        .unwrap_or_else(|err| panic!("invalid script transaction: {}", err))
+}
+
+/// Returns a transaction from `sender` running `script` with the given type arguments and
+/// arguments, which `From` conversions such as `TransactionArgument::from(42u64)` help build.
+///
+/// If `script` deserializes, the number of type arguments and the number and types of the
+/// arguments are checked against its [ABI][crate::compile::script_abi], returning an error on a
+/// mismatch, or if the script takes an argument no transaction can pass, instead of letting the VM
+/// reject the transaction. Scripts that don't deserialize are left for the VM to reject.
+pub fn try_script_txn(
+    sender: &Account,
+    script: Vec<u8>,
+    ty_args: Vec<TypeTag>,
+    args: Vec<TransactionArgument>,
+    seq_num: u64,
+) -> anyhow::Result<SignedTransaction> {
+    if CompiledScript::deserialize(&script).is_ok() {
+        let abi = try_script_abi(&script)?;
+        anyhow::ensure!(
+            ty_args.len() == abi.ty_args().len(),
+            "script takes {} type arguments, got {}",
+            abi.ty_args().len(),
+            ty_args.len()
+        );
+        anyhow::ensure!(
+            args.len() == abi.args().len(),
+            "script takes {} arguments, got {}",
+            abi.args().len(),
+            args.len()
+        );
+        for (idx, (arg, expected)) in args.iter().zip(abi.args()).enumerate() {
+            let actual = match arg {
+                TransactionArgument::U8(_) => TypeTag::U8,
+                TransactionArgument::U64(_) => TypeTag::U64,
+                TransactionArgument::U128(_) => TypeTag::U128,
+                TransactionArgument::Address(_) => TypeTag::Address,
+                TransactionArgument::U8Vector(_) => TypeTag::Vector(Box::new(TypeTag::U8)),
+                TransactionArgument::Bool(_) => TypeTag::Bool,
+            };
+            anyhow::ensure!(
+                &actual == expected.type_tag(),
+                "argument {} of the script has type {}, got {:?}",
+                idx,
+                expected.type_tag(),
+                arg
+            );
+        }
+    }
+    Ok(sender
+        .transaction()
+        .script(Script::new(script, ty_args, args))
+        .sequence_number(seq_num)
+        .sign())
+}
+
+/// Bytes that don't deserialize to any Move binary: they don't even start with the binary magic.
+const MALFORMED_CODE: &[u8] = b"this is not a move binary";
+
+/// Returns a transaction whose payload is a script that cannot be deserialized.
+pub fn malformed_script_txn(sender: &Account, seq_num: u64) -> SignedTransaction {
+    malformed_payload_txn(
+        sender,
+        seq_num,
+        TransactionPayload::Script(Script::new(MALFORMED_CODE.to_vec(), vec![], vec![])),
+    )
+}
+
+/// Returns a transaction whose payload is a module that cannot be deserialized.
+pub fn malformed_module_txn(sender: &Account, seq_num: u64) -> SignedTransaction {
+    malformed_payload_txn(
+        sender,
+        seq_num,
+        TransactionPayload::Module(Module::new(MALFORMED_CODE.to_vec())),
+    )
+}
+
+/// Returns a transaction from `sender` applying `write_set` to the state as is, without running
+/// any Move code. Only the libra root account is allowed to send one.
+pub fn write_set_txn(sender: &Account, write_set: WriteSet, seq_num: u64) -> SignedTransaction {
+    sender.create_signed_txn_impl(
+        *sender.address(),
+        TransactionPayload::WriteSet(ChangeSet::new(write_set, vec![])),
+        seq_num,
+        gas_costs::TXN_RESERVED,
+        0,
         LBR_NAME.to_owned(),
     )
 }
 
+/// Returns a transaction from `sender` publishing a module whose size is padded so that the
+/// transaction is exactly `target_bytes` long, as counted against the
+/// `max_transaction_size_in_bytes` gas constant.
+///
+/// The module is a valid `Padding` module holding a byte string constant, so a transaction under
+/// the limit publishes it. Panics if `target_bytes` is smaller than such a transaction can be.
+pub fn oversized_module_txn(
+    sender: &Account,
+    seq_num: u64,
+    target_bytes: usize,
+) -> SignedTransaction {
+    // Growing the constant can grow its length prefix, and that of the module, by a byte too,
+    // which may step over the target. The module name is then lengthened, which shifts the size
+    // by exactly one byte, and the padding aimed again.
+    let mut name_padding = 0;
+    loop {
+        let mut tried = HashSet::new();
+        let mut padding = 0;
+        while tried.insert(padding) {
+            let txn = padded_module_txn(sender, seq_num, name_padding, padding);
+            let size = txn.raw_txn_bytes_len();
+            if size == target_bytes {
+                return txn;
+            } else if size < target_bytes {
+                padding += target_bytes - size;
+            } else {
+                assert!(
+                    padding > 0 || name_padding > 0,
+                    "a module publish takes at least {} bytes, can't make one of {}",
+                    size,
+                    target_bytes
+                );
+                padding = padding.saturating_sub(size - target_bytes);
+            }
+        }
+        name_padding += 1;
+    }
+}
+
+fn padded_module_txn(
+    sender: &Account,
+    seq_num: u64,
+    name_padding: usize,
+    padding: usize,
+) -> SignedTransaction {
+    let code = format!(
+        "
+        module Padding{} {{
+            public padding(): vector<u8> {{
+                return h\"{}\";
+            }}
+        }}
+        ",
+        "X".repeat(name_padding),
+        "00".repeat(padding),
+    );
+    let module = try_compile_module_with_address(sender.address(), "file_name", &code)
+        .unwrap_or_else(|diagnostics| panic!("Module compilation failed: {:#?}", diagnostics));
+    sender.create_signed_txn_impl(
+        *sender.address(),
+        TransactionPayload::Module(Module::new(module)),
+        seq_num,
+        gas_costs::TXN_RESERVED,
+        0,
+        LBR_NAME.to_owned(),
+    )
+}
+
+fn malformed_payload_txn(
+    sender: &Account,
+    seq_num: u64,
+    payload: TransactionPayload,
+) -> SignedTransaction {
+    sender.create_signed_txn_impl(
+        *sender.address(),
+        payload,
+        seq_num,
+        gas_costs::TXN_RESERVED,
+        0,
+        LBR_NAME.to_owned(),
+    )
+}
+
+/// Builds a sequence of transactions from one account, numbering them one after the other.
+///
+/// Created by [`Account::txn_builder`]. Every transaction built takes the next sequence number, so
+/// as long as they all run in order and are kept, even if they abort, the sender's sequence number
+/// on chain ends up matching [`AccountTxnBuilder::next_sequence_number`]. A discarded transaction
+/// doesn't bump it, so the transactions built after it are discarded too.
+#[derive(Debug)]
+pub struct AccountTxnBuilder<'a> {
+    sender: &'a Account,
+    sequence_number: u64,
+}
+
+impl<'a> AccountTxnBuilder<'a> {
+    pub(crate) fn new(sender: &'a Account, starting_sequence_number: u64) -> Self {
+        Self {
+            sender,
+            sequence_number: starting_sequence_number,
+        }
+    }
+
+    /// Returns the sequence number the next transaction built will have.
+    pub fn next_sequence_number(&self) -> u64 {
+        self.sequence_number
+    }
+
+    /// Returns a transaction transferring `amount` LBR to `receiver`, see [`peer_to_peer_txn`].
+    pub fn peer_to_peer(&mut self, receiver: &Account, amount: u64) -> SignedTransaction {
+        let seq_num = self.bump();
+        peer_to_peer_txn(self.sender, receiver, seq_num, amount)
+    }
+
+    /// Returns a transaction publishing the serialized module `module`.
+    pub fn publish_module(&mut self, module: Vec<u8>) -> SignedTransaction {
+        self.payload(TransactionPayload::Module(Module::new(module)))
+    }
+
+    /// Returns a transaction running `script`, see [`script_txn`].
+    pub fn script(
+        &mut self,
+        script: Vec<u8>,
+        ty_args: Vec<TypeTag>,
+        args: Vec<TransactionArgument>,
+    ) -> SignedTransaction {
+        let seq_num = self.bump();
+        script_txn(self.sender, script, ty_args, args, seq_num)
+    }
+
+    /// Returns a transaction with the given payload and the default gas parameters.
+    pub fn payload(&mut self, payload: TransactionPayload) -> SignedTransaction {
+        let seq_num = self.bump();
+        self.sender
+            .transaction()
+            .payload(payload)
+            .sequence_number(seq_num)
+            .sign()
+    }
+
+    fn bump(&mut self) -> u64 {
+        let seq_num = self.sequence_number;
+        self.sequence_number += 1;
+        seq_num
+    }
+}
+
+/// Returns the hash identifying a script in the script allow list.
+pub fn script_allow_list_hash(script: &[u8]) -> [u8; SCRIPT_HASH_LENGTH] {
+    *HashValue::sha3_256_of(script).as_ref()
+}
+
+/// Returns a transaction from the libra root account allowing `script_hash` to run, through the
+/// `modify_publishing_option` governance script.
+///
+/// That script replaces the publishing option as a whole, so the scripts already allowed must be
+/// passed in as `allow_list` to be kept.
+pub fn add_to_script_allow_list_txn(
+    libra_root: &Account,
+    allow_list: &[[u8; SCRIPT_HASH_LENGTH]],
+    script_hash: [u8; SCRIPT_HASH_LENGTH],
+    seq_num: u64,
+) -> SignedTransaction {
+    let mut allow_list = allow_list.to_vec();
+    allow_list.push(script_hash);
+    let publishing_option = lcs::to_bytes(&VMPublishingOption::locked(allow_list))
+        .expect("publishing option must serialize");
+    libra_root.signed_script_txn(
+        encode_modify_publishing_option_script(publishing_option),
+        seq_num,
+    )
+}
+
 /// Returns a transaction to set config for a candidate validator
 pub fn set_validator_config_txn(
     sender_operator_account: &Account,
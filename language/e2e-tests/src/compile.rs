@@ -4,12 +4,66 @@
 //! Support for compiling scripts and modules in tests.
 
 use compiler::Compiler;
+use ir_to_bytecode::parser::{parse_module, InvalidTokenError};
 
 use libra_types::{
     account_address::AccountAddress,
-    transaction::{Module, Script, TransactionPayload},
+    account_config,
+    transaction::{
+        ArgumentABI, Module, Script, ScriptABI, TransactionArgument, TransactionPayload,
+        TypeArgumentABI,
+    },
 };
-use vm::CompiledModule;
+use move_core_types::{
+    identifier::Identifier,
+    language_storage::{ModuleId, TypeTag},
+};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fs, path::Path};
+use vm::{
+    access::{ModuleAccess, ScriptAccess},
+    file_format::{
+        CompiledScript, Kind, SignatureToken, StructFieldInformation, StructHandleIndex,
+    },
+    CompiledModule,
+};
+
+/// An error reported while compiling Move code in a test.
+///
+/// The IR compiler only reports where an error is for the tokens its parser can't parse, so the
+/// line and column are `None` for every other error.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    pub file_name: String,
+    pub message: String,
+    /// The line of the error in the source, starting at 1.
+    pub line: Option<usize>,
+    /// The column of the error in its line, starting at 1.
+    pub column: Option<usize>,
+}
+
+impl Diagnostic {
+    fn new(file_name: &str, message: String, position: Option<(usize, usize)>) -> Self {
+        Self {
+            file_name: file_name.to_string(),
+            message,
+            line: position.map(|(line, _)| line),
+            column: position.map(|(_, column)| column),
+        }
+    }
+}
+
+/// Returns the line and column in `code` of the token `error` reports, if it's a parser error
+/// for an invalid token.
+fn error_position(code: &str, error: &(dyn Error + 'static)) -> Option<(usize, usize)> {
+    let location = error.downcast_ref::<InvalidTokenError>()?.location;
+    let before = &code[..location.span().start().to_usize()];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    Some((
+        before.matches('\n').count() + 1,
+        before.len() - line_start + 1,
+    ))
+}
 
 /// Compile the provided Move code into a blob which can be used as the code to be published
 /// (a Module).
@@ -18,15 +72,112 @@ pub fn compile_module_with_address(
     file_name: &str,
     code: &str,
 ) -> TransactionPayload {
+    let blob = try_compile_module_with_address(address, file_name, code)
+        .unwrap_or_else(|diagnostics| panic!("Module compilation failed: {:#?}", diagnostics));
+    TransactionPayload::Module(Module::new(blob))
+}
+
+/// Compile the provided Move code into a module blob, returning the compiler's errors instead of
+/// panicking if it doesn't compile.
+///
+/// There is one diagnostic per error in the chain reported by the compiler, outermost first.
+pub fn try_compile_module_with_address(
+    address: &AccountAddress,
+    file_name: &str,
+    code: &str,
+) -> Result<Vec<u8>, Vec<Diagnostic>> {
     let compiler = Compiler {
         address: *address,
         ..Compiler::default()
     };
-    TransactionPayload::Module(Module::new(
-        compiler
-            .into_module_blob(file_name, code)
-            .expect("Module compilation failed"),
-    ))
+    compiler.into_module_blob(file_name, code).map_err(|error| {
+        error
+            .chain()
+            .map(|cause| Diagnostic::new(file_name, cause.to_string(), error_position(code, cause)))
+            .collect()
+    })
+}
+
+/// Compile the Move code in the file at `path` into a blob which can be used as the code to be
+/// published (a Module).
+///
+/// Panics if the file can't be read or doesn't compile, naming the file either way. The IR
+/// compiler reports errors without source locations, so the path is all a diagnostic points to.
+pub fn compile_module_from_file(
+    address: &AccountAddress,
+    path: impl AsRef<Path>,
+) -> TransactionPayload {
+    let path = path.as_ref();
+    let code = read_source(path);
+    let blob = try_compile_module_with_address(address, &path.display().to_string(), &code)
+        .unwrap_or_else(|diagnostics| panic!("Module compilation failed: {:#?}", diagnostics));
+    TransactionPayload::Module(Module::new(blob))
+}
+
+/// Compile the Move code in the file at `path` into a blob which can be used as the code to be
+/// executed (a Script), like [`compile_script_with_address`].
+///
+/// Panics if the file can't be read or doesn't compile, naming the file either way.
+pub fn compile_script_from_file(
+    address: &AccountAddress,
+    path: impl AsRef<Path>,
+    extra_deps: Vec<CompiledModule>,
+) -> TransactionPayload {
+    let path = path.as_ref();
+    let code = read_source(path);
+    let compiler = Compiler {
+        address: *address,
+        extra_deps,
+        ..Compiler::default()
+    };
+    let blob = compiler
+        .into_script_blob(&path.display().to_string(), &code)
+        .unwrap_or_else(|error| panic!("Script compilation failed for {:?}: {:#}", path, error));
+    TransactionPayload::Script(Script::new(blob, vec![], vec![]))
+}
+
+fn read_source(path: &Path) -> String {
+    fs::read_to_string(path)
+        .unwrap_or_else(|error| panic!("unable to read Move source {:?}: {}", path, error))
+}
+
+/// Compile two versions of the module `name` published at `address`, to test republishing it.
+///
+/// Panics if either version doesn't compile, or doesn't define a module called `name`: the
+/// second version would then publish a new module instead of upgrading the first one.
+pub fn compile_module_upgrade(
+    address: &AccountAddress,
+    name: &str,
+    old_src: &str,
+    new_src: &str,
+) -> (TransactionPayload, TransactionPayload) {
+    let compile = |version: &str, code: &str| {
+        let compiler = Compiler {
+            address: *address,
+            ..Compiler::default()
+        };
+        let module = compiler
+            .into_compiled_module(name, code)
+            .unwrap_or_else(|error| {
+                panic!(
+                    "{} version of {} failed to compile: {:#}",
+                    version, name, error
+                )
+            });
+        assert_eq!(
+            module.self_id().name().as_str(),
+            name,
+            "{} version of {} defines another module",
+            version,
+            name
+        );
+        let mut blob = vec![];
+        module
+            .serialize(&mut blob)
+            .expect("serializing this module should work");
+        TransactionPayload::Module(Module::new(blob))
+    };
+    (compile("old", old_src), compile("new", new_src))
 }
 
 /// Compile the provided Move code into a blob which can be used as the code to be executed
@@ -50,3 +201,431 @@ pub fn compile_script_with_address(
         vec![],
     ))
 }
+
+/// Compile a chain of `length` modules under `address`, in which each module `M<i>` imports and
+/// calls into `M<i - 1>`.
+///
+/// The modules are returned in dependency order, so they can be published one after the other.
+pub fn module_chain(address: &AccountAddress, length: usize) -> Vec<CompiledModule> {
+    let mut modules: Vec<CompiledModule> = Vec::with_capacity(length);
+    for idx in 0..length {
+        let code = if idx == 0 {
+            String::from(
+                "
+        module M0 {
+            public f() {
+                return;
+            }
+        }
+        ",
+            )
+        } else {
+            format!(
+                "
+        module M{0} {{
+            import 0x{1}.M{2};
+
+            public f() {{
+                M{2}.f();
+                return;
+            }}
+        }}
+        ",
+                idx,
+                address,
+                idx - 1,
+            )
+        };
+        let compiler = Compiler {
+            address: *address,
+            extra_deps: modules.clone(),
+            ..Compiler::default()
+        };
+        modules.push(
+            compiler
+                .into_compiled_module("file_name", &code)
+                .expect("Module compilation failed"),
+        );
+    }
+    modules
+}
+
+/// Compile a bundle of interdependent modules published by `sender`. Each entry of `sources` is a
+/// `(file_name, code)` pair, and modules may import any other module of the bundle, in any order.
+///
+/// `published` holds modules already on chain that the bundle may import as well, e.g. the ones
+/// returned by `FakeExecutor::published_modules`. The blobs are returned in dependency order, so
+/// they can be published one after the other.
+pub fn compile_module_bundle(
+    sender: &AccountAddress,
+    sources: &[(&str, &str)],
+    published: Vec<CompiledModule>,
+) -> Vec<Vec<u8>> {
+    try_compile_module_bundle(sender, sources, published).unwrap_or_else(|diagnostics| {
+        panic!("Module bundle compilation failed: {:#?}", diagnostics)
+    })
+}
+
+/// Compile a bundle of modules like [`compile_module_bundle`], returning the compiler's errors
+/// instead of panicking if it doesn't compile.
+///
+/// The IR compiler needs every dependency of a module to be compiled first, so modules are
+/// compiled in rounds, each one holding back the modules that import a module of the bundle that
+/// isn't compiled yet. The first error the compiler reports is returned as is. If a round makes no
+/// progress, the remaining modules import each other cyclically, and there is one diagnostic per
+/// remaining module listing the imports it's blocked on.
+pub fn try_compile_module_bundle(
+    sender: &AccountAddress,
+    sources: &[(&str, &str)],
+    published: Vec<CompiledModule>,
+) -> Result<Vec<Vec<u8>>, Vec<Diagnostic>> {
+    let mut remaining = vec![];
+    for (file_name, code) in sources {
+        let module = parse_module(file_name, code).map_err(|error| {
+            vec![Diagnostic::new(
+                file_name,
+                format!("{:#}", error),
+                error.chain().find_map(|cause| error_position(code, cause)),
+            )]
+        })?;
+        let id = ModuleId::new(
+            *sender,
+            Identifier::new(module.name.as_inner()).expect("module names are identifiers"),
+        );
+        remaining.push((*file_name, *code, id, module.get_external_deps()));
+    }
+
+    let mut deps = published;
+    let mut blobs = Vec::with_capacity(sources.len());
+    while !remaining.is_empty() {
+        let pending: Vec<_> = remaining.iter().map(|(_, _, id, _)| id.clone()).collect();
+        let mut blocked = vec![];
+        for (file_name, code, id, imports) in remaining {
+            if imports.iter().any(|import| pending.contains(import)) {
+                blocked.push((file_name, code, id, imports));
+                continue;
+            }
+            let compiler = Compiler {
+                address: *sender,
+                extra_deps: deps.clone(),
+                ..Compiler::default()
+            };
+            let module = compiler
+                .into_compiled_module(file_name, code)
+                .map_err(|error| {
+                    vec![Diagnostic::new(
+                        file_name,
+                        format!("{:#}", error),
+                        error.chain().find_map(|cause| error_position(code, cause)),
+                    )]
+                })?;
+            let mut blob = vec![];
+            module
+                .serialize(&mut blob)
+                .expect("serializing this module should work");
+            blobs.push(blob);
+            deps.push(module);
+        }
+        if blocked.len() == pending.len() {
+            return Err(blocked
+                .into_iter()
+                .map(|(file_name, _, _, imports)| {
+                    let cyclic: Vec<_> = imports
+                        .iter()
+                        .filter(|import| pending.contains(import))
+                        .map(|import| import.name().to_string())
+                        .collect();
+                    Diagnostic::new(
+                        file_name,
+                        format!(
+                            "cyclic dependency in module bundle: blocked on {}",
+                            cyclic.join(", ")
+                        ),
+                        None,
+                    )
+                })
+                .collect());
+        }
+        remaining = blocked;
+    }
+    Ok(blobs)
+}
+
+/// The abort code raised by [`conditional_abort_script`] when its argument is `true`.
+pub const CONDITIONAL_ABORT_CODE: u64 = 77;
+
+/// Compile a script that aborts with [`CONDITIONAL_ABORT_CODE`] if its `bool` argument is `true`
+/// and returns normally otherwise.
+///
+/// This gives tests a single script whose success and abort paths are selected by the argument.
+pub fn conditional_abort_script() -> Vec<u8> {
+    let code = format!(
+        "
+    main(account: &signer, should_abort: bool) {{
+      if (move(should_abort)) {{
+        abort({});
+      }}
+      return;
+    }}
+",
+        CONDITIONAL_ABORT_CODE
+    );
+
+    let compiler = Compiler {
+        address: account_config::CORE_CODE_ADDRESS,
+        ..Compiler::default()
+    };
+    compiler
+        .into_script_blob("file_name", &code)
+        .expect("Script compilation failed")
+}
+
+/// Returns a [`Script`] running [`conditional_abort_script`] with `should_abort` as its argument.
+pub fn conditional_abort_script_with_arg(should_abort: bool) -> Script {
+    Script::new(
+        conditional_abort_script(),
+        vec![],
+        vec![TransactionArgument::Bool(should_abort)],
+    )
+}
+
+/// Compile a script that creates an event handle on the sender's account and emits as many events
+/// on it as its `u64` argument says.
+///
+/// Every event goes through the same loop iteration, so the gas charged grows by a fixed amount
+/// per event.
+pub fn event_emitting_script() -> Vec<u8> {
+    let code = "
+    import 0x1.Event;
+
+    main(account: &signer, count: u64) {
+      let handle: Event.EventHandle<u64>;
+      let i: u64;
+      handle = Event.new_event_handle<u64>(move(account));
+      i = 0;
+      while (copy(i) < copy(count)) {
+        Event.emit_event<u64>(&mut handle, copy(i));
+        i = move(i) + 1;
+      }
+      Event.destroy_handle<u64>(move(handle));
+      return;
+    }
+";
+
+    let compiler = Compiler {
+        address: account_config::CORE_CODE_ADDRESS,
+        ..Compiler::default()
+    };
+    compiler
+        .into_script_blob("file_name", code)
+        .expect("Script compilation failed")
+}
+
+/// Returns a [`Script`] running [`event_emitting_script`] emitting `count` events.
+pub fn event_emitting_script_with_count(count: u64) -> Script {
+    Script::new(
+        event_emitting_script(),
+        vec![],
+        vec![TransactionArgument::U64(count)],
+    )
+}
+
+/// The interface of a compiled module, as extracted by [`module_abi`]: its public functions and
+/// the structs it declares.
+///
+/// Types are written as in Move source, e.g. `vector<u8>` or `&mut 0x1::M::T<T0>`, with type
+/// parameters named after their index. Compiled code keeps no parameter names.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ModuleABI {
+    pub address: AccountAddress,
+    pub name: String,
+    pub functions: Vec<FunctionABI>,
+    pub structs: Vec<StructABI>,
+}
+
+/// A public function of a [`ModuleABI`]. Type parameters are described by their kind constraint.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct FunctionABI {
+    pub name: String,
+    pub type_parameters: Vec<String>,
+    pub parameters: Vec<String>,
+    #[serde(rename = "return")]
+    pub return_: Vec<String>,
+}
+
+/// A struct declared in a [`ModuleABI`], with its fields in declaration order. Native structs
+/// have no fields.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct StructABI {
+    pub name: String,
+    pub is_resource: bool,
+    pub type_parameters: Vec<String>,
+    pub fields: Vec<(String, String)>,
+}
+
+/// Extracts the interface of a compiled script: its type parameters and the types of its
+/// arguments, in order.
+///
+/// Like the ABIs generated for the stdlib scripts, reference arguments such as `&signer` are
+/// skipped, since they aren't passed in the transaction. Compiled code keeps no names, so the
+/// script is called `main` and its parameters are named after their index, e.g. `arg0` and
+/// `type_arg0`. Panics if `bytes` isn't a script or takes an argument that can't be passed in a
+/// transaction, see [`try_script_abi`].
+pub fn script_abi(bytes: &[u8]) -> ScriptABI {
+    try_script_abi(bytes).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Extracts the interface of a compiled script like [`script_abi`], returning an error instead of
+/// panicking if `bytes` isn't a script or the script takes an argument that can't be passed in a
+/// transaction, such as a struct or a value of one of its type parameters.
+pub fn try_script_abi(bytes: &[u8]) -> anyhow::Result<ScriptABI> {
+    let script = CompiledScript::deserialize(bytes)
+        .map_err(|err| anyhow::anyhow!("script must deserialize: {:?}", err))?;
+    let ty_args = (0..script.as_inner().type_parameters.len())
+        .map(|idx| TypeArgumentABI::new(format!("type_arg{}", idx)))
+        .collect();
+    let args = script
+        .signature_at(script.as_inner().parameters)
+        .0
+        .iter()
+        .filter(|token| match token {
+            SignatureToken::Reference(_) | SignatureToken::MutableReference(_) => false,
+            _ => true,
+        })
+        .enumerate()
+        .map(|(idx, token)| {
+            Ok(ArgumentABI::new(
+                format!("arg{}", idx),
+                script_arg_type_tag(token)?,
+            ))
+        })
+        .collect::<anyhow::Result<_>>()?;
+    Ok(ScriptABI::new(
+        "main".to_string(),
+        String::new(),
+        bytes.to_vec(),
+        ty_args,
+        args,
+    ))
+}
+
+fn script_arg_type_tag(token: &SignatureToken) -> anyhow::Result<TypeTag> {
+    Ok(match token {
+        SignatureToken::Bool => TypeTag::Bool,
+        SignatureToken::U8 => TypeTag::U8,
+        SignatureToken::U64 => TypeTag::U64,
+        SignatureToken::U128 => TypeTag::U128,
+        SignatureToken::Address => TypeTag::Address,
+        SignatureToken::Signer => TypeTag::Signer,
+        SignatureToken::Vector(inner) => TypeTag::Vector(Box::new(script_arg_type_tag(inner)?)),
+        SignatureToken::Struct(_)
+        | SignatureToken::StructInstantiation(_, _)
+        | SignatureToken::Reference(_)
+        | SignatureToken::MutableReference(_)
+        | SignatureToken::TypeParameter(_) => anyhow::bail!(
+            "script argument of type {:?} can't be passed in a transaction",
+            token
+        ),
+    })
+}
+
+/// Extracts the interface of a compiled module: the signatures of its public functions and the
+/// layout of the structs it declares, in the order they are defined. Panics if `bytes` isn't a
+/// module.
+pub fn module_abi(bytes: &[u8]) -> ModuleABI {
+    let module = CompiledModule::deserialize(bytes).expect("module must deserialize");
+    let kinds = |kinds: &[Kind]| -> Vec<String> {
+        kinds.iter().map(|kind| format!("{:?}", kind)).collect()
+    };
+    let types = |tokens: &[SignatureToken]| -> Vec<String> {
+        tokens
+            .iter()
+            .map(|token| type_to_string(&module, token))
+            .collect()
+    };
+
+    let functions = module
+        .function_defs()
+        .iter()
+        .filter(|function_def| function_def.is_public)
+        .map(|function_def| {
+            let handle = module.function_handle_at(function_def.function);
+            FunctionABI {
+                name: module.identifier_at(handle.name).to_string(),
+                type_parameters: kinds(&handle.type_parameters),
+                parameters: types(&module.signature_at(handle.parameters).0),
+                return_: types(&module.signature_at(handle.return_).0),
+            }
+        })
+        .collect();
+    let structs = module
+        .struct_defs()
+        .iter()
+        .map(|struct_def| {
+            let handle = module.struct_handle_at(struct_def.struct_handle);
+            let fields = match &struct_def.field_information {
+                StructFieldInformation::Native => vec![],
+                StructFieldInformation::Declared(fields) => fields
+                    .iter()
+                    .map(|field| {
+                        (
+                            module.identifier_at(field.name).to_string(),
+                            type_to_string(&module, &field.signature.0),
+                        )
+                    })
+                    .collect(),
+            };
+            StructABI {
+                name: module.identifier_at(handle.name).to_string(),
+                is_resource: handle.is_nominal_resource,
+                type_parameters: kinds(&handle.type_parameters),
+                fields,
+            }
+        })
+        .collect();
+
+    let self_id = module.self_id();
+    ModuleABI {
+        address: *self_id.address(),
+        name: self_id.name().to_string(),
+        functions,
+        structs,
+    }
+}
+
+fn type_to_string(module: &CompiledModule, token: &SignatureToken) -> String {
+    let struct_name = |idx: StructHandleIndex| {
+        let handle = module.struct_handle_at(idx);
+        let module_handle = module.module_handle_at(handle.module);
+        format!(
+            "{}::{}::{}",
+            module
+                .address_identifier_at(module_handle.address)
+                .short_str(),
+            module.identifier_at(module_handle.name),
+            module.identifier_at(handle.name)
+        )
+    };
+    match token {
+        SignatureToken::Bool => "bool".to_string(),
+        SignatureToken::U8 => "u8".to_string(),
+        SignatureToken::U64 => "u64".to_string(),
+        SignatureToken::U128 => "u128".to_string(),
+        SignatureToken::Address => "address".to_string(),
+        SignatureToken::Signer => "signer".to_string(),
+        SignatureToken::Vector(inner) => format!("vector<{}>", type_to_string(module, inner)),
+        SignatureToken::Struct(idx) => struct_name(*idx),
+        SignatureToken::StructInstantiation(idx, type_args) => {
+            let type_args: Vec<_> = type_args
+                .iter()
+                .map(|type_arg| type_to_string(module, type_arg))
+                .collect();
+            format!("{}<{}>", struct_name(*idx), type_args.join(", "))
+        }
+        SignatureToken::Reference(inner) => format!("&{}", type_to_string(module, inner)),
+        SignatureToken::MutableReference(inner) => {
+            format!("&mut {}", type_to_string(module, inner))
+        }
+        SignatureToken::TypeParameter(idx) => format!("T{}", idx),
+    }
+}
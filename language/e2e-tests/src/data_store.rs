@@ -5,24 +5,27 @@
 
 use crate::account::AccountData;
 use anyhow::Result;
-use compiled_stdlib::StdLibOptions;
+use compiled_stdlib::{stdlib_modules, StdLibOptions};
+use libra_config::generator;
+use libra_crypto::HashValue;
 use libra_state_view::StateView;
 use libra_types::{
     access_path::AccessPath,
-    on_chain_config::ConfigStorage,
+    on_chain_config::{ConfigStorage, VMPublishingOption},
     transaction::ChangeSet,
     write_set::{WriteOp, WriteSet},
 };
 use libra_vm::data_cache::RemoteStorage;
 use move_core_types::{
     account_address::AccountAddress,
-    language_storage::{ModuleId, TypeTag},
+    identifier::Identifier,
+    language_storage::{ModuleId, TypeTag, CODE_TAG, CORE_CODE_ADDRESS},
 };
 use move_vm_runtime::data_cache::RemoteCache;
 use once_cell::sync::Lazy;
-use std::collections::HashMap;
-use vm::{errors::*, CompiledModule};
-use vm_genesis::generate_genesis_change_set_for_testing;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use vm::{access::ModuleAccess, errors::*, CompiledModule};
+use vm_genesis::{generate_genesis_change_set_for_testing, GENESIS_KEYPAIR};
 
 /// Dummy genesis ChangeSet for testing
 pub static GENESIS_CHANGE_SET: Lazy<ChangeSet> =
@@ -31,11 +34,54 @@ pub static GENESIS_CHANGE_SET: Lazy<ChangeSet> =
 pub static GENESIS_CHANGE_SET_FRESH: Lazy<ChangeSet> =
     Lazy::new(|| generate_genesis_change_set_for_testing(StdLibOptions::Fresh));
 
+/// Genesis ChangeSet with a single validator that only publishes the modules genesis depends on,
+/// for tests that don't exercise the validator set
+pub static GENESIS_CHANGE_SET_MINIMAL: Lazy<ChangeSet> = Lazy::new(|| {
+    let swarm = generator::validator_swarm_for_testing(1);
+    vm_genesis::encode_genesis_change_set(
+        &GENESIS_KEYPAIR.1,
+        &vm_genesis::validator_registrations(&swarm.nodes),
+        &genesis_dependencies(stdlib_modules(StdLibOptions::Compiled)),
+        VMPublishingOption::open(),
+    )
+    .0
+});
+
+/// Returns the `Genesis` module out of `modules` along with everything it depends on, directly or
+/// not, in the order they appear in `modules`.
+fn genesis_dependencies(modules: &[CompiledModule]) -> Vec<CompiledModule> {
+    let by_id: BTreeMap<_, _> = modules.iter().map(|m| (m.self_id(), m)).collect();
+    let mut needed = BTreeSet::new();
+    let mut pending = vec![ModuleId::new(
+        CORE_CODE_ADDRESS,
+        Identifier::new("Genesis").unwrap(),
+    )];
+    while let Some(module_id) = pending.pop() {
+        if !needed.insert(module_id.clone()) {
+            continue;
+        }
+        let module = by_id
+            .get(&module_id)
+            .unwrap_or_else(|| panic!("missing module {}", module_id));
+        pending.extend(
+            module
+                .module_handles()
+                .iter()
+                .map(|handle| module.module_id_for_handle(handle)),
+        );
+    }
+    modules
+        .iter()
+        .filter(|m| needed.contains(&m.self_id()))
+        .cloned()
+        .collect()
+}
+
 /// An in-memory implementation of [`StateView`] and [`RemoteCache`] for the VM.
 ///
 /// Tests use this to set up state, and pass in a reference to the cache whenever a `StateView` or
 /// `RemoteCache` is needed.
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct FakeDataStore {
     data: HashMap<AccessPath, Vec<u8>>,
 }
@@ -74,6 +120,77 @@ impl FakeDataStore {
         self.data.remove(access_path)
     }
 
+    /// Returns the number of items stored in this data store.
+    pub fn num_items(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns the total size in bytes of all the blobs stored in this data store.
+    pub fn total_bytes(&self) -> usize {
+        self.data.values().map(Vec::len).sum()
+    }
+
+    /// Returns the contents of this data store, sorted by access path.
+    pub fn to_sorted_map(&self) -> BTreeMap<AccessPath, Vec<u8>> {
+        self.data
+            .iter()
+            .map(|(access_path, blob)| (access_path.clone(), blob.clone()))
+            .collect()
+    }
+
+    /// Returns a hash of the contents of this data store.
+    ///
+    /// Data stores holding the same (key, value) pairs always have the same hash.
+    pub fn state_hash(&self) -> HashValue {
+        let sorted_data: BTreeMap<_, _> = self.data.iter().collect();
+        HashValue::sha3_256_of(
+            &lcs::to_bytes(&sorted_data).expect("serializing the data store should work"),
+        )
+    }
+
+    /// Returns all the modules published in this data store.
+    pub fn modules(&self) -> Vec<CompiledModule> {
+        self.data
+            .iter()
+            .filter(|(access_path, _)| access_path.path.first() == Some(&CODE_TAG))
+            .map(|(_, blob)| {
+                CompiledModule::deserialize(blob).expect("published module must deserialize")
+            })
+            .collect()
+    }
+
+    /// Returns the id and serialized bytes of every module published in this data store, sorted
+    /// by id.
+    ///
+    /// Access paths only hold a hash of the module id, so each module is deserialized to find it.
+    pub fn module_blobs(&self) -> impl Iterator<Item = (ModuleId, &[u8])> {
+        let mut blobs: Vec<_> = self
+            .data
+            .iter()
+            .filter(|(access_path, _)| access_path.path.first() == Some(&CODE_TAG))
+            .map(|(_, blob)| {
+                let module =
+                    CompiledModule::deserialize(blob).expect("published module must deserialize");
+                (module.self_id(), blob.as_slice())
+            })
+            .collect();
+        blobs.sort_by(|(id1, _), (id2, _)| id1.cmp(id2));
+        blobs.into_iter()
+    }
+
+    /// Returns the modules published under `address` in this data store.
+    pub fn modules_at(&self, address: &AccountAddress) -> Vec<CompiledModule> {
+        self.data
+            .iter()
+            .filter(|(access_path, _)| {
+                access_path.address == *address && access_path.path.first() == Some(&CODE_TAG)
+            })
+            .map(|(_, blob)| {
+                CompiledModule::deserialize(blob).expect("published module must deserialize")
+            })
+            .collect()
+    }
+
     /// Adds an [`AccountData`] to this data store.
     pub fn add_account_data(&mut self, account_data: &AccountData) {
         let write_set = account_data.to_writeset();
@@ -6,5 +6,7 @@
 pub mod basic_strategy;
 pub mod guided_strategy;
 pub mod multi_strategy;
+pub mod parallel_strategy;
 pub mod random_strategy;
+pub mod shuffle_strategy;
 pub mod types;
@@ -0,0 +1,67 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![forbid(unsafe_code)]
+use crate::{
+    execution_strategies::{
+        multi_strategy::MultiResult,
+        types::{Block, Executor, ExecutorResult},
+    },
+    executor::FakeExecutor,
+};
+use libra_types::{transaction::SignedTransaction, vm_status::VMStatus};
+
+/// Executes each block both sequentially and with [`FakeExecutor::execute_block_parallel`], against
+/// the same state, and fails with the first pair of outputs that differ. Outputs include write sets
+/// and events, so this is differential coverage of parallel execution with the sequential
+/// executor as the oracle.
+///
+/// The sequential outputs are returned and applied, so blocks build on each other.
+#[derive(Debug)]
+pub struct ParallelEquivalenceExecutor {
+    executor: FakeExecutor,
+}
+
+impl Default for ParallelEquivalenceExecutor {
+    fn default() -> Self {
+        Self::new(FakeExecutor::from_genesis_file())
+    }
+}
+
+impl ParallelEquivalenceExecutor {
+    pub fn new(executor: FakeExecutor) -> Self {
+        Self { executor }
+    }
+
+    /// Returns the underlying executor, e.g. to inspect the state the blocks left.
+    pub fn executor(&self) -> &FakeExecutor {
+        &self.executor
+    }
+}
+
+impl Executor for ParallelEquivalenceExecutor {
+    type Txn = SignedTransaction;
+    type BlockResult = MultiResult<VMStatus>;
+    fn execute_block(&mut self, txns: Block<Self::Txn>) -> ExecutorResult<Self::BlockResult> {
+        let parallel = self
+            .executor
+            .execute_block_parallel(txns.clone())
+            .map_err(MultiResult::OtherResult)?;
+        let sequential = self
+            .executor
+            .execute_block(txns)
+            .map_err(MultiResult::OtherResult)?;
+        for (parallel_output, sequential_output) in parallel.into_iter().zip(&sequential) {
+            if &parallel_output != sequential_output {
+                return Err(MultiResult::NonMatchingOutput(
+                    parallel_output,
+                    sequential_output.clone(),
+                ));
+            }
+        }
+        for output in &sequential {
+            self.executor.apply_write_set(output.write_set())
+        }
+        Ok(sequential)
+    }
+}
@@ -0,0 +1,390 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+#![forbid(unsafe_code)]
+use crate::{
+    execution_strategies::types::{Block, Executor, ExecutorResult, PartitionStrategy},
+    executor::{FakeExecutor, StateSnapshot},
+};
+use libra_crypto::HashValue;
+use libra_types::{
+    account_address::AccountAddress,
+    transaction::{SignedTransaction, TransactionOutput},
+    vm_status::VMStatus,
+};
+use rand::{
+    rngs::{OsRng, StdRng},
+    seq::SliceRandom,
+    Rng, SeedableRng,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    error::Error,
+    fmt,
+};
+
+/// Reorders the transactions of a block with a permutation derived from a seed, so that the same
+/// seed always yields the same order.
+#[derive(Debug, Clone)]
+pub struct SeededShuffleStrategy {
+    seed: u64,
+    gen: StdRng,
+}
+
+impl SeededShuffleStrategy {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            gen: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Picks a random seed, which [`SeededShuffleStrategy::seed`] returns so that the order can be
+    /// reproduced with [`SeededShuffleStrategy::new`].
+    pub fn from_os_rng() -> Self {
+        Self::new(OsRng.gen::<u64>())
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Returns the order in which the next block of `len` transactions will be executed, as
+    /// indexes into the block.
+    pub fn permutation(&mut self, len: usize) -> Vec<usize> {
+        let mut order: Vec<_> = (0..len).collect();
+        order.shuffle(&mut self.gen);
+        order
+    }
+}
+
+impl PartitionStrategy for SeededShuffleStrategy {
+    type Txn = SignedTransaction;
+    fn partition(&mut self, block: Block<Self::Txn>) -> Vec<Block<SignedTransaction>> {
+        let order = self.permutation(block.len());
+        let mut txns: Vec<_> = block.into_iter().map(Some).collect();
+        vec![order
+            .into_iter()
+            .map(|idx| txns[idx].take().expect("indexes are unique"))
+            .collect()]
+    }
+}
+
+/// A failure of an executor that orders transactions from a seed, along with that seed so the
+/// failing order can be reproduced.
+#[derive(Debug)]
+pub struct SeededFailure<E> {
+    pub seed: u64,
+    pub error: E,
+}
+
+impl<E: fmt::Display> fmt::Display for SeededFailure<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (seed {})", self.error, self.seed)
+    }
+}
+
+impl<E: Error> Error for SeededFailure<E> {}
+
+/// Executes each block in the order picked by a [`SeededShuffleStrategy`]. The outputs are returned
+/// in that order, which a new strategy with the same seed reproduces. Failures carry the seed.
+#[derive(Debug)]
+pub struct ShuffleExecutor {
+    strategy: SeededShuffleStrategy,
+    executor: FakeExecutor,
+}
+
+impl ShuffleExecutor {
+    pub fn from_seed(seed: u64) -> Self {
+        Self {
+            executor: FakeExecutor::from_genesis_file(),
+            strategy: SeededShuffleStrategy::new(seed),
+        }
+    }
+
+    pub fn from_os_rng() -> Self {
+        Self {
+            executor: FakeExecutor::from_genesis_file(),
+            strategy: SeededShuffleStrategy::from_os_rng(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.strategy.seed()
+    }
+}
+
+impl Executor for ShuffleExecutor {
+    type Txn = SignedTransaction;
+    type BlockResult = SeededFailure<VMStatus>;
+    fn execute_block(&mut self, txns: Block<Self::Txn>) -> ExecutorResult<Self::BlockResult> {
+        let seed = self.seed();
+        let mut block = self.strategy.partition(txns);
+        let outputs = self
+            .executor
+            .execute_block(block.remove(0))
+            .map_err(|error| SeededFailure { seed, error })?;
+        for output in &outputs {
+            self.executor.apply_write_set(output.write_set())
+        }
+        Ok(outputs)
+    }
+}
+
+/// The largest block [`PermutationExecutor`] accepts; a block of this size already has 720
+/// orders.
+pub const MAX_PERMUTATION_BLOCK_SIZE: usize = 6;
+
+/// Returns every order of `block`, starting with the original one.
+pub fn permutations<T: Clone>(block: &[T]) -> Vec<Block<T>> {
+    if block.is_empty() {
+        return vec![vec![]];
+    }
+    let mut results = vec![];
+    for idx in 0..block.len() {
+        let mut rest = block.to_vec();
+        let first = rest.remove(idx);
+        for mut permutation in permutations(&rest) {
+            permutation.insert(0, first.clone());
+            results.push(permutation);
+        }
+    }
+    results
+}
+
+/// Executes a block in every possible order, each one against the same starting state, for
+/// exhaustive tests of small blocks. Nothing is applied to the underlying executor.
+#[derive(Debug)]
+pub struct PermutationExecutor {
+    executor: FakeExecutor,
+}
+
+impl Default for PermutationExecutor {
+    fn default() -> Self {
+        Self::new(FakeExecutor::from_genesis_file())
+    }
+}
+
+impl PermutationExecutor {
+    pub fn new(executor: FakeExecutor) -> Self {
+        Self { executor }
+    }
+
+    /// Runs every order of `block` and returns each one, as indexes into `block`, along with its
+    /// outputs. Panics if the block is larger than [`MAX_PERMUTATION_BLOCK_SIZE`].
+    pub fn execute_permutations(
+        &self,
+        block: Block<SignedTransaction>,
+    ) -> Result<Vec<(Vec<usize>, Vec<TransactionOutput>)>, VMStatus> {
+        assert!(
+            block.len() <= MAX_PERMUTATION_BLOCK_SIZE,
+            "block of {} transactions is too large to run in every order",
+            block.len()
+        );
+        let indexes: Vec<_> = (0..block.len()).collect();
+        let mut results = vec![];
+        for order in permutations(&indexes) {
+            let txns = order.iter().map(|idx| block[*idx].clone()).collect();
+            results.push((order, self.executor.execute_block(txns)?));
+        }
+        Ok(results)
+    }
+}
+
+/// Picks random orders for a block of transactions, keeping the transactions of each sender in
+/// their original order so that sequence numbers still line up.
+#[derive(Debug, Clone)]
+pub struct RandomOrderExecutionStrategy {
+    shuffle: SeededShuffleStrategy,
+    num_orders: usize,
+}
+
+impl RandomOrderExecutionStrategy {
+    /// Picks `num_orders` orders per block, on top of the original one, from `seed`.
+    pub fn new(seed: u64, num_orders: usize) -> Self {
+        Self {
+            shuffle: SeededShuffleStrategy::new(seed),
+            num_orders,
+        }
+    }
+
+    /// Like [`RandomOrderExecutionStrategy::new`], but picks a random seed, which
+    /// [`RandomOrderExecutionStrategy::seed`] returns.
+    pub fn from_os_rng(num_orders: usize) -> Self {
+        Self {
+            shuffle: SeededShuffleStrategy::from_os_rng(),
+            num_orders,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.shuffle.seed()
+    }
+
+    /// Returns the orders to run `block` in, as indexes into the block, starting with the original
+    /// one.
+    pub fn orders(&mut self, block: &[SignedTransaction]) -> Vec<Vec<usize>> {
+        let mut orders = vec![(0..block.len()).collect()];
+        for _ in 0..self.num_orders {
+            // Each sender keeps the slots the shuffle gave its transactions, which are then filled
+            // in their original order.
+            let mut by_sender: HashMap<AccountAddress, VecDeque<usize>> = HashMap::new();
+            for (idx, txn) in block.iter().enumerate() {
+                by_sender.entry(txn.sender()).or_default().push_back(idx);
+            }
+            let order = self
+                .shuffle
+                .permutation(block.len())
+                .into_iter()
+                .map(|idx| {
+                    by_sender
+                        .get_mut(&block[idx].sender())
+                        .and_then(VecDeque::pop_front)
+                        .expect("each sender has as many slots as transactions")
+                })
+                .collect();
+            orders.push(order);
+        }
+        orders
+    }
+}
+
+impl PartitionStrategy for RandomOrderExecutionStrategy {
+    type Txn = SignedTransaction;
+    fn partition(&mut self, block: Block<Self::Txn>) -> Vec<Block<SignedTransaction>> {
+        self.orders(&block)
+            .into_iter()
+            .map(|order| order.into_iter().map(|idx| block[idx].clone()).collect())
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub enum RandomOrderResult {
+    /// The transaction at this index in the block got a different status or used a different
+    /// amount of gas in the original order and in another one.
+    DifferentOutputs(usize, TransactionOutput, TransactionOutput),
+    /// The original order and this one, as indexes into the block, left different states.
+    DifferentStates(Vec<usize>),
+    OtherResult(VMStatus),
+}
+
+impl fmt::Display for RandomOrderResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RandomOrderResult::DifferentOutputs(idx, original, reordered) => write!(
+                f,
+                "transaction {} depends on the order: {:?} != {:?}",
+                idx, original, reordered
+            ),
+            RandomOrderResult::DifferentStates(order) => write!(
+                f,
+                "running the block in the order {:?} leaves a different state",
+                order
+            ),
+            RandomOrderResult::OtherResult(err) => fmt::Display::fmt(err, f),
+        }
+    }
+}
+
+impl Error for RandomOrderResult {}
+
+/// Executes each block in the original order and in the orders picked by a
+/// [`RandomOrderExecutionStrategy`], all against the same starting state. Fails if a transaction
+/// gets a different status or uses a different amount of gas in some order, or if two orders leave
+/// different states.
+///
+/// This is meant for blocks of independent transactions, to catch accidental order dependence in
+/// natives and gas accounting. Events are not compared: the sequence numbers of events sent to a
+/// shared receiver legitimately depend on the order.
+///
+/// The outputs of the original order are returned, and its state is the one kept. Failures carry
+/// the seed of the strategy.
+#[derive(Debug)]
+pub struct RandomOrderExecutor {
+    strategy: RandomOrderExecutionStrategy,
+    executor: FakeExecutor,
+}
+
+impl RandomOrderExecutor {
+    pub fn new(executor: FakeExecutor, strategy: RandomOrderExecutionStrategy) -> Self {
+        Self { strategy, executor }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.strategy.seed()
+    }
+
+    /// Returns the underlying executor, e.g. to inspect the state the blocks left.
+    pub fn executor(&self) -> &FakeExecutor {
+        &self.executor
+    }
+
+    /// Runs `txns` in every order the strategy picks, see [`RandomOrderExecutor`].
+    fn execute_block_in_orders(
+        &mut self,
+        txns: Block<SignedTransaction>,
+    ) -> ExecutorResult<RandomOrderResult> {
+        let start = self.executor.snapshot();
+        let mut original: Option<(Vec<TransactionOutput>, HashValue, StateSnapshot)> = None;
+        for order in self.strategy.orders(&txns) {
+            self.executor.restore(start.clone());
+            let block = order.iter().map(|idx| txns[*idx].clone()).collect();
+            let outputs = self
+                .executor
+                .execute_block(block)
+                .map_err(RandomOrderResult::OtherResult)?;
+            for output in &outputs {
+                self.executor.apply_write_set(output.write_set())
+            }
+
+            // Put the outputs back in the order of the block.
+            let mut reordered: Vec<_> = outputs.into_iter().map(Some).collect();
+            let mut outputs = vec![None; txns.len()];
+            for (position, idx) in order.iter().enumerate() {
+                outputs[*idx] = reordered[position].take();
+            }
+            let outputs: Vec<_> = outputs
+                .into_iter()
+                .map(|output| output.expect("indexes are unique"))
+                .collect();
+
+            // The original order comes first and sets what the other ones are compared against.
+            if original.is_none() {
+                let state_hash = self.executor.state_hash();
+                original = Some((outputs, state_hash, self.executor.snapshot()));
+                continue;
+            }
+            let (original_outputs, original_hash, _) = original.as_ref().unwrap();
+            for (idx, (original_output, output)) in original_outputs.iter().zip(outputs).enumerate()
+            {
+                if original_output.status() != output.status()
+                    || original_output.gas_used() != output.gas_used()
+                {
+                    return Err(RandomOrderResult::DifferentOutputs(
+                        idx,
+                        original_output.clone(),
+                        output,
+                    ));
+                }
+            }
+            if self.executor.state_hash() != *original_hash {
+                return Err(RandomOrderResult::DifferentStates(order));
+            }
+        }
+
+        let (outputs, _, state) = original.expect("the original order is always run");
+        self.executor.restore(state);
+        Ok(outputs)
+    }
+}
+
+impl Executor for RandomOrderExecutor {
+    type Txn = SignedTransaction;
+    type BlockResult = SeededFailure<RandomOrderResult>;
+    fn execute_block(&mut self, txns: Block<Self::Txn>) -> ExecutorResult<Self::BlockResult> {
+        let seed = self.seed();
+        self.execute_block_in_orders(txns)
+            .map_err(|error| SeededFailure { seed, error })
+    }
+}
@@ -0,0 +1,26 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Measures how long the VM spends in each phase of executing transactions, to track performance
+//! regressions.
+
+use std::time::Duration;
+
+/// How long executing a transaction took, in total and in each phase.
+///
+/// The phases are timed inside the VM as it executes the transaction, so a phase that didn't run
+/// takes no time. Every timing is local to its run, so executions on other threads don't affect it.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ExecutionTiming {
+    /// Time spent in the prologue, which checks that the transaction can run and that its sender
+    /// can pay for it.
+    pub verification: Duration,
+    /// Time spent charging intrinsic gas and loading, verifying and running the script, or
+    /// publishing the module.
+    pub execution: Duration,
+    /// Time spent in the success or failure epilogue, which charges for gas and bumps the sequence
+    /// number.
+    pub epilogue: Duration,
+    /// The wall-clock time of the whole execution.
+    pub total: Duration,
+}
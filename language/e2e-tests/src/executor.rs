@@ -4,42 +4,413 @@
 //! Support for running the VM to execute and verify transactions.
 
 use crate::{
-    account::{Account, AccountData},
-    data_store::{FakeDataStore, GENESIS_CHANGE_SET, GENESIS_CHANGE_SET_FRESH},
+    abort_module,
+    account::{decode_role, Account, AccountData, AccountRoleSpecifier, Role},
+    compile::module_chain,
+    data_store::{
+        FakeDataStore, GENESIS_CHANGE_SET, GENESIS_CHANGE_SET_FRESH, GENESIS_CHANGE_SET_MINIMAL,
+    },
+    execution_timing::ExecutionTiming,
+    gas_costs,
+    transaction_log::{LogEntry, TransactionLog},
+    vm_log::{capture_logs, VMLogEntry},
+    write_set_diff::{diff_data_stores, diff_write_sets, write_set_between, WriteSetDiff},
 };
 use compiled_stdlib::{stdlib_modules, transaction_scripts::StdlibScript, StdLibOptions};
+use compiler::Compiler;
 use libra_config::generator;
 use libra_crypto::HashValue;
 use libra_state_view::StateView;
 use libra_types::{
     access_path::AccessPath,
-    account_config::{AccountResource, BalanceResource, CORE_CODE_ADDRESS},
-    block_metadata::{new_block_event_key, BlockMetadata, NewBlockEvent},
-    on_chain_config::{OnChainConfig, ScriptPublishingOption, VMPublishingOption, ValidatorSet},
+    account_config::{
+        from_currency_code_string, libra_root_address, testnet_dd_account_address,
+        treasury_compliance_account_address, type_tag_for_currency_code, AccountResource,
+        BalanceResource, CurrencyInfoResource, Limit, CORE_CODE_ADDRESS, LBR_NAME,
+    },
+    block_metadata::{new_block_event_key, BlockMetadata, LibraBlockResource, NewBlockEvent},
+    contract_event::ContractEvent,
+    event::EventHandle,
+    libra_timestamp::LibraTimestampResource,
+    on_chain_config::{
+        new_epoch_event_key, ConfigurationResource, LibraVersion, OnChainConfig,
+        RegisteredCurrencies, ScriptPublishingOption, VMConfig, VMPublishingOption, ValidatorSet,
+    },
     transaction::{
-        SignedTransaction, Transaction, TransactionOutput, TransactionStatus, VMValidatorResult,
+        Module, SignedTransaction, Transaction, TransactionArgument, TransactionOutput,
+        TransactionPayload, TransactionStatus, VMValidatorResult,
     },
     vm_status::{StatusCode, VMStatus},
-    write_set::WriteSet,
+    write_set::{WriteOp, WriteSet, WriteSetMut},
 };
 use libra_vm::{
-    data_cache::RemoteStorage, txn_effects_to_writeset_and_events, LibraVM, LibraVMValidator,
-    VMExecutor, VMValidator,
+    data_cache::RemoteStorage,
+    transaction_phases::{with_phase_hook, TransactionPhase},
+    txn_effects_to_writeset_and_events, LibraVM, LibraVMValidator, VMExecutor, VMValidator,
 };
 use move_core_types::{
     account_address::AccountAddress,
-    gas_schedule::{GasAlgebra, GasUnits},
+    gas_schedule::{CostTable, GasAlgebra, GasConstants, GasUnits},
     identifier::Identifier,
-    language_storage::{ModuleId, TypeTag},
+    language_storage::{ModuleId, StructTag, TypeTag, CODE_TAG},
+    move_resource::MoveResource,
+};
+use move_vm_runtime::{
+    move_vm::MoveVM,
+    tracing::{with_trace_hook, TraceEvent},
 };
-use move_vm_runtime::move_vm::MoveVM;
 use move_vm_types::{
     gas_schedule::{zero_cost_schedule, CostStrategy},
-    values::Value,
+    values::{Struct, Value},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt, fs,
+    path::Path,
+    rc::Rc,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+use transaction_builder::{
+    encode_add_currency_to_account_script, encode_create_child_vasp_account_script,
+    encode_create_parent_vasp_account_script, encode_create_testing_account_script,
+    encode_testnet_mint_script,
+};
+use vm::{
+    access::ModuleAccess,
+    errors::VMError,
+    file_format::{Bytecode, CodeOffset, FunctionSignature},
+    CompiledModule,
 };
-use vm::CompiledModule;
 use vm_genesis::GENESIS_KEYPAIR;
 
+/// The block time executors start at, in microseconds. This matches the on-chain time at genesis.
+pub const GENESIS_BLOCK_TIME: u64 = 0;
+
+/// The version of the format written by [`FakeExecutor::save_state`]. Bump it whenever that format
+/// or the layout of [`FakeDataStore`] changes, so that older files fail to load.
+pub const STATE_FILE_VERSION: u32 = 1;
+
+/// The change in the size of the data store over a workload.
+///
+/// Returned by [`FakeExecutor::run_and_measure_growth`]. Both fields are negative if the workload
+/// shrank the state.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct StateGrowth {
+    /// The number of items added to the data store.
+    pub resources_added: i64,
+    /// The number of serialized bytes added to the data store.
+    pub bytes_added: i64,
+}
+
+/// The outcome of a transaction along with where it aborted and with which code.
+///
+/// Returned by [`FakeExecutor::execute_decoded`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DecodedOutcome {
+    /// The status of the transaction.
+    pub status: TransactionStatus,
+    /// The abort code, if the transaction aborted in Move code.
+    pub abort_code: Option<u64>,
+    /// The module the transaction aborted in, if it aborted in a module rather than a script.
+    pub abort_module: Option<ModuleId>,
+}
+
+/// A copy of the full state of a [`FakeExecutor`], including published modules and the block time.
+///
+/// Returned by [`FakeExecutor::snapshot`] and consumed by [`FakeExecutor::restore`].
+#[derive(Clone, Debug)]
+pub struct StateSnapshot {
+    data_store: FakeDataStore,
+    block_time: u64,
+}
+
+/// On-chain configs for [`FakeExecutor::from_genesis_with_configs`] to set in place of the ones
+/// from genesis. Configs that aren't set keep their genesis value.
+#[derive(Clone, Debug, Default)]
+pub struct GenesisConfigs {
+    publishing_option: Option<VMPublishingOption>,
+    gas_schedule: Option<CostTable>,
+    // Every other config, LCS encoded, along with the path it's stored at.
+    configs: Vec<(AccessPath, Vec<u8>)>,
+}
+
+impl GenesisConfigs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the script and module publishing option, which can only be either `Open` or
+    /// `CustomScript`, like with [`FakeExecutor::from_genesis_with_options`].
+    pub fn publishing_option(mut self, publishing_option: VMPublishingOption) -> Self {
+        self.publishing_option = Some(publishing_option);
+        self
+    }
+
+    pub fn gas_schedule(mut self, gas_schedule: CostTable) -> Self {
+        self.gas_schedule = Some(gas_schedule);
+        self
+    }
+
+    pub fn libra_version(self, major: u64) -> Self {
+        self.config(&LibraVersion { major })
+    }
+
+    /// Sets the list of registered currencies. This doesn't register new currencies with the
+    /// `Libra` module, it only changes what the config reports, e.g. to
+    /// [`FakeExecutor::balances`].
+    pub fn registered_currencies(self, currency_codes: &[&str]) -> Self {
+        let currency_codes: Vec<_> = currency_codes
+            .iter()
+            .map(|code| from_currency_code_string(code).expect("currency code must be valid"))
+            .collect();
+        let config = RegisteredCurrencies::from_bytes(
+            &lcs::to_bytes(&currency_codes).expect("Failure encoding config"),
+        )
+        .expect("registered currencies are encoded as a list of currency codes");
+        self.config(&config)
+    }
+
+    /// Sets any other config. See [`FakeExecutor::set_config`] for which ones can be set this way.
+    pub fn config<T: OnChainConfig + Serialize>(mut self, config: &T) -> Self {
+        self.configs.push((
+            T::CONFIG_ID.access_path(),
+            lcs::to_bytes(config).expect("Failure encoding config"),
+        ));
+        self
+    }
+}
+
+/// The dependencies found between the transactions of a block when executing it in parallel.
+///
+/// Returned by [`FakeExecutor::execute_block_parallel_with_report`]. Transactions are identified
+/// by their index in the block.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ParallelExecutionReport {
+    /// The pairs `(i, j)`, with `i < j`, such that transaction `j` accessed a path written by
+    /// transaction `i`.
+    pub conflicts: Vec<(usize, usize)>,
+    /// The transactions that conflicted with an earlier one, and so were executed again after it.
+    pub serialized: Vec<usize>,
+}
+
+/// Where the Move code of a transaction aborted, as traced by
+/// [`FakeExecutor::execute_transaction_with_trace`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExecutionTrace {
+    /// The function the last `abort` instruction the transaction ran is in and its offset, e.g.
+    /// `("0x1::LibraAccount::withdraw_from", 12)`. `None` if the transaction didn't abort.
+    pub abort_frame: Option<(String, CodeOffset)>,
+}
+
+/// An instruction executed by the VM, as recorded by
+/// [`FakeExecutor::execute_transaction_with_instructions`].
+#[derive(Clone, Debug)]
+pub struct TracedInstruction {
+    /// The function the instruction is in, e.g. `0x1::LibraAccount::pay_from` or `Script::main`.
+    pub function: String,
+    /// The offset of the instruction in the function.
+    pub pc: CodeOffset,
+    pub instruction: Bytecode,
+    /// The number of frames under the one of `function` on the call stack.
+    pub call_depth: usize,
+    /// The values on the operand stack before the instruction ran, bottom first.
+    pub operand_stack: Vec<String>,
+}
+
+/// The state a user transaction went through while the VM executed it, as recorded by
+/// [`FakeExecutor::execute_transaction_staged`].
+#[derive(Clone, Debug)]
+pub struct StagedExecution {
+    /// The output of the transaction.
+    pub output: TransactionOutput,
+    /// What the transaction had written once its script ran or its module was published, or the
+    /// error that failed. `None` if the prologue failed.
+    pub body: Option<Result<WriteSet, VMStatus>>,
+    /// What the transaction had written once the success epilogue charged for gas and bumped the
+    /// sequence number, body included, or the error the epilogue failed with. `None` unless the
+    /// body succeeded.
+    ///
+    /// If the epilogue fails, the VM rolls the body back and only runs the failure epilogue, which
+    /// is what [`StagedExecution::output`] then holds.
+    pub epilogue: Option<Result<WriteSet, VMStatus>>,
+}
+
+/// How a transaction ran under two executors, as compared by [`FakeExecutor::compare_run`].
+#[derive(Clone, Debug)]
+pub struct RunComparison {
+    /// The output under the executor `compare_run` was called on.
+    pub output: TransactionOutput,
+    /// The output under the other executor.
+    pub other_output: TransactionOutput,
+    /// The access paths the two runs wrote differently, sorted, with what each run wrote there.
+    /// `None` means the run didn't write to the path.
+    pub write_set_differences: Vec<(AccessPath, Option<WriteOp>, Option<WriteOp>)>,
+}
+
+impl RunComparison {
+    fn new(output: TransactionOutput, other_output: TransactionOutput) -> Self {
+        let writes: BTreeMap<_, _> = output.write_set().iter().cloned().collect();
+        let other_writes: BTreeMap<_, _> = other_output.write_set().iter().cloned().collect();
+        let paths: BTreeSet<_> = writes.keys().chain(other_writes.keys()).collect();
+        let write_set_differences = paths
+            .into_iter()
+            .filter_map(|access_path| {
+                let write = writes.get(access_path);
+                let other_write = other_writes.get(access_path);
+                if write == other_write {
+                    None
+                } else {
+                    Some((access_path.clone(), write.cloned(), other_write.cloned()))
+                }
+            })
+            .collect();
+        Self {
+            output,
+            other_output,
+            write_set_differences,
+        }
+    }
+
+    pub fn status_diverges(&self) -> bool {
+        self.output.status() != self.other_output.status()
+    }
+
+    pub fn gas_used_diverges(&self) -> bool {
+        self.output.gas_used() != self.other_output.gas_used()
+    }
+
+    pub fn events_diverge(&self) -> bool {
+        self.output.events() != self.other_output.events()
+    }
+
+    pub fn write_set_diverges(&self) -> bool {
+        !self.write_set_differences.is_empty()
+    }
+
+    /// Returns whether the two runs differ in any way.
+    pub fn diverges(&self) -> bool {
+        self.status_diverges()
+            || self.gas_used_diverges()
+            || self.events_diverge()
+            || self.write_set_diverges()
+    }
+}
+
+/// Prints one line per way the runs differ, or nothing if they don't.
+impl fmt::Display for RunComparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.status_diverges() {
+            writeln!(
+                f,
+                "status: {:?} vs {:?}",
+                self.output.status(),
+                self.other_output.status()
+            )?;
+        }
+        if self.gas_used_diverges() {
+            writeln!(
+                f,
+                "gas used: {} vs {}",
+                self.output.gas_used(),
+                self.other_output.gas_used()
+            )?;
+        }
+        if self.events_diverge() {
+            writeln!(
+                f,
+                "events: {:?} vs {:?}",
+                self.output.events(),
+                self.other_output.events()
+            )?;
+        }
+        let describe = |write: &Option<WriteOp>| match write {
+            Some(WriteOp::Value(blob)) => format!("{} bytes", blob.len()),
+            Some(WriteOp::Deletion) => "deleted".to_string(),
+            None => "not written".to_string(),
+        };
+        for (access_path, write, other_write) in &self.write_set_differences {
+            writeln!(
+                f,
+                "write to {}: {} vs {}",
+                access_path,
+                describe(write),
+                describe(other_write)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The storage a transaction touched, as measured by
+/// [`FakeExecutor::execute_transaction_with_footprint`].
+///
+/// Reads include everything the prologue, the epilogue and the VM itself read, such as modules and
+/// on-chain configs, so compare footprints between transactions that only differ in the code path
+/// under test.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct StorageFootprint {
+    /// The number of distinct access paths read, including the ones holding no value.
+    pub reads: usize,
+    /// The total size of the values read.
+    pub bytes_read: usize,
+    /// The number of access paths written or deleted.
+    pub writes: usize,
+    /// The total size of the values written. Deletions don't count.
+    pub bytes_written: usize,
+}
+
+impl StorageFootprint {
+    /// Returns a footprint with the writes of `write_set` and no reads.
+    pub fn of_write_set(write_set: &WriteSet) -> Self {
+        let mut footprint = Self::default();
+        for (_, write_op) in write_set.iter() {
+            footprint.writes += 1;
+            if let WriteOp::Value(blob) = write_op {
+                footprint.bytes_written += blob.len();
+            }
+        }
+        footprint
+    }
+}
+
+/// A view of a [`FakeDataStore`] which records every access path read through it.
+struct ReadRecordingView<'a> {
+    data_store: &'a FakeDataStore,
+    reads: RefCell<HashSet<AccessPath>>,
+}
+
+impl<'a> ReadRecordingView<'a> {
+    fn new(data_store: &'a FakeDataStore) -> Self {
+        Self {
+            data_store,
+            reads: RefCell::new(HashSet::new()),
+        }
+    }
+
+    fn into_reads(self) -> HashSet<AccessPath> {
+        self.reads.into_inner()
+    }
+}
+
+impl<'a> StateView for ReadRecordingView<'a> {
+    fn get(&self, access_path: &AccessPath) -> anyhow::Result<Option<Vec<u8>>> {
+        self.reads.borrow_mut().insert(access_path.clone());
+        StateView::get(self.data_store, access_path)
+    }
+
+    fn multi_get(&self, _access_paths: &[AccessPath]) -> anyhow::Result<Vec<Option<Vec<u8>>>> {
+        unimplemented!();
+    }
+
+    fn is_genesis(&self) -> bool {
+        StateView::is_genesis(self.data_store)
+    }
+}
+
 /// Provides an environment to run a VM instance.
 ///
 /// This struct is a mock in-memory implementation of the Libra executor.
@@ -47,6 +418,7 @@ use vm_genesis::GENESIS_KEYPAIR;
 pub struct FakeExecutor {
     data_store: FakeDataStore,
     block_time: u64,
+    recording: RefCell<Option<TransactionLog>>,
 }
 
 impl FakeExecutor {
@@ -54,12 +426,62 @@ impl FakeExecutor {
     pub fn from_genesis(write_set: &WriteSet) -> Self {
         let mut executor = FakeExecutor {
             data_store: FakeDataStore::default(),
-            block_time: 0,
+            block_time: GENESIS_BLOCK_TIME,
+            recording: RefCell::new(None),
         };
         executor.apply_write_set(write_set);
         executor
     }
 
+    /// Creates an executor from a file written by [`FakeExecutor::save_state`]. Panics if the file
+    /// can't be read or was written in another version of the format.
+    pub fn from_state_file(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        Self::try_from_state_file(path)
+            .unwrap_or_else(|err| panic!("unable to load state file {:?}: {:#}", path, err))
+    }
+
+    /// Creates an executor from a file written by [`FakeExecutor::save_state`], returning an error
+    /// if the file can't be read or was written in another version of the format.
+    pub fn try_from_state_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Self::try_from_state_bytes(&fs::read(path)?)
+    }
+
+    /// Creates an executor from the bytes returned by [`FakeExecutor::state_bytes`], returning an
+    /// error if they were written in another version of the format.
+    pub fn try_from_state_bytes(bytes: &[u8]) -> anyhow::Result<Self> {
+        // The version is encoded on its own so it can be checked before decoding the rest.
+        let (version, state): (u32, Vec<u8>) = lcs::from_bytes(bytes)?;
+        anyhow::ensure!(
+            version == STATE_FILE_VERSION,
+            "state file has version {}, expected {}",
+            version,
+            STATE_FILE_VERSION
+        );
+        let (block_time, data): (u64, BTreeMap<AccessPath, Vec<u8>>) = lcs::from_bytes(&state)?;
+        Ok(FakeExecutor {
+            data_store: FakeDataStore::new(data.into_iter().collect()),
+            block_time,
+            recording: RefCell::new(None),
+        })
+    }
+
+    /// Writes the full state of this executor, including published modules and the block time, to
+    /// `path`. [`FakeExecutor::from_state_file`] reads it back.
+    pub fn save_state(&self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        fs::write(path, self.state_bytes())
+            .unwrap_or_else(|err| panic!("unable to write state file {:?}: {}", path, err));
+    }
+
+    /// Returns the full state of this executor in the format written by
+    /// [`FakeExecutor::save_state`], to embed it in other files.
+    pub fn state_bytes(&self) -> Vec<u8> {
+        let state = lcs::to_bytes(&(self.block_time, self.data_store.to_sorted_map()))
+            .expect("executor state must serialize");
+        lcs::to_bytes(&(STATE_FILE_VERSION, state)).expect("state file must serialize")
+    }
+
     /// Creates an executor from the genesis file GENESIS_FILE_LOCATION
     pub fn from_genesis_file() -> Self {
         Self::from_genesis(GENESIS_CHANGE_SET.clone().write_set())
@@ -70,6 +492,18 @@ impl FakeExecutor {
         Self::from_genesis(GENESIS_CHANGE_SET_FRESH.clone().write_set())
     }
 
+    /// Creates an executor from a reduced genesis for focused tests that need fast setup.
+    ///
+    /// Only the `Genesis` module and the modules it depends on are published, which leaves out
+    /// standard library modules like `RecoveryAddress`, `SharedEd25519PublicKey` and `Offer`.
+    /// Genesis itself sets up accounts, currencies and roles, so those are all there, but the
+    /// validator set only holds a single validator and no accounts beyond the ones genesis always
+    /// creates exist. Tests that exercise validator set management or rely on the full
+    /// testing swarm should use [`FakeExecutor::from_genesis_file`] instead.
+    pub fn minimal() -> Self {
+        Self::from_genesis(GENESIS_CHANGE_SET_MINIMAL.clone().write_set())
+    }
+
     pub fn whitelist_genesis() -> Self {
         Self::custom_genesis(
             stdlib_modules(StdLibOptions::Compiled).to_vec(),
@@ -93,11 +527,69 @@ impl FakeExecutor {
         )
     }
 
+    /// Creates an executor like [`FakeExecutor::from_genesis_with_options`], metering transactions
+    /// against `gas_schedule` instead of the genesis gas schedule.
+    pub fn from_genesis_with_gas_schedule(
+        publishing_options: VMPublishingOption,
+        gas_schedule: CostTable,
+    ) -> Self {
+        let mut executor = Self::from_genesis_with_options(publishing_options);
+        executor.set_gas_schedule(gas_schedule);
+        executor
+    }
+
+    /// Creates an executor from the genesis file GENESIS_FILE_LOCATION, with the on-chain configs
+    /// set in `configs` replacing the genesis ones before anything runs.
+    pub fn from_genesis_with_configs(configs: GenesisConfigs) -> Self {
+        let mut executor = match configs.publishing_option {
+            Some(publishing_option) => Self::from_genesis_with_options(publishing_option),
+            None => Self::from_genesis_file(),
+        };
+        if let Some(gas_schedule) = configs.gas_schedule {
+            executor.set_gas_schedule(gas_schedule);
+        }
+        for (access_path, blob) in configs.configs {
+            executor.data_store.set(access_path, blob);
+        }
+        executor
+    }
+
+    /// Creates an executor like [`FakeExecutor::from_genesis_with_options`], with the dual
+    /// attestation limit set to `micro_lbr` instead of its genesis value.
+    ///
+    /// Payments between VASPs of at least that much, once converted to micro LBR, need a signature
+    /// from the payee's compliance key, so a low limit keeps the amounts of such tests small.
+    pub fn from_genesis_with_dual_attestation_limit(
+        micro_lbr: u64,
+        publishing_options: VMPublishingOption,
+    ) -> Self {
+        let mut executor = Self::from_genesis_with_options(publishing_options);
+        let limit = Limit {
+            micro_lbr_limit: micro_lbr,
+        };
+        executor.data_store.set(
+            AccessPath::new(libra_root_address(), Limit::resource_path()),
+            lcs::to_bytes(&limit).expect("Failure encoding the dual attestation limit"),
+        );
+        executor
+    }
+
+    /// Creates an executor running against `data_store`, such as the state fetched by a
+    /// [`RemoteDataStore`][crate::remote_data_store::RemoteDataStore].
+    pub fn from_data_store(data_store: FakeDataStore) -> Self {
+        FakeExecutor {
+            data_store,
+            block_time: GENESIS_BLOCK_TIME,
+            recording: RefCell::new(None),
+        }
+    }
+
     /// Creates an executor in which no genesis state has been applied yet.
     pub fn no_genesis() -> Self {
         FakeExecutor {
             data_store: FakeDataStore::default(),
-            block_time: 0,
+            block_time: GENESIS_BLOCK_TIME,
+            recording: RefCell::new(None),
         }
     }
 
@@ -107,6 +599,41 @@ impl FakeExecutor {
         validator_accounts: Option<usize>,
         publishing_options: VMPublishingOption,
     ) -> Self {
+        Self::try_custom_genesis(genesis_modules, validator_accounts, publishing_options)
+            .unwrap_or_else(|err| panic!("Invalid genesis modules: {:#}", err))
+    }
+
+    /// Creates fresh genesis from the stdlib modules passed in, returning an error instead of
+    /// panicking if a module depends on one that isn't part of `genesis_modules`, or if the
+    /// `Genesis` module that sets up the initial accounts is missing.
+    ///
+    /// Modules can be passed in any order. Use [`stdlib_modules_with`] to replace or add to some
+    /// of the standard library modules.
+    pub fn try_custom_genesis(
+        genesis_modules: Vec<CompiledModule>,
+        validator_accounts: Option<usize>,
+        publishing_options: VMPublishingOption,
+    ) -> anyhow::Result<Self> {
+        let module_ids: BTreeSet<_> = genesis_modules.iter().map(|m| m.self_id()).collect();
+        let genesis_module_id = ModuleId::new(CORE_CODE_ADDRESS, Identifier::new("Genesis")?);
+        anyhow::ensure!(
+            module_ids.contains(&genesis_module_id),
+            "missing module {}, which genesis runs to initialize accounts",
+            genesis_module_id,
+        );
+        for module in &genesis_modules {
+            let self_id = module.self_id();
+            for handle in module.module_handles() {
+                let dependency = module.module_id_for_handle(handle);
+                anyhow::ensure!(
+                    dependency == self_id || module_ids.contains(&dependency),
+                    "module {} depends on missing module {}",
+                    self_id,
+                    dependency,
+                );
+            }
+        }
+
         let genesis_change_set = {
             let validator_count = validator_accounts.map_or(10, |s| s);
             let swarm = generator::validator_swarm_for_testing(validator_count);
@@ -119,7 +646,7 @@ impl FakeExecutor {
             )
             .0
         };
-        Self::from_genesis(genesis_change_set.write_set())
+        Ok(Self::from_genesis(genesis_change_set.write_set()))
     }
 
     /// Creates a number of [`Account`] instances all with the same balance and sequence number,
@@ -136,21 +663,249 @@ impl FakeExecutor {
 
     /// Applies a [`WriteSet`] to this executor's data store.
     pub fn apply_write_set(&mut self, write_set: &WriteSet) {
+        self.record(LogEntry::WriteSet(write_set.clone()));
         self.data_store.add_write_set(write_set);
     }
 
     /// Adds an account to this executor's data store.
     pub fn add_account_data(&mut self, account_data: &AccountData) {
-        self.data_store.add_account_data(account_data)
+        self.apply_write_set(&account_data.to_writeset())
+    }
+
+    /// Creates `count` new accounts holding `balance` LBR each, and adds them to this executor's
+    /// data store.
+    ///
+    /// The accounts are random. Use [`account::gen_accounts`][crate::account::gen_accounts] to get
+    /// the same accounts on every run.
+    pub fn add_funded_accounts(&mut self, count: usize, balance: u64) -> Vec<AccountData> {
+        (0..count)
+            .map(|_| {
+                let account = AccountData::new(balance, 0);
+                self.add_account_data(&account);
+                account
+            })
+            .collect()
+    }
+
+    /// Gives `amount` of `currency` to `account` and applies it, minting it from the testnet
+    /// designated dealer.
+    ///
+    /// If `account` doesn't exist on chain yet, it is created by Libra root first, holding only
+    /// `currency`. If it exists without a balance in `currency`, that balance is added by a
+    /// transaction from `account` itself. Panics if any of these transactions fails, e.g. because
+    /// `amount` is above the dual attestation limit that testnet mints are held to.
+    pub fn fund_account(&mut self, account: &Account, amount: u64, currency: &str) {
+        let currency_code =
+            from_currency_code_string(currency).expect("currency code must be an identifier");
+        let currency_tag = type_tag_for_currency_code(currency_code.clone());
+        match self.sequence_number(account.address()) {
+            None => {
+                let libra_root = Account::new_libra_root();
+                let seq_num = self
+                    .sequence_number(libra_root.address())
+                    .expect("Libra root must exist after genesis");
+                self.execute_and_apply(libra_root.signed_script_txn(
+                    encode_create_testing_account_script(
+                        currency_tag.clone(),
+                        *account.address(),
+                        account.auth_key_prefix(),
+                        false, // add_all_currencies
+                    ),
+                    seq_num,
+                ));
+            }
+            Some(seq_num) => {
+                if self.read_balance_resource(account, currency_code).is_none() {
+                    self.execute_and_apply(account.signed_script_txn(
+                        encode_add_currency_to_account_script(currency_tag.clone()),
+                        seq_num,
+                    ));
+                }
+            }
+        }
+
+        let dd = Account::new_genesis_account(testnet_dd_account_address());
+        let seq_num = self
+            .sequence_number(dd.address())
+            .expect("the testnet designated dealer must exist");
+        self.execute_and_apply(dd.signed_script_txn(
+            encode_testnet_mint_script(currency_tag, *account.address(), amount),
+            seq_num,
+        ));
+    }
+
+    /// Creates a new parent VASP account holding a zero balance in `currency`, on behalf of Libra
+    /// root, and returns it. Its compliance key is its own public key.
+    ///
+    /// Accounts created from [`AccountData`] only have the parent VASP role: they can't have
+    /// children since the `VASP::ParentVASP` resource isn't published under them.
+    pub fn create_parent_vasp(&mut self, currency: &str) -> Account {
+        let currency_code =
+            from_currency_code_string(currency).expect("currency code must be an identifier");
+        let parent = Account::new();
+        let libra_root = Account::new_libra_root();
+        let seq_num = self
+            .sequence_number(libra_root.address())
+            .expect("Libra root must exist after genesis");
+        self.execute_and_apply(libra_root.signed_script_txn(
+            encode_create_parent_vasp_account_script(
+                type_tag_for_currency_code(currency_code),
+                *parent.address(),
+                parent.auth_key_prefix(),
+                vec![],
+                vec![],
+                parent.pubkey.to_bytes().to_vec(),
+                false, // add_all_currencies
+            ),
+            seq_num,
+        ));
+        parent
+    }
+
+    /// Creates a new child VASP account of `parent`, holding `initial_balance` of `currency` paid
+    /// by `parent`, with a transaction from `parent` using sequence number `seq_num`. Returns the
+    /// new account, or panics if the transaction fails, see
+    /// [`FakeExecutor::try_create_child_vasp`].
+    pub fn create_child_vasp(
+        &mut self,
+        parent: &Account,
+        initial_balance: u64,
+        currency: &str,
+        seq_num: u64,
+    ) -> Account {
+        self.try_create_child_vasp(parent, initial_balance, currency, seq_num)
+            .unwrap_or_else(|output| {
+                panic!(
+                    "creating a child VASP of {} failed: {:?}",
+                    parent.address(),
+                    output.status()
+                )
+            })
+    }
+
+    /// Like [`FakeExecutor::create_child_vasp`], but returns the output of the transaction if it
+    /// wasn't executed successfully, e.g. because `parent` isn't a parent VASP. The output is
+    /// applied if it is kept.
+    pub fn try_create_child_vasp(
+        &mut self,
+        parent: &Account,
+        initial_balance: u64,
+        currency: &str,
+        seq_num: u64,
+    ) -> Result<Account, TransactionOutput> {
+        let currency_code =
+            from_currency_code_string(currency).expect("currency code must be an identifier");
+        let child = Account::new();
+        let output = self.execute_transaction(parent.signed_script_txn(
+            encode_create_child_vasp_account_script(
+                type_tag_for_currency_code(currency_code),
+                *child.address(),
+                child.auth_key_prefix(),
+                false, // add_all_currencies
+                initial_balance,
+            ),
+            seq_num,
+        ));
+        if let TransactionStatus::Keep(_) = output.status() {
+            self.apply_write_set(output.write_set());
+        }
+        match output.status() {
+            TransactionStatus::Keep(VMStatus::Executed) => Ok(child),
+            _ => Err(output),
+        }
     }
 
     /// Adds a module to this executor's data store.
     ///
     /// Does not do any sort of verification on the module.
     pub fn add_module(&mut self, module_id: &ModuleId, module: &CompiledModule) {
+        if self.recording.get_mut().is_some() {
+            let mut blob = vec![];
+            module
+                .serialize(&mut blob)
+                .expect("serializing this module should work");
+            self.record(LogEntry::WriteSet(
+                WriteSetMut::new(vec![(AccessPath::from(module_id), WriteOp::Value(blob))])
+                    .freeze()
+                    .expect("a single write must freeze"),
+            ));
+        }
         self.data_store.add_module(module_id, module)
     }
 
+    /// Starts recording the transactions executed and the write sets applied by this executor,
+    /// replacing any previous recording. [`FakeExecutor::replay`] runs them again, starting from
+    /// the current state.
+    ///
+    /// Everything that goes through [`FakeExecutor::execute_block`] is recorded, which includes
+    /// [`FakeExecutor::execute_transaction`] and [`FakeExecutor::execute_and_apply`], as well as
+    /// every change made through [`FakeExecutor::apply_write_set`],
+    /// [`FakeExecutor::add_account_data`] and [`FakeExecutor::add_module`], and restoring a
+    /// snapshot with [`FakeExecutor::restore`].
+    pub fn start_recording(&mut self) {
+        *self.recording.get_mut() = Some(TransactionLog::new(
+            self.block_time,
+            self.data_store.to_sorted_map(),
+        ));
+    }
+
+    /// Stops recording and returns what was recorded since [`FakeExecutor::start_recording`].
+    pub fn stop_recording(&mut self) -> Option<TransactionLog> {
+        self.recording.get_mut().take()
+    }
+
+    /// Returns a copy of what was recorded so far, if this executor is recording.
+    pub fn transaction_log(&self) -> Option<TransactionLog> {
+        self.recording.borrow().clone()
+    }
+
+    fn record(&self, entry: LogEntry) {
+        if let Some(log) = self.recording.borrow_mut().as_mut() {
+            log.entries.push(entry);
+        }
+    }
+
+    /// Creates an executor from the state `log` started from and replays everything it recorded.
+    ///
+    /// Panics, reporting both statuses, as soon as a transaction gets a different status than the
+    /// recorded one. Recorded write sets are applied as they were, so the state stays the one
+    /// the recorded statuses were computed against.
+    pub fn replay(log: &TransactionLog) -> Self {
+        let mut executor = FakeExecutor {
+            data_store: FakeDataStore::new(log.initial_state.clone().into_iter().collect()),
+            block_time: log.initial_block_time,
+            recording: RefCell::new(None),
+        };
+        for (entry_idx, entry) in log.entries.iter().enumerate() {
+            match entry {
+                LogEntry::Block(txns, statuses) => {
+                    let outputs = executor
+                        .execute_block(txns.clone())
+                        .expect("The VM should not fail to startup");
+                    for (txn_idx, (output, status)) in outputs.iter().zip(statuses).enumerate() {
+                        assert!(
+                            output.status() == status,
+                            "transaction {} of log entry {} replayed with status {:?}, \
+                             recorded {:?}",
+                            txn_idx,
+                            entry_idx,
+                            output.status(),
+                            status,
+                        );
+                    }
+                }
+                LogEntry::WriteSet(write_set) => executor.apply_write_set(write_set),
+            }
+        }
+        executor
+    }
+
+    /// Loads a log written by [`TransactionLog::save`] and replays it like
+    /// [`FakeExecutor::replay`].
+    pub fn replay_from(path: impl AsRef<Path>) -> Self {
+        Self::replay(&TransactionLog::load(path))
+    }
+
     /// Reads the resource [`Value`] for an account from this executor's data store.
     pub fn read_account_resource(&self, account: &Account) -> Option<AccountResource> {
         let ap = account.make_account_access_path();
@@ -160,6 +915,20 @@ impl FakeExecutor {
         lcs::from_bytes(data_blob.as_slice()).ok()
     }
 
+    /// Returns the current epoch, from the on-chain configuration.
+    pub fn epoch(&self) -> u64 {
+        self.read_resource::<ConfigurationResource>(&libra_root_address())
+            .expect("Unable to retrieve the on-chain configuration from storage")
+            .epoch()
+    }
+
+    /// Returns the sequence number of the account at `address`, or `None` if there is no account
+    /// there.
+    pub fn sequence_number(&self, address: &AccountAddress) -> Option<u64> {
+        self.read_resource::<AccountResource>(address)
+            .map(|resource| resource.sequence_number())
+    }
+
     /// Reads the balance resource value for an account from this executor's data store with the
     /// given balance currency_code.
     pub fn read_balance_resource(
@@ -175,6 +944,159 @@ impl FakeExecutor {
             })
     }
 
+    /// Returns the balance of `account` in `currency` in display units, i.e. divided by the
+    /// currency's on-chain scaling factor. Use it for logging; balances should be compared in base
+    /// units, with [`FakeExecutor::assert_balance`].
+    pub fn balance_display(&self, account: &Account, currency: &str) -> f64 {
+        let currency_code =
+            from_currency_code_string(currency).expect("currency code must be an identifier");
+        let balance = self
+            .read_balance_resource(account, currency_code.clone())
+            .unwrap_or_else(|| panic!("{} holds no {} balance", account.address(), currency))
+            .coin();
+        self.to_display_units(balance, currency_code)
+    }
+
+    /// Asserts that `account` holds exactly `expected` base units of `currency`. On a mismatch,
+    /// both balances are reported in base and display units.
+    pub fn assert_balance(&self, account: &Account, currency: &str, expected: u64) {
+        let currency_code =
+            from_currency_code_string(currency).expect("currency code must be an identifier");
+        let actual = self
+            .read_balance_resource(account, currency_code.clone())
+            .unwrap_or_else(|| panic!("{} holds no {} balance", account.address(), currency))
+            .coin();
+        assert!(
+            actual == expected,
+            "{} balance of {} mismatch: expected {} ({} {}), got {} ({} {})",
+            currency,
+            account.address(),
+            expected,
+            self.to_display_units(expected, currency_code.clone()),
+            currency,
+            actual,
+            self.to_display_units(actual, currency_code),
+            currency,
+        );
+    }
+
+    fn to_display_units(&self, amount: u64, currency_code: Identifier) -> f64 {
+        let currency_info: CurrencyInfoResource = self
+            .read_from_access_path(&CurrencyInfoResource::resource_path_for(
+                currency_code.clone(),
+            ))
+            .map(|blob| lcs::from_bytes(&blob).expect("Failure decoding currency info"))
+            .unwrap_or_else(|| panic!("currency {} is not registered", currency_code));
+        amount as f64 / currency_info.scaling_factor() as f64
+    }
+
+    /// Returns the balances `address` holds, by currency code.
+    ///
+    /// Balances are stored under a hash of their type, so only the currencies registered on chain
+    /// are looked up.
+    pub fn balances(&self, address: &AccountAddress) -> BTreeMap<String, u64> {
+        let registered_currencies = RegisteredCurrencies::fetch_config(&self.data_store)
+            .expect("Unable to retrieve the registered currencies from storage");
+        registered_currencies
+            .currency_codes()
+            .iter()
+            .filter_map(|currency_code| {
+                let balance = self.read_resource_at::<BalanceResource>(
+                    address,
+                    vec![type_tag_for_currency_code(currency_code.clone())],
+                )?;
+                Some((currency_code.to_string(), balance.coin()))
+            })
+            .collect()
+    }
+
+    /// Asserts that the currencies `address` holds a balance in are exactly `expected`.
+    ///
+    /// Only the currencies registered on chain are looked up, like [`FakeExecutor::balances`].
+    pub fn assert_currencies(&self, address: &AccountAddress, expected: &[&str]) {
+        let actual: BTreeSet<String> = self.balances(address).keys().cloned().collect();
+        let expected: BTreeSet<String> = expected.iter().map(|code| code.to_string()).collect();
+        let missing: Vec<_> = expected.difference(&actual).collect();
+        let unexpected: Vec<_> = actual.difference(&expected).collect();
+        assert!(
+            missing.is_empty() && unexpected.is_empty(),
+            "currencies held by {} differ from the expected ones: missing {:?}, unexpected {:?}",
+            address,
+            missing,
+            unexpected,
+        );
+    }
+
+    /// Asserts that the on-chain sequence number of `account` accounts for exactly the kept
+    /// transactions in `outputs`, which must all have been sent by `account` and applied.
+    pub fn assert_sequence_after(&self, account: &AccountData, outputs: &[TransactionOutput]) {
+        let kept = outputs
+            .iter()
+            .filter(|output| !output.status().is_discarded())
+            .count();
+        let sequence_number = self
+            .read_account_resource(account.account())
+            .expect("account must exist")
+            .sequence_number();
+        assert_eq!(
+            sequence_number,
+            account.expected_sequence_after(kept),
+            "unexpected sequence number after {} kept out of {} transactions",
+            kept,
+            outputs.len(),
+        );
+    }
+
+    /// Reads the role published under an account from this executor's data store, or `None` if
+    /// the account has no role.
+    pub fn read_role(&self, account: &Account) -> Option<Role> {
+        let ap = account.make_access_path(AccountRoleSpecifier::role_id_struct_tag());
+        StateView::get(&self.data_store, &ap)
+            .unwrap_or_else(|_| panic!("account {:?} must exist in data store", account.address()))
+            .map(|data_blob| decode_role(&data_blob))
+    }
+
+    /// Reads the gas constants from the on-chain VM config in this executor's data store.
+    pub fn read_gas_constants(&self) -> GasConstants {
+        self.read_gas_schedule().gas_constants
+    }
+
+    /// Reads the gas schedule from the on-chain VM config in this executor's data store.
+    pub fn read_gas_schedule(&self) -> CostTable {
+        VMConfig::fetch_config(&self.data_store)
+            .expect("Unable to retrieve the VM config from storage")
+            .gas_schedule
+    }
+
+    /// Replaces the gas schedule in the on-chain VM config in this executor's data store.
+    /// Transactions executed from then on are metered against `gas_schedule`.
+    pub fn set_gas_schedule(&mut self, gas_schedule: CostTable) {
+        let vm_config = VMConfig::fetch_config(&self.data_store)
+            .expect("Unable to retrieve the VM config from storage");
+        // On chain, the publishing option and the cost tables are stored as nested LCS blobs.
+        let blob = lcs::to_bytes(&(
+            lcs::to_bytes(&vm_config.publishing_option).expect("Failure encoding config"),
+            (
+                lcs::to_bytes(&gas_schedule.instruction_table).expect("Failure encoding config"),
+                lcs::to_bytes(&gas_schedule.native_table).expect("Failure encoding config"),
+                gas_schedule.gas_constants,
+            ),
+        ))
+        .expect("Failure encoding config");
+        self.data_store.set(VMConfig::CONFIG_ID.access_path(), blob);
+    }
+
+    /// Replaces the on-chain config `T` in this executor's data store with `config`.
+    ///
+    /// This works for the configs stored as the LCS encoding of their Rust type, which is all of
+    /// them but [`VMConfig`]: use [`FakeExecutor::set_gas_schedule`] for that one.
+    pub fn set_config<T: OnChainConfig + Serialize>(&mut self, config: &T) {
+        self.data_store.set(
+            T::CONFIG_ID.access_path(),
+            lcs::to_bytes(config).expect("Failure encoding config"),
+        );
+    }
+
     /// Executes the given block of transactions.
     ///
     /// Typical tests will call this method and check that the output matches what was expected.
@@ -183,13 +1105,132 @@ impl FakeExecutor {
         &self,
         txn_block: Vec<SignedTransaction>,
     ) -> Result<Vec<TransactionOutput>, VMStatus> {
-        LibraVM::execute_block(
+        let recorded = if self.recording.borrow().is_some() {
+            Some(txn_block.clone())
+        } else {
+            None
+        };
+        let outputs = LibraVM::execute_block(
             txn_block
                 .into_iter()
                 .map(Transaction::UserTransaction)
                 .collect(),
             &self.data_store,
-        )
+        )?;
+        if let Some(txns) = recorded {
+            let statuses = outputs
+                .iter()
+                .map(|output| output.status().clone())
+                .collect();
+            self.record(LogEntry::Block(txns, statuses));
+        }
+        Ok(outputs)
+    }
+
+    /// Executes the transactions like [`FakeExecutor::execute_block`], but runs them concurrently
+    /// where they don't conflict.
+    ///
+    /// The outputs are the same as the ones [`FakeExecutor::execute_block`] would return, so the
+    /// latter serves as an oracle for this one.
+    pub fn execute_block_parallel(
+        &self,
+        txn_block: Vec<SignedTransaction>,
+    ) -> Result<Vec<TransactionOutput>, VMStatus> {
+        self.execute_block_parallel_with_report(txn_block)
+            .map(|(outputs, _)| outputs)
+    }
+
+    /// Executes the transactions like [`FakeExecutor::execute_block_parallel`], also reporting
+    /// which transactions conflicted and had to be serialized.
+    ///
+    /// Every transaction is first executed on its own against the state before the block, on its
+    /// own thread, recording the access paths it reads. Going through the block in order, a
+    /// transaction whose reads and writes don't overlap the writes of any earlier transaction keeps
+    /// that output. Any other transaction is executed again against the state left by all the
+    /// earlier ones. Transactions after a reconfiguration are left to be retried, like the VM does.
+    pub fn execute_block_parallel_with_report(
+        &self,
+        txn_block: Vec<SignedTransaction>,
+    ) -> Result<(Vec<TransactionOutput>, ParallelExecutionReport), VMStatus> {
+        let base = Arc::new(self.data_store.clone());
+        let handles: Vec<_> = txn_block
+            .iter()
+            .cloned()
+            .map(|txn| {
+                let base = Arc::clone(&base);
+                thread::spawn(move || {
+                    let view = ReadRecordingView::new(&base);
+                    let output = Self::execute_alone(txn, &view);
+                    (output, view.into_reads())
+                })
+            })
+            .collect();
+        let speculative: Vec<_> = handles
+            .into_iter()
+            .map(|handle| handle.join().expect("speculative execution must not panic"))
+            .collect();
+
+        let mut report = ParallelExecutionReport::default();
+        let mut state = (*base).clone();
+        let mut last_writers: HashMap<AccessPath, usize> = HashMap::new();
+        let mut outputs = Vec::with_capacity(txn_block.len());
+        let mut should_restart = false;
+        for (idx, (txn, (output, reads))) in txn_block.into_iter().zip(speculative).enumerate() {
+            if should_restart {
+                outputs.push(TransactionOutput::new(
+                    WriteSet::default(),
+                    vec![],
+                    0,
+                    TransactionStatus::Retry,
+                ));
+                continue;
+            }
+            let output = output?;
+            let conflicts: BTreeSet<usize> = reads
+                .iter()
+                .chain(
+                    output
+                        .write_set()
+                        .iter()
+                        .map(|(access_path, _)| access_path),
+                )
+                .filter_map(|access_path| last_writers.get(access_path).copied())
+                .collect();
+            let output = if conflicts.is_empty() {
+                output
+            } else {
+                report.serialized.push(idx);
+                report
+                    .conflicts
+                    .extend(conflicts.into_iter().map(|writer| (writer, idx)));
+                Self::execute_alone(txn, &state)?
+            };
+
+            if !output.status().is_discarded() {
+                state.add_write_set(output.write_set());
+                for (access_path, _) in output.write_set() {
+                    last_writers.insert(access_path.clone(), idx);
+                }
+            }
+            let new_epoch_event_key = new_epoch_event_key();
+            should_restart = output
+                .events()
+                .iter()
+                .any(|event| *event.key() == new_epoch_event_key);
+            outputs.push(output);
+        }
+        Ok((outputs, report))
+    }
+
+    pub(crate) fn execute_alone(
+        txn: SignedTransaction,
+        state_view: &dyn StateView,
+    ) -> Result<TransactionOutput, VMStatus> {
+        let mut outputs =
+            LibraVM::execute_block(vec![Transaction::UserTransaction(txn)], state_view)?;
+        Ok(outputs
+            .pop()
+            .expect("A block with one transaction should have one output"))
     }
 
     /// Executes the transaction as a singleton block and applies the resulting write set to the
@@ -213,12 +1254,135 @@ impl FakeExecutor {
         }
     }
 
-    pub fn execute_transaction_block(
-        &self,
-        txn_block: Vec<Transaction>,
-    ) -> Result<Vec<TransactionOutput>, VMStatus> {
-        LibraVM::execute_block(txn_block, &self.data_store)
-    }
+    /// Executes the given block of transactions, applies their write sets to the data store and
+    /// reports how much the state grew as a result.
+    pub fn run_and_measure_growth(&mut self, txn_block: Vec<SignedTransaction>) -> StateGrowth {
+        let items_before = self.data_store.num_items() as i64;
+        let bytes_before = self.data_store.total_bytes() as i64;
+        let outputs = self
+            .execute_block(txn_block)
+            .expect("The VM should not fail to startup");
+        for output in &outputs {
+            self.apply_write_set(output.write_set());
+        }
+        StateGrowth {
+            resources_added: self.data_store.num_items() as i64 - items_before,
+            bytes_added: self.data_store.total_bytes() as i64 - bytes_before,
+        }
+    }
+
+    /// Transfers `amount` of `currency` from `account` to itself and asserts that the only net
+    /// change to the account's balance is the gas fee.
+    ///
+    /// The transfer is run with a gas price of 1 and paid for in `currency`, so that the gas fee is
+    /// visible in the balance. Panics if the transfer does not execute successfully.
+    pub fn assert_self_transfer_net_zero(
+        &mut self,
+        account: &Account,
+        amount: u64,
+        currency: Identifier,
+    ) {
+        let balance_before = self
+            .read_balance_resource(account, currency.clone())
+            .expect("account balance must exist")
+            .coin();
+        let sequence_number = self
+            .read_account_resource(account)
+            .expect("account must exist")
+            .sequence_number();
+
+        let txn = account.create_signed_txn_with_args(
+            StdlibScript::PeerToPeerWithMetadata
+                .compiled_bytes()
+                .into_vec(),
+            vec![type_tag_for_currency_code(currency.clone())],
+            vec![
+                TransactionArgument::Address(*account.address()),
+                TransactionArgument::U64(amount),
+                TransactionArgument::U8Vector(vec![]),
+                TransactionArgument::U8Vector(vec![]),
+            ],
+            sequence_number,
+            gas_costs::TXN_RESERVED,
+            1,
+            currency.as_str().to_owned(),
+        );
+        let gas_unit_price = txn.gas_unit_price();
+        let output = self.execute_and_apply(txn);
+
+        let balance_after = self
+            .read_balance_resource(account, currency)
+            .expect("account balance must exist")
+            .coin();
+        assert_eq!(
+            balance_before - balance_after,
+            output.gas_used() * gas_unit_price,
+            "self-transfer of {} from {} should only be charged for gas",
+            amount,
+            account.address(),
+        );
+    }
+
+    /// Publishes a [`module_chain`] of up to `max_depth` modules from `account`, one module at a
+    /// time, and returns the first dependency depth the VM refuses to publish.
+    ///
+    /// Returns `None` if every depth up to `max_depth` is accepted. The VM does not currently
+    /// configure a limit on the dependency depth of published modules, so there is no constant to
+    /// compare the result against.
+    pub fn find_module_dependency_depth_limit(
+        &mut self,
+        account: &Account,
+        max_depth: usize,
+    ) -> Option<usize> {
+        let mut sequence_number = self
+            .read_account_resource(account)
+            .expect("account must exist")
+            .sequence_number();
+        for (idx, module) in module_chain(account.address(), max_depth)
+            .into_iter()
+            .enumerate()
+        {
+            let mut blob = vec![];
+            module
+                .serialize(&mut blob)
+                .expect("serializing this module should work");
+            let txn = account.create_signed_txn_impl(
+                *account.address(),
+                TransactionPayload::Module(Module::new(blob)),
+                sequence_number,
+                gas_costs::TXN_RESERVED,
+                0,
+                LBR_NAME.to_owned(),
+            );
+            let output = self.execute_transaction(txn);
+            if output.status() != &TransactionStatus::Keep(VMStatus::Executed) {
+                return Some(idx + 1);
+            }
+            self.apply_write_set(output.write_set());
+            sequence_number += 1;
+        }
+        None
+    }
+
+    /// Executes the transaction as a singleton block, applies the resulting write set to the data
+    /// store and returns its status together with where it aborted and with which code.
+    pub fn execute_decoded(&mut self, txn: SignedTransaction) -> DecodedOutcome {
+        let output = self.execute_transaction(txn);
+        self.apply_write_set(output.write_set());
+        let vm_status = output.status().vm_status();
+        DecodedOutcome {
+            status: output.status().clone(),
+            abort_code: vm_status.move_abort_code(),
+            abort_module: abort_module(&vm_status),
+        }
+    }
+
+    pub fn execute_transaction_block(
+        &self,
+        txn_block: Vec<Transaction>,
+    ) -> Result<Vec<TransactionOutput>, VMStatus> {
+        LibraVM::execute_block(txn_block, &self.data_store)
+    }
 
     pub fn execute_transaction(&self, txn: SignedTransaction) -> TransactionOutput {
         let txn_block = vec![txn];
@@ -230,32 +1394,477 @@ impl FakeExecutor {
             .expect("A block with one transaction should have one output")
     }
 
+    /// Executes the transaction like [`FakeExecutor::execute_transaction`], also returning what was
+    /// logged while executing it, such as the errors the VM logs on invariant violations.
+    ///
+    /// Only the messages logged on this thread during this execution are returned, so no global
+    /// logger needs to be configured.
+    pub fn execute_transaction_with_logs(
+        &self,
+        txn: SignedTransaction,
+    ) -> (TransactionOutput, Vec<VMLogEntry>) {
+        capture_logs(|| self.execute_transaction(txn))
+    }
+
+    /// Executes the transaction like [`FakeExecutor::execute_transaction`], also returning how long
+    /// it took in total and in each phase. See [`ExecutionTiming`] for how the phases are timed.
+    pub fn execute_transaction_timed(
+        &self,
+        txn: SignedTransaction,
+    ) -> (TransactionOutput, ExecutionTiming) {
+        let timing = Rc::new(RefCell::new(ExecutionTiming::default()));
+        let recorded = Rc::clone(&timing);
+        let start = Instant::now();
+        let output = with_phase_hook(
+            move |event| {
+                let mut timing = recorded.borrow_mut();
+                match event.phase {
+                    TransactionPhase::Prologue => timing.verification += event.duration,
+                    TransactionPhase::Payload => timing.execution += event.duration,
+                    TransactionPhase::SuccessEpilogue | TransactionPhase::FailureEpilogue => {
+                        timing.epilogue += event.duration
+                    }
+                }
+            },
+            || self.execute_transaction(txn),
+        );
+        let total = start.elapsed();
+        let timing = ExecutionTiming {
+            total,
+            ..*timing.borrow()
+        };
+        (output, timing)
+    }
+
+    /// Executes the transaction like [`FakeExecutor::execute_transaction`], also tracing where its
+    /// Move code aborted, be it in the prologue, the script or the epilogue.
+    ///
+    /// The abort is found by watching the instructions the VM executes, see
+    /// [`FakeExecutor::execute_transaction_with_hook`], so it's only traced in debug builds.
+    pub fn execute_transaction_with_trace(
+        &self,
+        txn: SignedTransaction,
+    ) -> (TransactionOutput, ExecutionTrace) {
+        let abort_frame = Rc::new(RefCell::new(None));
+        let recorded = Rc::clone(&abort_frame);
+        let output = self.execute_transaction_with_hook(txn, move |event| {
+            if let Bytecode::Abort = event.instruction {
+                *recorded.borrow_mut() = Some((event.function.to_string(), event.pc));
+            }
+        });
+        let abort_frame = abort_frame.replace(None);
+        (output, ExecutionTrace { abort_frame })
+    }
+
+    /// Executes the transaction like [`FakeExecutor::execute_transaction`], calling `hook` before
+    /// every instruction the VM executes for it, including the prologue and the epilogue.
+    ///
+    /// The VM only traces instructions in debug builds, so the hook isn't called in release ones.
+    pub fn execute_transaction_with_hook(
+        &self,
+        txn: SignedTransaction,
+        hook: impl FnMut(&TraceEvent) + 'static,
+    ) -> TransactionOutput {
+        with_trace_hook(hook, || self.execute_transaction(txn))
+    }
+
+    /// Executes the transaction like [`FakeExecutor::execute_transaction`], also recording every
+    /// instruction the VM executes for it, in order, along with the call depth and the operand
+    /// stack. See [`FakeExecutor::execute_transaction_with_hook`].
+    pub fn execute_transaction_with_instructions(
+        &self,
+        txn: SignedTransaction,
+    ) -> (TransactionOutput, Vec<TracedInstruction>) {
+        let instructions = Rc::new(RefCell::new(vec![]));
+        let recorded = Rc::clone(&instructions);
+        let output = self.execute_transaction_with_hook(txn, move |event| {
+            recorded.borrow_mut().push(TracedInstruction {
+                function: event.function.to_string(),
+                pc: event.pc,
+                instruction: event.instruction.clone(),
+                call_depth: event.call_depth,
+                operand_stack: event
+                    .operand_stack
+                    .iter()
+                    .map(|value| value.to_string())
+                    .collect(),
+            })
+        });
+        let instructions = instructions.replace(vec![]);
+        (output, instructions)
+    }
+
+    /// Executes the transaction like [`FakeExecutor::execute_transaction`], also recording what
+    /// it had written after its body and after its success epilogue, to expose the state in
+    /// between. The prologue only checks the transaction, so it isn't staged.
+    ///
+    /// The write sets are taken from the VM as it executes the transaction, see
+    /// [`with_phase_hook`].
+    pub fn execute_transaction_staged(&self, txn: SignedTransaction) -> StagedExecution {
+        let phases = Rc::new(RefCell::new(vec![]));
+        let recorded = Rc::clone(&phases);
+        let output = with_phase_hook(
+            move |event| recorded.borrow_mut().push(event.clone()),
+            || self.execute_transaction(txn),
+        );
+        let phases = phases.replace(vec![]);
+        let phase_result = |phase: TransactionPhase| {
+            phases
+                .iter()
+                .find(|event| event.phase == phase)
+                .map(|event| match &event.error {
+                    Some(err) => Err(err.clone()),
+                    None => Ok(event
+                        .write_set
+                        .clone()
+                        .expect("Failed to generate writeset")),
+                })
+        };
+        StagedExecution {
+            output,
+            body: phase_result(TransactionPhase::Payload),
+            epilogue: phase_result(TransactionPhase::SuccessEpilogue),
+        }
+    }
+
+    /// Executes the transaction like [`FakeExecutor::execute_transaction`], also measuring the
+    /// storage it read and wrote.
+    pub fn execute_transaction_with_footprint(
+        &self,
+        txn: SignedTransaction,
+    ) -> (TransactionOutput, StorageFootprint) {
+        let view = ReadRecordingView::new(&self.data_store);
+        let output = Self::execute_alone(txn, &view).expect("The VM should not fail to startup");
+        let reads = view.into_reads();
+        let mut footprint = StorageFootprint::of_write_set(output.write_set());
+        footprint.reads = reads.len();
+        footprint.bytes_read = reads
+            .iter()
+            .filter_map(|access_path| self.read_from_access_path(access_path))
+            .map(|blob| blob.len())
+            .sum();
+        (output, footprint)
+    }
+
+    /// Describes the changes `write_set` would make to this executor's data store.
+    pub fn diff_write_set(&self, write_set: &WriteSet) -> WriteSetDiff {
+        diff_write_sets(&self.data_store, write_set)
+    }
+
+    /// Returns the addresses of the accounts `output` creates, i.e. that have no `LibraAccount`
+    /// resource in this executor's data store yet. The output must not have been applied.
+    pub fn created_accounts(&self, output: &TransactionOutput) -> Vec<AccountAddress> {
+        self.diff_write_set(output.write_set()).created_accounts()
+    }
+
+    /// Asserts that `output` creates exactly the accounts in `expected`, in any order, naming the
+    /// ones created unexpectedly and the ones missing if not. The output must not have been
+    /// applied.
+    pub fn assert_created_accounts(&self, output: &TransactionOutput, expected: &[AccountAddress]) {
+        let created: BTreeSet<_> = self.created_accounts(output).into_iter().collect();
+        let expected: BTreeSet<_> = expected.iter().copied().collect();
+        assert!(
+            created == expected,
+            "unexpected accounts created by a transaction with status {:?}: \
+             extra {:?}, missing {:?}",
+            output.status(),
+            created.difference(&expected).collect::<Vec<_>>(),
+            expected.difference(&created).collect::<Vec<_>>(),
+        );
+    }
+
+    /// Describes the changes that would turn this executor's data store into the one of `other`,
+    /// decoding the modules and resources involved where possible.
+    pub fn diff_state(&self, other: &FakeExecutor) -> WriteSetDiff {
+        diff_data_stores(&self.data_store, &other.data_store)
+    }
+
+    /// Asserts that this executor's data store holds exactly the same data as the one of `other`.
+    /// Otherwise, the message lists every access path that differs, like
+    /// [`FakeExecutor::diff_state`].
+    pub fn assert_state_eq(&self, other: &FakeExecutor) {
+        let diff = self.diff_state(other);
+        assert!(
+            diff.entries.is_empty(),
+            "executors have different states, going from this one to the other:\n{}",
+            diff
+        );
+    }
+
     /// Get the blob for the associated AccessPath
     pub fn read_from_access_path(&self, path: &AccessPath) -> Option<Vec<u8>> {
         StateView::get(&self.data_store, path).unwrap()
     }
 
+    /// Reads the resource `T` published under `address` from this executor's data store.
+    ///
+    /// Returns `None` if there is no such resource.
+    pub fn read_resource<T: MoveResource + DeserializeOwned>(
+        &self,
+        address: &AccountAddress,
+    ) -> Option<T> {
+        self.read_resource_at(address, T::type_params())
+    }
+
+    /// Reads the instance of the generic resource `T` with the given type arguments (e.g. the
+    /// `Balance<Coin1>` of an account) published under `address` from this executor's data store.
+    ///
+    /// Returns `None` if there is no such resource.
+    pub fn read_resource_at<T: MoveResource + DeserializeOwned>(
+        &self,
+        address: &AccountAddress,
+        type_args: Vec<TypeTag>,
+    ) -> Option<T> {
+        let struct_tag = StructTag {
+            type_params: type_args,
+            ..T::struct_tag()
+        };
+        let blob =
+            self.read_from_access_path(&AccessPath::new(*address, struct_tag.access_vector()))?;
+        Some(
+            lcs::from_bytes(&blob)
+                .unwrap_or_else(|err| panic!("Failure decoding resource {}: {}", struct_tag, err)),
+        )
+    }
+
+    /// Returns whether the resource `T` is published under `address` in this executor's data
+    /// store.
+    pub fn exists_resource<T: MoveResource>(&self, address: &AccountAddress) -> bool {
+        self.exists_resource_at::<T>(address, T::type_params())
+    }
+
+    /// Returns whether the instance of the generic resource `T` with the given type arguments is
+    /// published under `address` in this executor's data store.
+    pub fn exists_resource_at<T: MoveResource>(
+        &self,
+        address: &AccountAddress,
+        type_args: Vec<TypeTag>,
+    ) -> bool {
+        let struct_tag = StructTag {
+            type_params: type_args,
+            ..T::struct_tag()
+        };
+        self.read_from_access_path(&AccessPath::new(*address, struct_tag.access_vector()))
+            .is_some()
+    }
+
+    /// Publishes `value` as the resource `T` under `address`, replacing any existing one, directly
+    /// in this executor's data store. Transactions executed afterwards read the new value.
+    ///
+    /// This is a test setup tool which bypasses the VM: nothing checks that the resulting state is
+    /// one the Move code could ever reach, e.g. that balances add up to the total supply, so tests
+    /// using it may observe behavior that can't happen on chain.
+    pub fn update_resource<T: MoveResource + Serialize>(
+        &mut self,
+        address: &AccountAddress,
+        value: T,
+    ) {
+        self.update_resource_at(address, T::type_params(), value)
+    }
+
+    /// Like [`FakeExecutor::update_resource`], for the instance of the generic resource `T` with
+    /// the given type arguments.
+    pub fn update_resource_at<T: MoveResource + Serialize>(
+        &mut self,
+        address: &AccountAddress,
+        type_args: Vec<TypeTag>,
+        value: T,
+    ) {
+        let struct_tag = StructTag {
+            type_params: type_args,
+            ..T::struct_tag()
+        };
+        let blob = lcs::to_bytes(&value)
+            .unwrap_or_else(|err| panic!("Failure encoding resource {}: {}", struct_tag, err));
+        self.write_resource(address, struct_tag, WriteOp::Value(blob));
+    }
+
+    /// Removes the resource `T` from under `address` directly in this executor's data store, if
+    /// it is published there. Transactions executed afterwards don't see it anymore.
+    ///
+    /// Like [`FakeExecutor::update_resource`], this bypasses the VM and may leave the state
+    /// inconsistent, e.g. an account without its `LibraAccount` resource.
+    pub fn remove_resource<T: MoveResource>(&mut self, address: &AccountAddress) {
+        self.remove_resource_at::<T>(address, T::type_params())
+    }
+
+    /// Like [`FakeExecutor::remove_resource`], for the instance of the generic resource `T` with
+    /// the given type arguments.
+    pub fn remove_resource_at<T: MoveResource>(
+        &mut self,
+        address: &AccountAddress,
+        type_args: Vec<TypeTag>,
+    ) {
+        let struct_tag = StructTag {
+            type_params: type_args,
+            ..T::struct_tag()
+        };
+        self.write_resource(address, struct_tag, WriteOp::Deletion);
+    }
+
+    // Goes through `apply_write_set`, so that the change is recorded like any other.
+    fn write_resource(&mut self, address: &AccountAddress, struct_tag: StructTag, op: WriteOp) {
+        let write_set = WriteSetMut::new(vec![(
+            AccessPath::new(*address, struct_tag.access_vector()),
+            op,
+        )])
+        .freeze()
+        .expect("a single write should make a valid write set");
+        self.apply_write_set(&write_set);
+    }
+
+    /// Asserts that the resource `T` is published under `address`, e.g. after a transaction that
+    /// should publish it.
+    pub fn assert_resource_exists<T: MoveResource>(&self, address: &AccountAddress) {
+        assert!(
+            self.exists_resource::<T>(address),
+            "expected resource {} to be published under {}",
+            T::struct_tag(),
+            address,
+        );
+    }
+
+    /// Asserts that the resource `T` isn't published under `address`, e.g. after a transaction
+    /// that should remove it.
+    pub fn assert_no_resource<T: MoveResource>(&self, address: &AccountAddress) {
+        assert!(
+            !self.exists_resource::<T>(address),
+            "expected no resource {} under {}",
+            T::struct_tag(),
+            address,
+        );
+    }
+
+    /// Returns the modules published under `address` in this executor's data store, e.g. to
+    /// compile code depending on them.
+    pub fn published_modules(&self, address: &AccountAddress) -> Vec<CompiledModule> {
+        self.data_store.modules_at(address)
+    }
+
+    /// Returns the id and serialized bytes of every module published in this executor's data
+    /// store, at any address, sorted by id.
+    pub fn published_module_blobs(&self) -> impl Iterator<Item = (ModuleId, &[u8])> {
+        self.data_store.module_blobs()
+    }
+
+    /// Publishes the first version of a module from `sender`, then attempts to republish it with
+    /// the second one, e.g. as compiled by [`compile_module_upgrade`][crate::compile::compile_module_upgrade].
+    ///
+    /// The first version must be published successfully. The output of the second attempt is
+    /// returned and its write set applied if it is kept, so tests can assert on how the upgrade
+    /// was handled.
+    pub fn publish_module_upgrade(
+        &mut self,
+        sender: &Account,
+        sequence_number: u64,
+        (old, new): (TransactionPayload, TransactionPayload),
+    ) -> TransactionOutput {
+        let publish = |payload, sequence_number| {
+            sender.create_signed_txn_impl(
+                *sender.address(),
+                payload,
+                sequence_number,
+                gas_costs::TXN_RESERVED,
+                0,
+                LBR_NAME.to_owned(),
+            )
+        };
+        self.execute_and_apply(publish(old, sequence_number));
+        let output = self.execute_transaction(publish(new, sequence_number + 1));
+        if let TransactionStatus::Keep(_) = output.status() {
+            self.apply_write_set(output.write_set());
+        }
+        output
+    }
+
+    /// Lists the public functions of a module published in this executor's data store, along with
+    /// their signatures.
+    ///
+    /// Returns `None` if the module isn't published.
+    pub fn module_functions(
+        &self,
+        module_id: &ModuleId,
+    ) -> Option<Vec<(Identifier, FunctionSignature)>> {
+        let blob = self.read_from_access_path(&AccessPath::from(module_id))?;
+        let module = CompiledModule::deserialize(&blob).expect("published module must deserialize");
+        let functions = module
+            .function_defs()
+            .iter()
+            .filter(|function_def| function_def.is_public)
+            .map(|function_def| {
+                let handle = module.function_handle_at(function_def.function);
+                let signature = FunctionSignature {
+                    return_: module.signature_at(handle.return_).0.clone(),
+                    parameters: module.signature_at(handle.parameters).0.clone(),
+                    type_parameters: handle.type_parameters.clone(),
+                };
+                (module.identifier_at(handle.name).to_owned(), signature)
+            })
+            .collect();
+        Some(functions)
+    }
+
     /// Verifies the given transaction by running it through the VM verifier.
     pub fn verify_transaction(&self, txn: SignedTransaction) -> VMValidatorResult {
         let vm = LibraVMValidator::new(self.get_state_view());
         vm.validate_transaction(txn, &self.data_store)
     }
 
+    /// Verifies a batch of transactions in order, the way mempool prevalidates them, returning one
+    /// status per transaction.
+    ///
+    /// Each transaction is run through the VM verifier like [`FakeExecutor::verify_transaction`],
+    /// but sequence numbers are checked against a running view in which every transaction that
+    /// passed bumps its sender's sequence number. So consecutive transactions from one sender all
+    /// pass, while one reusing a sequence number of the batch is `SEQUENCE_NUMBER_TOO_OLD` and one
+    /// leaving a gap is `SEQUENCE_NUMBER_TOO_NEW`.
+    pub fn verify_block(&self, txn_block: Vec<SignedTransaction>) -> Vec<Option<VMStatus>> {
+        let mut next_sequence_numbers: HashMap<AccountAddress, u64> = HashMap::new();
+        txn_block
+            .into_iter()
+            .map(|txn| {
+                let sender = txn.sender();
+                let sequence_number = txn.sequence_number();
+                let expected = match next_sequence_numbers.get(&sender) {
+                    Some(expected) => *expected,
+                    None => self
+                        .read_resource::<AccountResource>(&sender)
+                        .map_or(sequence_number, |resource| resource.sequence_number()),
+                };
+                if sequence_number < expected {
+                    return Some(VMStatus::Error(StatusCode::SEQUENCE_NUMBER_TOO_OLD));
+                }
+                if sequence_number > expected {
+                    return Some(VMStatus::Error(StatusCode::SEQUENCE_NUMBER_TOO_NEW));
+                }
+                let status = self.verify_transaction(txn).status();
+                if status.is_none() {
+                    next_sequence_numbers.insert(sender, expected + 1);
+                }
+                status
+            })
+            .collect()
+    }
+
     pub fn get_state_view(&self) -> &FakeDataStore {
         &self.data_store
     }
 
     pub fn new_block(&mut self) {
-        let validator_set = ValidatorSet::fetch_config(&self.data_store)
-            .expect("Unable to retrieve the validator set from storage");
-        self.block_time += 1;
-        let new_block = BlockMetadata::new(
-            HashValue::zero(),
-            0,
-            self.block_time,
-            vec![],
-            *validator_set.payload()[0].account_address(),
-        );
+        self.new_block_with_timestamp(self.block_time + 1);
+    }
+
+    /// Runs `n` empty blocks, advancing the block time by `block_time_delta` microseconds for each
+    /// one. Useful to move past time windows that reset on-chain limits.
+    pub fn advance_blocks(&mut self, n: usize, block_time_delta: u64) {
+        for _ in 0..n {
+            self.new_block_with_timestamp(self.block_time + block_time_delta);
+        }
+    }
+
+    fn new_block_with_timestamp(&mut self, block_time: u64) {
+        self.block_time = block_time;
+        let new_block = self.new_block_metadata();
         let output = self
             .execute_transaction_block(vec![Transaction::BlockMetadata(new_block)])
             .expect("Executing block prologue should succeed")
@@ -268,6 +1877,371 @@ impl FakeExecutor {
         self.apply_write_set(output.write_set());
     }
 
+    /// Returns the metadata for a block at the current block time, proposed by the first validator.
+    ///
+    /// The VM only sees a new block time once a block prologue has run with it.
+    pub fn new_block_metadata(&self) -> BlockMetadata {
+        let validator_set = ValidatorSet::fetch_config(&self.data_store)
+            .expect("Unable to retrieve the validator set from storage");
+        BlockMetadata::new(
+            HashValue::zero(),
+            0,
+            self.block_time,
+            vec![],
+            *validator_set.payload()[0].account_address(),
+        )
+    }
+
+    /// Executes a block made of the block prologue for `metadata` followed by `txn_block`. The
+    /// first output is the prologue's.
+    pub fn execute_block_with_metadata(
+        &self,
+        txn_block: Vec<SignedTransaction>,
+        metadata: BlockMetadata,
+    ) -> Result<Vec<TransactionOutput>, VMStatus> {
+        let mut txns = vec![Transaction::BlockMetadata(metadata)];
+        txns.extend(txn_block.into_iter().map(Transaction::UserTransaction));
+        self.execute_transaction_block(txns)
+    }
+
+    /// Executes a block like [`FakeExecutor::execute_block_with_metadata`] and applies the outputs
+    /// that are kept, so that the block time and proposer of `metadata` are what later
+    /// transactions see. The first output is the prologue's, which must succeed.
+    pub fn apply_block_with_metadata(
+        &mut self,
+        txn_block: Vec<SignedTransaction>,
+        metadata: BlockMetadata,
+    ) -> Vec<TransactionOutput> {
+        let (_, timestamp_usecs, _, _) = metadata
+            .clone()
+            .into_inner()
+            .expect("block metadata must decode");
+        let outputs = self
+            .execute_block_with_metadata(txn_block, metadata)
+            .expect("The VM should not fail to startup");
+        assert_eq!(
+            outputs[0].status(),
+            &TransactionStatus::Keep(VMStatus::Executed),
+            "the block prologue must succeed"
+        );
+        for output in &outputs {
+            if let TransactionStatus::Keep(_) = output.status() {
+                self.apply_write_set(output.write_set());
+            }
+        }
+        self.block_time = timestamp_usecs;
+        outputs
+    }
+
+    /// Returns the on-chain block height.
+    pub fn get_block_height(&self) -> u64 {
+        self.read_block_resource().0
+    }
+
+    /// Overwrites the on-chain block height. The next block prologue runs at `height + 1`.
+    pub fn set_block_height(&mut self, height: u64) {
+        let (_, new_block_events) = self.read_block_resource();
+        self.data_store.set(
+            Self::block_resource_path(),
+            lcs::to_bytes(&(height, new_block_events)).expect("block resource must serialize"),
+        );
+    }
+
+    // `LibraBlockResource` doesn't expose its height, so decode its fields directly.
+    fn read_block_resource(&self) -> (u64, EventHandle) {
+        let blob = self
+            .read_from_access_path(&Self::block_resource_path())
+            .expect("block resource must exist");
+        lcs::from_bytes(&blob).expect("Failure decoding block resource")
+    }
+
+    fn block_resource_path() -> AccessPath {
+        AccessPath::new(libra_root_address(), LibraBlockResource::resource_path())
+    }
+
+    /// Takes a snapshot of this executor's state, which [`FakeExecutor::restore`] can roll back to.
+    pub fn snapshot(&self) -> StateSnapshot {
+        StateSnapshot {
+            data_store: self.data_store.clone(),
+            block_time: self.block_time,
+        }
+    }
+
+    /// Rolls this executor's state back to `snapshot`, discarding everything applied since.
+    ///
+    /// If this executor is recording, the rollback is recorded as the write set that turns the
+    /// current state into the snapshot's, so that replaying the log still branches the same way.
+    pub fn restore(&mut self, snapshot: StateSnapshot) {
+        if self.recording.get_mut().is_some() {
+            let write_set = write_set_between(&self.data_store, &snapshot.data_store);
+            self.record(LogEntry::WriteSet(write_set));
+        }
+        self.data_store = snapshot.data_store;
+        self.block_time = snapshot.block_time;
+    }
+
+    /// Returns a hash of the state in this executor's data store.
+    pub fn state_hash(&self) -> HashValue {
+        self.data_store.state_hash()
+    }
+
+    /// Executes `txn`, applies its write set and returns the gas it used.
+    pub fn execute_and_record_gas(&mut self, txn: SignedTransaction) -> u64 {
+        let output = self.execute_transaction(txn);
+        self.apply_write_set(output.write_set());
+        output.gas_used()
+    }
+
+    /// Executes `txn`, applies its write set and returns its status along with the events it
+    /// emitted. A discarded transaction emits no events.
+    pub fn execute_and_collect_events(
+        &mut self,
+        txn: SignedTransaction,
+    ) -> (TransactionStatus, Vec<ContractEvent>) {
+        let output = self.execute_transaction(txn);
+        self.apply_write_set(output.write_set());
+        (output.status().clone(), output.events().to_vec())
+    }
+
+    /// Executes the given block of transactions, applies the write sets of the ones that were
+    /// kept and returns their outputs along with all the events they emitted, in emission order.
+    ///
+    /// Events of discarded transactions are left out. Use [`filter_events`] to pick events of one
+    /// type out of the result.
+    pub fn execute_block_and_collect_events(
+        &mut self,
+        txn_block: Vec<SignedTransaction>,
+    ) -> (Vec<TransactionOutput>, Vec<ContractEvent>) {
+        let outputs = self
+            .execute_block(txn_block)
+            .expect("The VM should not fail to execute");
+        let mut events = vec![];
+        for output in &outputs {
+            if !output.status().is_discarded() {
+                self.apply_write_set(output.write_set());
+                events.extend_from_slice(output.events());
+            }
+        }
+        (outputs, events)
+    }
+
+    /// Executes `txn`, asserts that its write set contains no module writes and applies it.
+    ///
+    /// Transactions that don't publish code should never touch a module; any module write is
+    /// reported with its module id.
+    pub fn assert_no_module_published(&mut self, txn: SignedTransaction) -> TransactionOutput {
+        let output = self.execute_transaction(txn);
+        let module_writes: Vec<String> = output
+            .write_set()
+            .iter()
+            .filter(|(access_path, _)| access_path.path.first() == Some(&CODE_TAG))
+            .map(|(access_path, write_op)| match write_op {
+                WriteOp::Value(blob) => CompiledModule::deserialize(blob)
+                    .map(|module| module.self_id().to_string())
+                    .unwrap_or_else(|_| access_path.to_string()),
+                WriteOp::Deletion => format!("deletion of {}", access_path),
+            })
+            .collect();
+        assert!(
+            module_writes.is_empty(),
+            "transaction unexpectedly wrote modules: {:?}",
+            module_writes
+        );
+        self.apply_write_set(output.write_set());
+        output
+    }
+
+    /// Executes `txn` against a copy of this executor's state and returns its output, like a dry run
+    /// would. None of its effects are applied to this executor.
+    ///
+    /// This only borrows the executor immutably and has no side effects, so the status, gas,
+    /// events and write set it returns are a preview that can't end up in the state by accident.
+    /// Use [`FakeExecutor::execute_and_apply`] to actually run the transaction.
+    pub fn simulate(&self, txn: SignedTransaction) -> TransactionOutput {
+        let snapshot = self.data_store.clone();
+        LibraVM::execute_block(vec![Transaction::UserTransaction(txn)], &snapshot)
+            .expect("The VM should not fail to startup")
+            .pop()
+            .expect("A block with one transaction should have one output")
+    }
+
+    /// Executes `txn` under both this executor and `other`, applies each output to its own
+    /// executor and compares the two runs, e.g. to find the transactions of a corpus that behave
+    /// differently under another gas schedule or publishing option. Discarded outputs aren't
+    /// applied. If the runs diverge, a report of how is printed.
+    ///
+    /// The executors are expected to start from the same state apart from their configuration.
+    pub fn compare_run(
+        &mut self,
+        txn: SignedTransaction,
+        other: &mut FakeExecutor,
+    ) -> RunComparison {
+        let run = |executor: &mut FakeExecutor, txn| {
+            let output = executor.execute_transaction(txn);
+            if !output.status().is_discarded() {
+                executor.apply_write_set(output.write_set());
+            }
+            output
+        };
+        let comparison = RunComparison::new(run(self, txn.clone()), run(other, txn));
+        if comparison.diverges() {
+            println!("transaction runs diverge:\n{}", comparison);
+        }
+        comparison
+    }
+
+    /// Executes `txn` like [`FakeExecutor::execute_transaction`], but with its gas limit replaced by
+    /// `max_gas_amount`, e.g. to make it run out of gas at a chosen point. Nothing is applied.
+    ///
+    /// Changing the limit invalidates the signature, so the transaction is signed again by
+    /// `sender`, which must be the account that sent it.
+    pub fn execute_with_gas_limit(
+        &self,
+        sender: &Account,
+        txn: SignedTransaction,
+        max_gas_amount: u64,
+    ) -> TransactionOutput {
+        assert_eq!(
+            txn.sender(),
+            *sender.address(),
+            "the transaction must be signed again by its sender"
+        );
+        let txn = sender
+            .transaction()
+            .payload(txn.payload().clone())
+            .sequence_number(txn.sequence_number())
+            .max_gas_amount(max_gas_amount)
+            .gas_unit_price(txn.gas_unit_price())
+            .gas_currency_code(txn.gas_currency_code())
+            .ttl(txn.expiration_time().as_secs())
+            .sign();
+        self.execute_transaction(txn)
+    }
+
+    /// Sends `payload` from `account` with the sequence number the account currently has on chain,
+    /// executes it and applies its output, so that tests don't need to track sequence numbers.
+    ///
+    /// If the account doesn't exist, the transaction is sent with sequence number 0 and is
+    /// discarded with `SENDING_ACCOUNT_DOES_NOT_EXIST`.
+    pub fn submit_with_auto_seq(
+        &mut self,
+        account: &Account,
+        payload: TransactionPayload,
+        max_gas_amount: u64,
+        gas_unit_price: u64,
+        gas_currency_code: &str,
+    ) -> TransactionOutput {
+        let txn = account
+            .transaction()
+            .payload(payload)
+            .sequence_number(self.sequence_number(account.address()).unwrap_or(0))
+            .max_gas_amount(max_gas_amount)
+            .gas_unit_price(gas_unit_price)
+            .gas_currency_code(gas_currency_code)
+            .sign();
+        let output = self.execute_transaction(txn);
+        if let TransactionStatus::Keep(_) = output.status() {
+            self.apply_write_set(output.write_set());
+        }
+        output
+    }
+
+    /// Executes `txn` twice against the same snapshot of the state and asserts that both runs
+    /// produce identical outputs and leave the state with identical hashes.
+    ///
+    /// The executor's own state is left untouched.
+    pub fn assert_deterministic(&self, txn: SignedTransaction) {
+        let outputs: Vec<_> = (0..2)
+            .map(|_| {
+                let output = self.simulate(txn.clone());
+                let mut snapshot = self.data_store.clone();
+                snapshot.add_write_set(output.write_set());
+                (output, snapshot.state_hash())
+            })
+            .collect();
+        assert_eq!(
+            outputs[0].0, outputs[1].0,
+            "transaction produced different outputs on re-execution"
+        );
+        assert_eq!(
+            outputs[0].1, outputs[1].1,
+            "transaction produced different states on re-execution"
+        );
+    }
+
+    /// Executes an empty block, applies it and asserts that it had no side effects beyond what a
+    /// new block requires.
+    ///
+    /// Without `with_block_metadata`, the block is truly empty: it must have no outputs and leave
+    /// the state untouched. With it, the block only holds a block prologue one microsecond after
+    /// the current block time, which must succeed, emit nothing but its new block event, bump the
+    /// block height by one, move the on-chain time to the block's, and write nothing besides the
+    /// block metadata and the time.
+    pub fn assert_empty_block_noop(&mut self, with_block_metadata: bool) {
+        let before = self.data_store.clone();
+        let height = self.get_block_height();
+        let outputs = if with_block_metadata {
+            self.block_time += 1;
+            self.execute_block_with_metadata(vec![], self.new_block_metadata())
+        } else {
+            self.execute_transaction_block(vec![])
+        }
+        .expect("Executing an empty block should succeed");
+        assert_eq!(
+            outputs.len(),
+            with_block_metadata as usize,
+            "empty block produced {} outputs",
+            outputs.len()
+        );
+        for output in &outputs {
+            assert_eq!(
+                output.status(),
+                &TransactionStatus::Keep(VMStatus::Executed),
+                "the block prologue must succeed"
+            );
+            let keys: Vec<_> = output.events().iter().map(|event| event.key()).collect();
+            assert_eq!(
+                keys,
+                vec![&new_block_event_key()],
+                "empty block emitted events other than its new block event"
+            );
+            self.apply_write_set(output.write_set());
+        }
+
+        let mut allowed = BTreeSet::new();
+        if with_block_metadata {
+            assert_eq!(
+                self.get_block_height(),
+                height + 1,
+                "block height not bumped"
+            );
+            let timestamp = self
+                .read_resource::<LibraTimestampResource>(&libra_root_address())
+                .expect("the timestamp must be published");
+            assert_eq!(
+                timestamp.libra_timestamp.microseconds, self.block_time,
+                "block time not updated"
+            );
+            allowed.insert(Self::block_resource_path());
+            allowed.insert(AccessPath::new(
+                libra_root_address(),
+                LibraTimestampResource::resource_path(),
+            ));
+        } else {
+            assert_eq!(self.get_block_height(), height, "block height changed");
+        }
+        let unexpected: Vec<_> = write_set_between(&before, &self.data_store)
+            .iter()
+            .map(|(access_path, _)| access_path.clone())
+            .filter(|access_path| !allowed.contains(access_path))
+            .collect();
+        assert!(
+            unexpected.is_empty(),
+            "empty block changed the state at {:?}",
+            unexpected
+        );
+    }
+
     fn module(name: &str) -> ModuleId {
         ModuleId::new(CORE_CODE_ADDRESS, Identifier::new(name).unwrap())
     }
@@ -276,8 +2250,24 @@ impl FakeExecutor {
         Identifier::new(name).unwrap()
     }
 
+    /// Runs an empty block at `new_block_time`, in microseconds, so that transactions executed
+    /// from then on see that time. Executors start at [`GENESIS_BLOCK_TIME`].
+    ///
+    /// Like on chain, the block time can only move forward.
     pub fn set_block_time(&mut self, new_block_time: u64) {
-        self.block_time = new_block_time;
+        assert!(
+            new_block_time > self.block_time,
+            "the block time must move forward, but {} is not after {}",
+            new_block_time,
+            self.block_time
+        );
+        self.new_block_with_timestamp(new_block_time);
+    }
+
+    /// Runs an empty block `duration` after the current block time, like
+    /// [`FakeExecutor::set_block_time`].
+    pub fn advance_time(&mut self, duration: Duration) {
+        self.set_block_time(self.block_time + duration.as_micros() as u64);
     }
 
     pub fn get_block_time(&mut self) -> u64 {
@@ -292,29 +2282,135 @@ impl FakeExecutor {
         args: Vec<Value>,
         sender: &AccountAddress,
     ) {
+        self.try_exec(module_name, function_name, type_params, args, sender)
+            .unwrap_or_else(|e| panic!("Error calling {}.{}: {}", module_name, function_name, e))
+    }
+
+    /// Calls a function of a module under `0x1` like [`FakeExecutor::exec`], returning the VM's
+    /// error instead of panicking if the call fails. Nothing is applied to the state in that case.
+    pub fn try_exec(
+        &mut self,
+        module_name: &str,
+        function_name: &str,
+        type_params: Vec<TypeTag>,
+        args: Vec<Value>,
+        sender: &AccountAddress,
+    ) -> Result<(), VMError> {
         let write_set = {
             let cost_table = zero_cost_schedule();
             let mut cost_strategy = CostStrategy::system(&cost_table, GasUnits::new(100_000_000));
             let vm = MoveVM::new();
             let remote_view = RemoteStorage::new(&self.data_store);
             let mut session = vm.new_session(&remote_view);
-            session
-                .execute_function(
-                    &Self::module(module_name),
-                    &Self::name(function_name),
-                    type_params,
-                    args,
-                    *sender,
-                    &mut cost_strategy,
-                )
-                .unwrap_or_else(|e| {
-                    panic!("Error calling {}.{}: {}", module_name, function_name, e)
-                });
+            session.execute_function(
+                &Self::module(module_name),
+                &Self::name(function_name),
+                type_params,
+                args,
+                *sender,
+                &mut cost_strategy,
+            )?;
             let effects = session.finish().expect("Failed to generate txn effects");
             let (writeset, _events) =
                 txn_effects_to_writeset_and_events(effects).expect("Failed to generate writeset");
             writeset
         };
-        self.data_store.add_write_set(&write_set);
+        self.apply_write_set(&write_set);
+        Ok(())
     }
+
+    /// Registers a new currency `code` on chain, with the same exchange rate to LBR as Coin1, so
+    /// that accounts can hold balances in it, e.g. through
+    /// [`AccountData::with_balance_for_currency`].
+    ///
+    /// Currencies are Move types, so unless a module `0x1::<code>` exists already, one declaring
+    /// the struct `0x1::<code>::<code>` is published first. The currency is then registered on
+    /// behalf of the Libra root and treasury compliance accounts, the way genesis registers Coin1.
+    /// Registering a currency that exists already returns the VM's error.
+    pub fn register_currency(
+        &mut self,
+        code: &str,
+        scaling_factor: u64,
+        fractional_part: u64,
+    ) -> Result<(), VMError> {
+        let module_id = Self::module(code);
+        if StateView::get(&self.data_store, &AccessPath::from(&module_id))
+            .expect("reading from the data store should work")
+            .is_none()
+        {
+            let module = Compiler {
+                address: CORE_CODE_ADDRESS,
+                ..Compiler::default()
+            }
+            .into_compiled_module(
+                "file_name",
+                &format!("module {0} {{ struct {0} {{ }} }}", code),
+            )
+            .expect("Currency module compilation failed");
+            self.add_module(&module_id, &module);
+        }
+
+        let code = Self::name(code);
+        // 1/2 as a 32|32 fixed-point number, like Coin1.
+        let to_lbr_exchange_rate = Value::struct_(Struct::pack(vec![Value::u64(1 << 31)], false));
+        self.try_exec(
+            "Libra",
+            "register_SCS_currency",
+            vec![type_tag_for_currency_code(code.clone())],
+            vec![
+                Value::transaction_argument_signer_reference(libra_root_address()),
+                Value::transaction_argument_signer_reference(treasury_compliance_account_address()),
+                to_lbr_exchange_rate,
+                Value::u64(scaling_factor),
+                Value::u64(fractional_part),
+                Value::vector_u8(code.into_string().into_bytes()),
+            ],
+            &libra_root_address(),
+        )
+    }
+}
+
+/// Returns the compiled standard library modules, with each of `modules` replacing the module of
+/// the same name, or added if there is none. Pass the result to [`FakeExecutor::custom_genesis`].
+pub fn stdlib_modules_with(modules: Vec<CompiledModule>) -> Vec<CompiledModule> {
+    let mut merged: Vec<_> = stdlib_modules(StdLibOptions::Compiled).to_vec();
+    for module in modules {
+        match merged.iter_mut().find(|m| m.self_id() == module.self_id()) {
+            Some(existing) => *existing = module,
+            None => merged.push(module),
+        }
+    }
+    merged
+}
+
+/// Returns the payload of the first event in `events` whose type is `T`, or `None` if there is no
+/// such event.
+pub fn find_event<T: MoveResource + DeserializeOwned>(events: &[ContractEvent]) -> Option<T> {
+    let struct_tag = T::struct_tag();
+    events
+        .iter()
+        .find(|event| match event.type_tag() {
+            TypeTag::Struct(event_tag) => event_tag == &struct_tag,
+            _ => false,
+        })
+        .map(|event| lcs::from_bytes(event.event_data()).expect("Failure decoding event"))
+}
+
+/// Returns the events of type `T` in `events`, in the same order, along with their decoded
+/// contents.
+pub fn filter_events<T: MoveResource + DeserializeOwned>(
+    events: &[ContractEvent],
+) -> Vec<(&ContractEvent, T)> {
+    let struct_tag = T::struct_tag();
+    events
+        .iter()
+        .filter(|event| match event.type_tag() {
+            TypeTag::Struct(event_tag) => event_tag == &struct_tag,
+            _ => false,
+        })
+        .map(|event| {
+            let data = lcs::from_bytes(event.event_data()).expect("Failure decoding event");
+            (event, data)
+        })
+        .collect()
 }
@@ -6,11 +6,14 @@
 use crate::{
     account::{Account, AccountData},
     common_transactions::{create_account_txn, peer_to_peer_txn, rotate_key_txn},
+    compile::compile_module_with_address,
     executor::FakeExecutor,
+    gas_snapshot::GasSnapshot,
 };
 use libra_crypto::{ed25519::Ed25519PrivateKey, PrivateKey, Uniform};
 use libra_types::{
     account_config,
+    on_chain_config::VMPublishingOption,
     transaction::{authenticator::AuthenticationKey, SignedTransaction},
 };
 use once_cell::sync::Lazy;
@@ -308,6 +311,123 @@ pub static ROTATE_KEY: Lazy<u64> = Lazy::new(|| {
     compute_gas_used(txn, &mut executor)
 });
 
+/// The gas cost of publishing a module with a single empty function.
+///
+/// All such transactions are expected to cost the same gas.
+pub static PUBLISH_MODULE: Lazy<u64> = Lazy::new(|| {
+    let mut executor = FakeExecutor::from_genesis_with_options(VMPublishingOption::open());
+    let sender = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&sender);
+
+    let module = compile_module_with_address(
+        sender.address(),
+        "file_name",
+        "
+        module M {
+            public f() {
+                return;
+            }
+        }
+        ",
+    );
+    let txn = sender
+        .account()
+        .transaction()
+        .payload(module)
+        .sequence_number(10)
+        .sign();
+    compute_gas_used(txn, &mut executor)
+});
+
+/// The gas costs of common transactions, by name.
+///
+/// Each field is the value of the static of the same name in this module. Convert it into a
+/// [`GasSnapshot`] to look costs up by operation name, report drift or compare against a
+/// checked-in baseline.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct GasCosts {
+    pub create_account_first: u64,
+    pub create_account_next: u64,
+    pub create_account_too_low_first: u64,
+    pub create_account_too_low_next: u64,
+    pub create_existing_account_first: u64,
+    pub create_existing_account_next: u64,
+    pub peer_to_peer: u64,
+    pub peer_to_peer_too_low: u64,
+    pub peer_to_peer_new_receiver_first: u64,
+    pub peer_to_peer_new_receiver_next: u64,
+    pub peer_to_peer_new_receiver_too_low_first: u64,
+    pub peer_to_peer_new_receiver_too_low_next: u64,
+    pub rotate_key: u64,
+    pub publish_module: u64,
+}
+
+impl From<&GasCosts> for GasSnapshot {
+    /// Records each cost under the name of its field.
+    fn from(costs: &GasCosts) -> Self {
+        let mut snapshot = GasSnapshot::new(0);
+        snapshot.record("create_account_first", costs.create_account_first);
+        snapshot.record("create_account_next", costs.create_account_next);
+        snapshot.record(
+            "create_account_too_low_first",
+            costs.create_account_too_low_first,
+        );
+        snapshot.record(
+            "create_account_too_low_next",
+            costs.create_account_too_low_next,
+        );
+        snapshot.record(
+            "create_existing_account_first",
+            costs.create_existing_account_first,
+        );
+        snapshot.record(
+            "create_existing_account_next",
+            costs.create_existing_account_next,
+        );
+        snapshot.record("peer_to_peer", costs.peer_to_peer);
+        snapshot.record("peer_to_peer_too_low", costs.peer_to_peer_too_low);
+        snapshot.record(
+            "peer_to_peer_new_receiver_first",
+            costs.peer_to_peer_new_receiver_first,
+        );
+        snapshot.record(
+            "peer_to_peer_new_receiver_next",
+            costs.peer_to_peer_new_receiver_next,
+        );
+        snapshot.record(
+            "peer_to_peer_new_receiver_too_low_first",
+            costs.peer_to_peer_new_receiver_too_low_first,
+        );
+        snapshot.record(
+            "peer_to_peer_new_receiver_too_low_next",
+            costs.peer_to_peer_new_receiver_too_low_next,
+        );
+        snapshot.record("rotate_key", costs.rotate_key);
+        snapshot.record("publish_module", costs.publish_module);
+        snapshot
+    }
+}
+
+/// Returns the gas costs of common transactions, all in one place.
+pub fn expected() -> GasCosts {
+    GasCosts {
+        create_account_first: *CREATE_ACCOUNT_FIRST,
+        create_account_next: *CREATE_ACCOUNT_NEXT,
+        create_account_too_low_first: *CREATE_ACCOUNT_TOO_LOW_FIRST,
+        create_account_too_low_next: *CREATE_ACCOUNT_TOO_LOW_NEXT,
+        create_existing_account_first: *CREATE_EXISTING_ACCOUNT_FIRST,
+        create_existing_account_next: *CREATE_EXISTING_ACCOUNT_NEXT,
+        peer_to_peer: *PEER_TO_PEER,
+        peer_to_peer_too_low: *PEER_TO_PEER_TOO_LOW,
+        peer_to_peer_new_receiver_first: *PEER_TO_PEER_NEW_RECEIVER_FIRST,
+        peer_to_peer_new_receiver_next: *PEER_TO_PEER_NEW_RECEIVER_NEXT,
+        peer_to_peer_new_receiver_too_low_first: *PEER_TO_PEER_NEW_RECEIVER_TOO_LOW_FIRST,
+        peer_to_peer_new_receiver_too_low_next: *PEER_TO_PEER_NEW_RECEIVER_TOO_LOW_NEXT,
+        rotate_key: *ROTATE_KEY,
+        publish_module: *PUBLISH_MODULE,
+    }
+}
+
 fn compute_gas_used(txn: SignedTransaction, executor: &mut FakeExecutor) -> u64 {
     let output = &executor.execute_transaction(txn);
     output.gas_used()
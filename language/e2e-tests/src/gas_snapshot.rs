@@ -0,0 +1,175 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Snapshots of the gas used by transactions, to catch unintended changes in gas costs.
+
+use libra_types::transaction::TransactionOutput;
+use std::{collections::BTreeMap, env, fs, path::Path};
+
+/// The environment variable which, set to `1` or `true`, makes
+/// [`GasSnapshot::assert_matches_baseline`] rewrite baselines instead of checking them.
+pub const UPDATE_BASELINE: &str = "UPDATE_BASELINE";
+
+/// The gas used by a set of labelled transactions over a test.
+///
+/// A snapshot is compared against a checked-in JSON object mapping each label to the gas its
+/// transaction used. Entries may drift by up to `tolerance` gas units before the comparison fails.
+#[derive(Debug, Default)]
+pub struct GasSnapshot {
+    entries: BTreeMap<String, u64>,
+    tolerance: u64,
+}
+
+impl GasSnapshot {
+    /// Creates an empty snapshot accepting drifts of up to `tolerance` gas units per entry.
+    pub fn new(tolerance: u64) -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            tolerance,
+        }
+    }
+
+    /// Records the gas used by the transaction labelled `label`. Labels must be unique.
+    pub fn record(&mut self, label: impl Into<String>, gas_used: u64) {
+        let label = label.into();
+        assert!(
+            self.entries.insert(label.clone(), gas_used).is_none(),
+            "gas already recorded for {}",
+            label
+        );
+    }
+
+    /// Returns the recorded entries, sorted by label.
+    pub fn entries(&self) -> &BTreeMap<String, u64> {
+        &self.entries
+    }
+
+    /// Returns the gas recorded for `label`, if any.
+    pub fn get(&self, label: &str) -> Option<u64> {
+        self.entries.get(label).copied()
+    }
+
+    /// Returns a description of how the gas used by `output` drifted from the gas recorded for
+    /// `label` by more than the tolerance, or `None` if it didn't.
+    ///
+    /// Panics if nothing was recorded for that label.
+    pub fn drift(&self, label: &str, output: &TransactionOutput) -> Option<String> {
+        let expected = self
+            .get(label)
+            .unwrap_or_else(|| panic!("no gas is recorded for {:?}", label));
+        let gas_used = output.gas_used();
+        let drift = gas_used as i128 - expected as i128;
+        if drift.abs() <= self.tolerance as i128 {
+            return None;
+        }
+        Some(format!(
+            "{}: expected {}, got {} ({:+}) with status {:?}",
+            label,
+            expected,
+            gas_used,
+            drift,
+            output.status(),
+        ))
+    }
+
+    /// Asserts that `output` used the gas recorded for `label` within the tolerance, naming the
+    /// label if not.
+    pub fn assert_gas_used(&self, label: &str, output: &TransactionOutput) {
+        if let Some(drift) = self.drift(label, output) {
+            panic!("gas used drifted for {}", drift);
+        }
+    }
+
+    /// Serializes the recorded entries to JSON, in the format expected by [`Self::diff`]. Use this
+    /// to regenerate a checked-in snapshot after an intended change in gas costs.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.entries).expect("gas snapshot must serialize")
+    }
+
+    /// Compares the recorded entries against `expected_json`, returning one line per entry that
+    /// is missing on either side or that drifted by more than the tolerance.
+    pub fn diff(&self, expected_json: &str) -> Vec<String> {
+        let expected: BTreeMap<String, u64> =
+            serde_json::from_str(expected_json).expect("malformed gas snapshot");
+        let mut diff = vec![];
+        for (label, expected_gas) in &expected {
+            match self.entries.get(label) {
+                None => diff.push(format!(
+                    "{}: expected {}, not recorded",
+                    label, expected_gas
+                )),
+                Some(gas_used) => {
+                    let drift = *gas_used as i128 - *expected_gas as i128;
+                    if drift.abs() > self.tolerance as i128 {
+                        diff.push(format!(
+                            "{}: expected {}, got {} ({:+})",
+                            label, expected_gas, gas_used, drift
+                        ));
+                    }
+                }
+            }
+        }
+        for (label, gas_used) in &self.entries {
+            if !expected.contains_key(label) {
+                diff.push(format!("{}: got {}, not in snapshot", label, gas_used));
+            }
+        }
+        diff
+    }
+
+    /// Asserts that the recorded entries match `expected_json` within the tolerance.
+    pub fn assert_matches(&self, expected_json: &str) {
+        let diff = self.diff(expected_json);
+        assert!(
+            diff.is_empty(),
+            "gas usage drifted from the snapshot:\n{}\nnew snapshot:\n{}",
+            diff.join("\n"),
+            self.to_json()
+        );
+    }
+
+    /// Asserts that the recorded entries match the JSON snapshot at `path` within the tolerance.
+    pub fn assert_matches_file(&self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        let expected_json = fs::read_to_string(path)
+            .unwrap_or_else(|err| panic!("unable to read gas snapshot {:?}: {}", path, err));
+        self.assert_matches(&expected_json);
+    }
+
+    /// Asserts that the recorded entries match the checked-in baseline at `path` within the
+    /// tolerance, like [`Self::assert_matches_file`].
+    ///
+    /// A missing baseline fails the assertion. Set the [`UPDATE_BASELINE`] environment variable
+    /// to write it instead, either to create it or to bless an intended change in gas costs.
+    pub fn assert_matches_baseline(&self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        if update_baseline() {
+            fs::write(path, self.to_json() + "\n")
+                .unwrap_or_else(|err| panic!("unable to write gas baseline {:?}: {}", path, err));
+            return;
+        }
+        assert!(
+            path.exists(),
+            "gas baseline {:?} is missing, run the test with {}=1 to create it",
+            path,
+            UPDATE_BASELINE,
+        );
+        let expected_json = fs::read_to_string(path)
+            .unwrap_or_else(|err| panic!("unable to read gas baseline {:?}: {}", path, err));
+        let diff = self.diff(&expected_json);
+        assert!(
+            diff.is_empty(),
+            "gas usage drifted from the baseline {:?}:\n{}\n\
+             if this is intended, run the test again with {}=1 to update it",
+            path,
+            diff.join("\n"),
+            UPDATE_BASELINE,
+        );
+    }
+}
+
+fn update_baseline() -> bool {
+    env::var(UPDATE_BASELINE)
+        .map(|value| value == "1" || value == "true")
+        .unwrap_or(false)
+}
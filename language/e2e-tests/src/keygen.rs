@@ -11,12 +11,22 @@ use rand::{
 };
 
 /// Ed25519 key generator.
-pub struct KeyGen(StdRng);
+///
+/// The keys are a deterministic stream derived from the seed, so a generator built with
+/// [`KeyGen::from_seed`] and the [`seed`](KeyGen::seed) of another one produces the same keys, in
+/// the same order.
+pub struct KeyGen {
+    seed: [u8; 32],
+    rng: StdRng,
+}
 
 impl KeyGen {
     /// Constructs a key generator with a specific seed.
     pub fn from_seed(seed: [u8; 32]) -> Self {
-        Self(StdRng::from_seed(seed))
+        Self {
+            seed,
+            rng: StdRng::from_seed(seed),
+        }
     }
 
     /// Constructs a key generator with a random seed.
@@ -27,10 +37,20 @@ impl KeyGen {
         Self::from_seed(seed)
     }
 
+    /// Returns the seed this generator was constructed with.
+    pub fn seed(&self) -> [u8; 32] {
+        self.seed
+    }
+
     /// Generate an Ed25519 key pair.
     pub fn generate_keypair(&mut self) -> (Ed25519PrivateKey, Ed25519PublicKey) {
-        let private_key = Ed25519PrivateKey::generate(&mut self.0);
+        let private_key = Ed25519PrivateKey::generate(&mut self.rng);
         let public_key = private_key.public_key();
         (private_key, public_key)
     }
+
+    /// Returns the next key pair of this generator's stream.
+    pub fn next_keypair(&mut self) -> (Ed25519PrivateKey, Ed25519PublicKey) {
+        self.generate_keypair()
+    }
 }
@@ -7,7 +7,16 @@
 //!
 //! This crate contains helpers for executing tests against the Libra VM.
 
-use libra_types::{transaction::TransactionStatus, vm_status::VMStatus};
+use crate::executor::StorageFootprint;
+use libra_types::{
+    account_config::NewEpochEvent,
+    event::EventKey,
+    on_chain_config::new_epoch_event_key,
+    transaction::{TransactionOutput, TransactionStatus},
+    vm_status::{AbortLocation, StatusCode, StatusType, VMStatus},
+};
+use move_core_types::language_storage::ModuleId;
+use serde::de::DeserializeOwned;
 
 #[cfg(test)]
 mod tests;
@@ -18,10 +27,16 @@ pub mod common_transactions;
 pub mod compile;
 pub mod data_store;
 pub mod execution_strategies;
+pub mod execution_timing;
 pub mod executor;
 pub mod gas_costs;
+pub mod gas_snapshot;
 pub mod keygen;
-mod proptest_types;
+pub mod proptest_types;
+pub mod remote_data_store;
+pub mod transaction_log;
+pub mod vm_log;
+pub mod write_set_diff;
 
 pub fn assert_status_eq(s1: &VMStatus, s2: &VMStatus) -> bool {
     // TODO(tmn) After providing real abort locations, use normal equality
@@ -30,6 +45,44 @@ pub fn assert_status_eq(s1: &VMStatus, s2: &VMStatus) -> bool {
     true
 }
 
+/// Like [`assert_status_eq`], but also compares where `MoveAbort` statuses were raised, so that
+/// aborts with the same code from different modules, or from a script, don't compare equal.
+pub fn assert_status_eq_strict(s1: &VMStatus, s2: &VMStatus) -> bool {
+    assert_status_eq(s1, s2);
+    if let (VMStatus::MoveAbort(location1, _), VMStatus::MoveAbort(location2, _)) = (s1, s2) {
+        assert!(
+            location1 == location2,
+            "abort locations differ: {:?} != {:?}",
+            location1,
+            location2,
+        );
+    }
+    true
+}
+
+/// Asserts that `status` falls in the `expected` category, reporting the actual category and code
+/// otherwise.
+pub fn assert_status_type(status: &VMStatus, expected: StatusType) {
+    let actual = status.status_type();
+    assert!(
+        actual == expected,
+        "expected a status of type {:?}, got {:?} ({:?})",
+        expected,
+        actual,
+        status,
+    );
+}
+
+/// Asserts that `output` used exactly `expected` gas units.
+pub fn assert_gas_used(output: &TransactionOutput, expected: u64) {
+    assert_eq!(
+        output.gas_used(),
+        expected,
+        "unexpected gas used by a transaction with status {:?}",
+        output.status(),
+    );
+}
+
 pub fn transaction_status_eq(t1: &TransactionStatus, t2: &TransactionStatus) -> bool {
     match (t1, t2) {
         (TransactionStatus::Discard(s1), TransactionStatus::Discard(s2))
@@ -38,6 +91,174 @@ pub fn transaction_status_eq(t1: &TransactionStatus, t2: &TransactionStatus) ->
     }
 }
 
+/// Asserts that `output` was discarded with the `expected` status code, reporting the actual
+/// transaction status otherwise.
+pub fn assert_discarded_with(output: &TransactionOutput, expected: StatusCode) {
+    match output.status() {
+        TransactionStatus::Discard(status) if status.status_code() == expected => (),
+        actual => panic!(
+            "expected the transaction to be discarded with {:?}, got {:?}",
+            expected, actual,
+        ),
+    }
+}
+
+/// Asserts that `output` was kept with the `expected` status code, reporting the actual
+/// transaction status otherwise. Use `StatusCode::EXECUTED` for successful transactions and
+/// `StatusCode::ABORTED` for Move aborts.
+pub fn assert_kept_with(output: &TransactionOutput, expected: StatusCode) {
+    match output.status() {
+        TransactionStatus::Keep(status) if status.status_code() == expected => (),
+        actual => panic!(
+            "expected the transaction to be kept with {:?}, got {:?}",
+            expected, actual,
+        ),
+    }
+}
+
+/// Asserts that a transaction touched the `expected` storage, as measured by
+/// [`FakeExecutor::execute_transaction_with_footprint`][executor::FakeExecutor::execute_transaction_with_footprint],
+/// naming each count that differs otherwise.
+pub fn assert_storage_footprint(actual: &StorageFootprint, expected: &StorageFootprint) {
+    let counts = [
+        ("reads", actual.reads, expected.reads),
+        ("bytes_read", actual.bytes_read, expected.bytes_read),
+        ("writes", actual.writes, expected.writes),
+        (
+            "bytes_written",
+            actual.bytes_written,
+            expected.bytes_written,
+        ),
+    ];
+    let mismatches: Vec<_> = counts
+        .iter()
+        .filter(|(_, actual, expected)| actual != expected)
+        .map(|(name, actual, expected)| format!("{}: expected {}, got {}", name, expected, actual))
+        .collect();
+    assert!(
+        mismatches.is_empty(),
+        "storage footprint mismatch: {}",
+        mismatches.join(", ")
+    );
+}
+
+/// Returns the module a `MoveAbort` status was raised in. Returns `None` if the status isn't a Move
+/// abort or if the abort was raised in a script.
+pub fn abort_module(status: &VMStatus) -> Option<ModuleId> {
+    match status {
+        VMStatus::MoveAbort(AbortLocation::Module(module_id), _) => Some(module_id.clone()),
+        VMStatus::MoveAbort(AbortLocation::Script, _) | VMStatus::Error(_) | VMStatus::Executed => {
+            None
+        }
+    }
+}
+
+/// Where a Move abort was raised and the code it was raised with, as returned by [`move_abort`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MoveAbortInfo {
+    pub location: AbortLocation,
+    pub abort_code: u64,
+}
+
+/// Returns where the transaction of `output` aborted and with which code, if it was kept with a
+/// `MoveAbort` status.
+///
+/// The status only records the module, not the function that aborted:
+/// [`FakeExecutor::execute_transaction_with_trace`][executor::FakeExecutor::execute_transaction_with_trace]
+/// finds the function too.
+pub fn move_abort(output: &TransactionOutput) -> Option<MoveAbortInfo> {
+    match output.status() {
+        TransactionStatus::Keep(VMStatus::MoveAbort(location, abort_code)) => Some(MoveAbortInfo {
+            location: location.clone(),
+            abort_code: *abort_code,
+        }),
+        _ => None,
+    }
+}
+
+/// Asserts that `output` was kept with a Move abort raised in `module_id` with `abort_code`,
+/// reporting the actual transaction status otherwise.
+///
+/// Unlike comparing status codes, this tells apart two modules aborting with the same code.
+pub fn assert_aborts_with(output: &TransactionOutput, module_id: &ModuleId, abort_code: u64) {
+    let expected = MoveAbortInfo {
+        location: AbortLocation::Module(module_id.clone()),
+        abort_code,
+    };
+    assert!(
+        move_abort(output).as_ref() == Some(&expected),
+        "expected the transaction to abort in {} with code {}, got {:?}",
+        module_id,
+        abort_code,
+        output.status(),
+    );
+}
+
+/// Returns the event announcing the new epoch, if the transaction of `output` triggered a
+/// reconfiguration. Panics if it announces several, since there can be at most one
+/// reconfiguration per block.
+pub fn reconfiguration_event(output: &TransactionOutput) -> Option<NewEpochEvent> {
+    let mut events = output
+        .events()
+        .iter()
+        .filter(|event| event.key() == &new_epoch_event_key());
+    let event = events.next()?;
+    assert!(
+        events.next().is_none(),
+        "transaction triggered more than one reconfiguration"
+    );
+    Some(NewEpochEvent::try_from_bytes(event.event_data()).expect("Failure decoding event"))
+}
+
+/// Returns the payloads of the events `output` emitted under `key`, in order, decoded as `T`.
+///
+/// All the events under a key have the same type, so this panics if one doesn't decode as `T`.
+pub fn decode_events<T: DeserializeOwned>(output: &TransactionOutput, key: &EventKey) -> Vec<T> {
+    output
+        .events()
+        .iter()
+        .filter(|event| event.key() == key)
+        .map(|event| {
+            lcs::from_bytes(event.event_data()).unwrap_or_else(|err| {
+                panic!(
+                    "event under key {} doesn't decode as {}: {}",
+                    key,
+                    std::any::type_name::<T>(),
+                    err
+                )
+            })
+        })
+        .collect()
+}
+
+/// Asserts that a transaction output emitted an event of the given type under the given key, and
+/// evaluates to its decoded payload. With a predicate, the first event satisfying it is returned.
+///
+/// ```ignore
+/// let key = EventKey::try_from(sender.sent_events_key()).unwrap();
+/// let event = assert_event_emitted!(output, key, SentPaymentEvent, |event| event.amount() == 1_000);
+/// ```
+#[macro_export]
+macro_rules! assert_event_emitted {
+    ($output:expr, $key:expr, $event_type:ty) => {
+        $crate::assert_event_emitted!($output, $key, $event_type, |_| true)
+    };
+    ($output:expr, $key:expr, $event_type:ty, $predicate:expr) => {{
+        let key = &$key;
+        let events: Vec<$event_type> = $crate::decode_events(&$output, key);
+        let predicate: &dyn Fn(&$event_type) -> bool = &$predicate;
+        match events.iter().position(|event| predicate(event)) {
+            Some(idx) => events.into_iter().nth(idx).unwrap(),
+            None => panic!(
+                "no matching {} event emitted under key {}, got {:?}",
+                stringify!($event_type),
+                key,
+                events,
+            ),
+        }
+    }};
+}
+
 #[macro_export]
 macro_rules! assert_prologue_parity {
     ($e1:expr, $e2:expr, $e3:expr) => {
@@ -2,7 +2,26 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::account::{lbr_currency_code, Account, AccountData, AccountRoleSpecifier};
-use proptest::prelude::*;
+use compiled_stdlib::transaction_scripts::StdlibScript;
+use libra_types::{
+    account_config::{
+        coin1_tag, lbr_type_tag, libra_root_address, COIN1_NAME, COIN2_NAME, LBR_NAME,
+    },
+    transaction::{ChangeSet, Module, Script, ScriptABI, TransactionArgument, TransactionPayload},
+    vm_status::StatusCode,
+    write_set::WriteSet,
+};
+use move_core_types::{
+    account_address::AccountAddress,
+    gas_schedule::{GasAlgebra, GasConstants},
+    language_storage::TypeTag,
+};
+use proptest::{
+    collection::vec,
+    prelude::*,
+    sample::{select, subsequence},
+};
+use vm::{access::ModuleAccess, file_format::CompiledModule};
 
 impl Arbitrary for Account {
     type Parameters = ();
@@ -45,4 +64,182 @@ impl AccountData {
                 },
             )
     }
+
+    /// Returns a [`Strategy`] that creates `AccountData` instances holding a balance in a random,
+    /// non-empty mix of the currencies registered at genesis, each drawn from `balance_strategy`.
+    pub fn multi_currency_strategy(
+        balance_strategy: impl Strategy<Value = u64>,
+    ) -> impl Strategy<Value = Self> {
+        let currencies = vec![LBR_NAME, COIN1_NAME, COIN2_NAME];
+        let num_currencies = currencies.len();
+
+        (
+            any::<Account>(),
+            subsequence(currencies, 1..=num_currencies),
+            vec(balance_strategy, num_currencies),
+            0u64..(1 << 32),
+        )
+            .prop_map(|(account, currencies, balances, sequence_number)| {
+                currencies
+                    .into_iter()
+                    .zip(balances)
+                    .fold(
+                        AccountData::builder()
+                            .account(account)
+                            .sequence_number(sequence_number),
+                        |builder, (currency_code, balance)| {
+                            builder.balance_in(currency_code, balance)
+                        },
+                    )
+                    .build()
+            })
+    }
+}
+
+/// Gas parameters for a transaction, along with the status the VM's gas checks are expected to
+/// reject them with, if any.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GasParams {
+    pub max_gas_amount: u64,
+    pub gas_unit_price: u64,
+    pub expected_error: Option<StatusCode>,
+}
+
+impl GasParams {
+    /// Computes the outcome of the VM's gas checks for these parameters, in the order the VM runs
+    /// them. Assumes the transaction is smaller than the large transaction cutoff, so that its
+    /// intrinsic cost is the minimum transaction cost.
+    fn new(max_gas_amount: u64, gas_unit_price: u64, gas_constants: &GasConstants) -> Self {
+        let expected_error = if max_gas_amount > gas_constants.maximum_number_of_gas_units.get() {
+            Some(StatusCode::MAX_GAS_UNITS_EXCEEDS_MAX_GAS_UNITS_BOUND)
+        } else if max_gas_amount < gas_constants.min_transaction_gas_units.get() {
+            Some(StatusCode::MAX_GAS_UNITS_BELOW_MIN_TRANSACTION_GAS_UNITS)
+        } else if gas_unit_price < gas_constants.min_price_per_gas_unit.get() {
+            Some(StatusCode::GAS_UNIT_PRICE_BELOW_MIN_BOUND)
+        } else if gas_unit_price > gas_constants.max_price_per_gas_unit.get() {
+            Some(StatusCode::GAS_UNIT_PRICE_ABOVE_MAX_BOUND)
+        } else {
+            None
+        };
+        Self {
+            max_gas_amount,
+            gas_unit_price,
+            expected_error,
+        }
+    }
+}
+
+/// Returns a [`Strategy`] that generates gas parameters around the bounds in `gas_constants`.
+///
+/// Most parameters are within the bounds, but some are picked just outside of them to exercise
+/// the rejection paths. Pass in the constants read from the executor under test (see
+/// [`FakeExecutor::read_gas_constants`][crate::executor::FakeExecutor::read_gas_constants]) so
+/// that the expected outcomes follow the active configuration.
+pub fn arb_gas_params(gas_constants: GasConstants) -> impl Strategy<Value = GasParams> {
+    let min_gas = gas_constants.min_transaction_gas_units.get();
+    let max_gas = gas_constants.maximum_number_of_gas_units.get();
+    let min_price = gas_constants.min_price_per_gas_unit.get();
+    let max_price = gas_constants.max_price_per_gas_unit.get();
+
+    let max_gas_strategy = prop_oneof![
+        8 => min_gas..=max_gas,
+        1 => 0..min_gas,
+        1 => (max_gas + 1)..=(max_gas * 2),
+    ];
+    let gas_price_strategy = prop_oneof![
+        8 => min_price..=max_price,
+        1 => 0..=min_price,
+        1 => (max_price + 1)..=(max_price * 2),
+    ];
+    (max_gas_strategy, gas_price_strategy).prop_map(move |(max_gas_amount, gas_unit_price)| {
+        GasParams::new(max_gas_amount, gas_unit_price, &gas_constants)
+    })
+}
+
+/// Returns a [`Strategy`] that generates payloads for `sender` to fuzz the VM with: scripts,
+/// module publishes and write sets, in roughly equal parts.
+///
+/// The payloads are well-formed but otherwise garbage. Scripts are standard library scripts
+/// called with arguments of the right types but random values, modules are structurally valid
+/// but random and published under `sender`, and write sets write random values to random access
+/// paths. Whatever the payload, executing it should end with a clean keep or discard status and
+/// never with a panic or an invariant violation.
+pub fn arb_transaction_payload(
+    sender: AccountAddress,
+) -> impl Strategy<Value = TransactionPayload> {
+    prop_oneof![
+        arb_stdlib_script(sender).prop_map(TransactionPayload::Script),
+        arb_module(sender).prop_map(TransactionPayload::Module),
+        any::<WriteSet>()
+            .prop_map(|write_set| TransactionPayload::WriteSet(ChangeSet::new(write_set, vec![]))),
+    ]
+}
+
+/// Returns a [`Strategy`] that calls one of the standard library scripts with arguments of the
+/// types in its ABI.
+fn arb_stdlib_script(sender: AccountAddress) -> impl Strategy<Value = Script> {
+    let abis: Vec<ScriptABI> = StdlibScript::all()
+        .into_iter()
+        .map(StdlibScript::abi)
+        .collect();
+    select(abis).prop_flat_map(move |abi| {
+        let ty_args = vec(arb_currency_type_tag(), abi.ty_args().len());
+        let args: Vec<_> = abi
+            .args()
+            .iter()
+            .map(|arg| arb_script_argument(arg.type_tag(), sender))
+            .collect();
+        (Just(abi.code().to_vec()), ty_args, args)
+            .prop_map(|(code, ty_args, args)| Script::new(code, ty_args, args))
+    })
+}
+
+/// Mostly picks currencies that exist, so that scripts get past their type checks, but sometimes
+/// any type at all.
+fn arb_currency_type_tag() -> impl Strategy<Value = TypeTag> {
+    prop_oneof![
+        3 => Just(lbr_type_tag()),
+        3 => Just(coin1_tag()),
+        1 => any::<TypeTag>(),
+    ]
+}
+
+fn arb_script_argument(
+    type_tag: &TypeTag,
+    sender: AccountAddress,
+) -> BoxedStrategy<TransactionArgument> {
+    match type_tag {
+        TypeTag::Bool => any::<bool>().prop_map(TransactionArgument::Bool).boxed(),
+        TypeTag::U8 => any::<u8>().prop_map(TransactionArgument::U8).boxed(),
+        TypeTag::U64 => any::<u64>().prop_map(TransactionArgument::U64).boxed(),
+        TypeTag::U128 => any::<u128>().prop_map(TransactionArgument::U128).boxed(),
+        // Addresses of accounts that exist make the scripts go further.
+        TypeTag::Address => prop_oneof![
+            Just(sender),
+            Just(libra_root_address()),
+            any::<AccountAddress>(),
+        ]
+        .prop_map(TransactionArgument::Address)
+        .boxed(),
+        TypeTag::Vector(inner) if **inner == TypeTag::U8 => vec(any::<u8>(), 0..64)
+            .prop_map(TransactionArgument::U8Vector)
+            .boxed(),
+        _ => panic!("unsupported script argument type {}", type_tag),
+    }
+}
+
+/// Returns a [`Strategy`] that generates structurally valid modules published under `sender`.
+fn arb_module(sender: AccountAddress) -> impl Strategy<Value = Module> {
+    (1usize..16)
+        .prop_flat_map(CompiledModule::valid_strategy)
+        .prop_map(move |module| {
+            let self_address = module.self_handle().address;
+            let mut module = module.into_inner();
+            module.address_identifiers[self_address.0 as usize] = sender;
+            let mut blob = vec![];
+            module
+                .serialize(&mut blob)
+                .expect("generated modules should always serialize");
+            Module::new(blob)
+        })
 }
@@ -0,0 +1,239 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A data store that fetches the state it is missing from a Libra node, to run transactions
+//! locally against the state of a real network.
+
+use crate::data_store::FakeDataStore;
+use anyhow::{bail, Result};
+use libra_secure_json_rpc::{Error as JsonRpcError, JsonRpcClient};
+use libra_state_view::StateView;
+use libra_types::{
+    access_path::AccessPath,
+    on_chain_config::ConfigStorage,
+    transaction::{SignedTransaction, Transaction, TransactionOutput},
+    vm_status::VMStatus,
+    write_set::{WriteOp, WriteSet},
+};
+use libra_vm::{data_cache::RemoteStorage, LibraVM};
+use move_core_types::{
+    account_address::AccountAddress,
+    language_storage::{ModuleId, TypeTag},
+};
+use move_vm_runtime::data_cache::RemoteCache;
+use std::{cell::RefCell, collections::HashSet};
+use vm::errors::*;
+
+/// A [`FakeDataStore`] which, on a miss, fetches the whole state of the account being read from a
+/// node's JSON-RPC endpoint and caches it.
+///
+/// Data set or written locally always takes precedence and is read without going to the node, and
+/// each account is fetched at most once. In offline mode, reading anything that isn't already
+/// known is an error instead, so that tests built on a previously fetched state are deterministic.
+pub struct RemoteDataStore {
+    local: FakeDataStore,
+    /// Access paths deleted locally, which must not be read from the node again.
+    deleted: HashSet<AccessPath>,
+    fetched: RefCell<FakeDataStore>,
+    fetched_accounts: RefCell<HashSet<AccountAddress>>,
+    client: Option<JsonRpcClient>,
+    version: Option<u64>,
+}
+
+impl RemoteDataStore {
+    /// Creates a store that reads from `local` first and fetches anything else from the node at
+    /// `url`, at its latest version.
+    pub fn new(local: FakeDataStore, url: impl Into<String>) -> Self {
+        Self {
+            local,
+            deleted: HashSet::new(),
+            fetched: RefCell::new(FakeDataStore::default()),
+            fetched_accounts: RefCell::new(HashSet::new()),
+            client: Some(JsonRpcClient::new(url.into())),
+            version: None,
+        }
+    }
+
+    /// Creates a store that only reads from `local`, such as one returned by
+    /// [`RemoteDataStore::to_data_store`]. The accounts in `local` are taken to be complete, and
+    /// reading from any other account returns an error.
+    pub fn offline(local: FakeDataStore) -> Self {
+        let accounts = local
+            .to_sorted_map()
+            .keys()
+            .map(|access_path| access_path.address)
+            .collect();
+        Self {
+            local,
+            deleted: HashSet::new(),
+            fetched: RefCell::new(FakeDataStore::default()),
+            fetched_accounts: RefCell::new(accounts),
+            client: None,
+            version: None,
+        }
+    }
+
+    /// Fetches accounts as of `version` instead of the latest version of the node.
+    pub fn at_version(mut self, version: u64) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Stops fetching from the node: the accounts fetched so far stay readable, and reading
+    /// anything else returns an error.
+    pub fn go_offline(&mut self) {
+        self.client = None;
+    }
+
+    /// Returns whether this store fetches what it is missing from a node.
+    pub fn is_offline(&self) -> bool {
+        self.client.is_none()
+    }
+
+    /// Applies a [`WriteSet`] locally. Nothing is ever written to the node.
+    pub fn add_write_set(&mut self, write_set: &WriteSet) {
+        for (access_path, write_op) in write_set {
+            match write_op {
+                WriteOp::Value(blob) => {
+                    self.deleted.remove(access_path);
+                    self.local.set(access_path.clone(), blob.clone());
+                }
+                WriteOp::Deletion => {
+                    self.local.remove(access_path);
+                    self.deleted.insert(access_path.clone());
+                }
+            }
+        }
+    }
+
+    /// Returns the addresses of the accounts fetched from the node so far.
+    pub fn fetched_accounts(&self) -> Vec<AccountAddress> {
+        let mut addresses: Vec<_> = self.fetched_accounts.borrow().iter().copied().collect();
+        addresses.sort();
+        addresses
+    }
+
+    /// Returns everything this store knows, local data taking precedence over fetched data.
+    ///
+    /// A [`FakeExecutor`] created from it with [`FakeExecutor::from_data_store`] can then keep
+    /// running against the fetched state without the node.
+    pub fn to_data_store(&self) -> FakeDataStore {
+        let mut data_store = FakeDataStore::default();
+        for (access_path, blob) in self.fetched.borrow().to_sorted_map() {
+            if !self.deleted.contains(&access_path) {
+                data_store.set(access_path, blob);
+            }
+        }
+        for (access_path, blob) in self.local.to_sorted_map() {
+            data_store.set(access_path, blob);
+        }
+        data_store
+    }
+
+    /// Executes the given block of transactions against this store, fetching whatever they read
+    /// that isn't known yet. Like [`FakeExecutor::execute_block`], each transaction sees the
+    /// writes of the ones before it, but the outputs aren't applied.
+    pub fn execute_block(
+        &self,
+        txn_block: Vec<SignedTransaction>,
+    ) -> Result<Vec<TransactionOutput>, VMStatus> {
+        LibraVM::execute_block(
+            txn_block
+                .into_iter()
+                .map(Transaction::UserTransaction)
+                .collect(),
+            self,
+        )
+    }
+
+    /// Executes the given transaction against this store, fetching whatever it reads that isn't
+    /// known yet. The output isn't applied.
+    pub fn execute_transaction(&self, txn: SignedTransaction) -> TransactionOutput {
+        self.execute_block(vec![txn])
+            .expect("The VM should not fail to execute")
+            .pop()
+            .expect("A block with one transaction should have one output")
+    }
+
+    fn fetch_account(&self, address: AccountAddress) -> Result<()> {
+        let client = match &self.client {
+            Some(client) => client,
+            None => bail!("account {} is not available offline", address),
+        };
+        let mut fetched = self.fetched.borrow_mut();
+        match client.get_account_state(address, self.version) {
+            Ok(account_state) => {
+                for (path, blob) in account_state.iter() {
+                    fetched.set(AccessPath::new(address, path.clone()), blob.clone());
+                }
+            }
+            // The account doesn't exist on the node.
+            Err(JsonRpcError::MissingData(_)) => (),
+            Err(err) => bail!("unable to fetch account {}: {}", address, err),
+        }
+        self.fetched_accounts.borrow_mut().insert(address);
+        Ok(())
+    }
+}
+
+impl ConfigStorage for RemoteDataStore {
+    fn fetch_config(&self, access_path: AccessPath) -> Option<Vec<u8>> {
+        StateView::get(self, &access_path).unwrap_or_default()
+    }
+}
+
+impl StateView for RemoteDataStore {
+    fn get(&self, access_path: &AccessPath) -> Result<Option<Vec<u8>>> {
+        if let Some(blob) = StateView::get(&self.local, access_path)? {
+            return Ok(Some(blob));
+        }
+        if self.deleted.contains(access_path) {
+            return Ok(None);
+        }
+        if !self
+            .fetched_accounts
+            .borrow()
+            .contains(&access_path.address)
+        {
+            self.fetch_account(access_path.address)?;
+        }
+        StateView::get(&*self.fetched.borrow(), access_path)
+    }
+
+    fn multi_get(&self, access_paths: &[AccessPath]) -> Result<Vec<Option<Vec<u8>>>> {
+        access_paths
+            .iter()
+            .map(|access_path| StateView::get(self, access_path))
+            .collect()
+    }
+
+    fn is_genesis(&self) -> bool {
+        false
+    }
+}
+
+impl RemoteCache for RemoteDataStore {
+    fn get_module(&self, module_id: &ModuleId) -> VMResult<Option<Vec<u8>>> {
+        RemoteStorage::new(self).get_module(module_id)
+    }
+
+    fn get_resource(
+        &self,
+        address: &AccountAddress,
+        tag: &TypeTag,
+    ) -> PartialVMResult<Option<Vec<u8>>> {
+        RemoteStorage::new(self).get_resource(address, tag)
+    }
+}
+
+impl std::fmt::Debug for RemoteDataStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("RemoteDataStore")
+            .field("local", &self.local)
+            .field("deleted", &self.deleted)
+            .field("fetched_accounts", &self.fetched_accounts)
+            .field("offline", &self.is_offline())
+            .field("version", &self.version)
+            .finish()
+    }
+}
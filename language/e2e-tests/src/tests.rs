@@ -12,9 +12,12 @@
 mod account_universe;
 mod create_account;
 mod data_store;
+mod empty_block;
 mod execution_strategies;
 mod failed_transaction_tests;
+mod gas_snapshot;
 mod genesis;
+mod keygen;
 mod mint;
 mod module_publishing;
 mod on_chain_configs;
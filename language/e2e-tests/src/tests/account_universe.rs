@@ -3,18 +3,24 @@
 
 mod bad_transaction;
 mod create_account;
+mod gas_boundary;
 mod peer_to_peer;
+mod publish_module;
 mod rotate_key;
 
 use crate::{
+    account::AccountData,
     account_universe::{
         all_transactions_strategy, default_num_accounts, default_num_transactions,
-        log_balance_strategy, run_and_assert_universe, AccountCurrent, AccountPairGen,
-        AccountPickStyle, AccountUniverseGen,
+        log_balance_strategy, replay_universe_failure, run_and_assert_universe, AccountCurrent,
+        AccountPairGen, AccountPickStyle, AccountUniverseGen, ExpectedAccount, UniverseFailure,
     },
+    common_transactions::peer_to_peer_txn,
     executor::FakeExecutor,
 };
+use libra_types::{account_config::LBR_NAME, transaction::TransactionStatus, vm_status::VMStatus};
 use proptest::{collection::vec, prelude::*};
+use std::{env, fs, panic, process};
 
 proptest! {
     // These tests are pretty slow but quite comprehensive, so run a smaller number of them.
@@ -82,3 +88,49 @@ proptest! {
         run_and_assert_universe(universe, transactions)?;
     }
 }
+
+#[test]
+fn replay_saved_universe_failure() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    let expected_account = |account: &AccountData, sequence_number, balance| ExpectedAccount {
+        address: *account.address(),
+        auth_key: account.account().auth_key(),
+        sequence_number,
+        balances: vec![(LBR_NAME.to_owned(), balance)].into_iter().collect(),
+    };
+    let mut failure = UniverseFailure {
+        initial_state: executor.state_bytes(),
+        transactions: vec![peer_to_peer_txn(
+            sender.account(),
+            receiver.account(),
+            10,
+            1_000,
+        )],
+        expected_statuses: vec![TransactionStatus::Keep(VMStatus::Executed)],
+        // the transfer is free of gas
+        expected_accounts: vec![
+            expected_account(&sender, 11, 999_000),
+            expected_account(&receiver, 10, 101_000),
+        ],
+    };
+    failure.replay().unwrap();
+
+    let path = env::temp_dir().join(format!("e2e-universe-failure-{}.universe", process::id()));
+    failure.save(&path);
+    assert_eq!(UniverseFailure::load(&path), failure);
+    replay_universe_failure(&path);
+
+    // a run the model got wrong still fails once replayed
+    failure.expected_accounts[1]
+        .balances
+        .insert(LBR_NAME.to_owned(), 100_000);
+    failure.save(&path);
+    let result = panic::catch_unwind(|| replay_universe_failure(&path));
+    fs::remove_file(&path).unwrap();
+    assert!(result.is_err());
+}
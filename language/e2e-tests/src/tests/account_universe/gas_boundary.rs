@@ -0,0 +1,38 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::account_universe::{
+    default_num_accounts, default_num_transactions, gas_boundary_strategy, log_balance_strategy,
+    run_and_assert_gas_cost_stability, run_and_assert_universe, AccountUniverseGen,
+    GasBoundaryP2PGen,
+};
+use proptest::{collection::vec, prelude::*};
+
+proptest! {
+    // These tests are pretty slow but quite comprehensive, so run a smaller number of them.
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    /// Transactions that run out of gas are charged exactly their gas limit, and the others exactly
+    /// the cost of execution.
+    #[test]
+    fn gas_boundary_gas_cost_stability(
+        universe in AccountUniverseGen::success_strategy(2),
+        transfers in vec(
+            any_with::<GasBoundaryP2PGen>((1, 10_000)),
+            0..default_num_transactions(),
+        ),
+    ) {
+        run_and_assert_gas_cost_stability(universe, transfers)?;
+    }
+
+    #[test]
+    fn gas_boundary_mixed(
+        universe in AccountUniverseGen::strategy(
+            2..default_num_accounts(),
+            log_balance_strategy(10_000_000),
+        ),
+        transfers in vec(gas_boundary_strategy(1, 1_000_000), 0..default_num_transactions()),
+    ) {
+        run_and_assert_universe(universe, transfers)?;
+    }
+}
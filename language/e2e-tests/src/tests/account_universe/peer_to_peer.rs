@@ -2,8 +2,9 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::account_universe::{
-    default_num_accounts, default_num_transactions, log_balance_strategy, p2p_strategy,
-    run_and_assert_gas_cost_stability, run_and_assert_universe, AccountUniverseGen, P2PTransferGen,
+    default_num_accounts, default_num_transactions, log_balance_strategy,
+    multi_currency_p2p_strategy, p2p_strategy, run_and_assert_gas_cost_stability,
+    run_and_assert_universe, AccountUniverseGen, P2PTransferGen,
 };
 use proptest::{collection::vec, prelude::*};
 
@@ -53,4 +54,17 @@ proptest! {
     ) {
         run_and_assert_universe(universe, transfers)?;
     }
+
+    /// Accounts hold different mixes of currencies, so transfers may go to receivers that can't
+    /// accept them.
+    #[test]
+    fn p2p_multi_currency(
+        universe in AccountUniverseGen::multi_currency_strategy(
+            2..default_num_accounts(),
+            0u64..100_000,
+        ),
+        transfers in vec(multi_currency_p2p_strategy(1, 50_000), 0..default_num_transactions()),
+    ) {
+        run_and_assert_universe(universe, transfers)?;
+    }
 }
@@ -0,0 +1,42 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    account_universe::{
+        default_num_accounts, default_num_transactions, p2p_strategy, publish_module_strategy,
+        run_and_assert_universe_on, AccountUniverseGen,
+    },
+    executor::FakeExecutor,
+};
+use libra_types::on_chain_config::VMPublishingOption;
+use proptest::{collection::vec, prelude::*};
+
+proptest! {
+    // These tests are pretty slow but quite comprehensive, so run a smaller number of them.
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn publish_modules(
+        universe in AccountUniverseGen::strategy(1..default_num_accounts(), 0u64..100_000),
+        modules in vec(publish_module_strategy(), 0..default_num_transactions()),
+    ) {
+        let executor = FakeExecutor::from_genesis_with_options(VMPublishingOption::open());
+        run_and_assert_universe_on(executor, universe, modules)?;
+    }
+
+    /// Transfers in between publishing transactions keep sequence numbers moving.
+    #[test]
+    fn publish_modules_with_p2p(
+        universe in AccountUniverseGen::strategy(
+            2..default_num_accounts(),
+            1_000_000u64..10_000_000,
+        ),
+        transactions in vec(
+            prop_oneof![publish_module_strategy(), p2p_strategy(1, 10_000)],
+            0..default_num_transactions(),
+        ),
+    ) {
+        let executor = FakeExecutor::from_genesis_with_options(VMPublishingOption::open());
+        run_and_assert_universe_on(executor, universe, transactions)?;
+    }
+}
@@ -2,8 +2,8 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::account_universe::{
-    default_num_accounts, default_num_transactions, run_and_assert_gas_cost_stability,
-    run_and_assert_universe, AccountUniverseGen, RotateKeyGen,
+    default_num_accounts, default_num_transactions, log_balance_strategy, rotate_key_strategy,
+    run_and_assert_gas_cost_stability, run_and_assert_universe, AccountUniverseGen, RotateKeyGen,
 };
 use proptest::{collection::vec, prelude::*};
 
@@ -37,4 +37,15 @@ proptest! {
     ) {
         run_and_assert_universe(universe, key_rotations)?;
     }
+
+    #[test]
+    fn rotate_key_with_p2p(
+        universe in AccountUniverseGen::strategy(
+            2..default_num_accounts(),
+            log_balance_strategy(10_000_000),
+        ),
+        transactions in vec(rotate_key_strategy(1, 1_000_000), 0..default_num_transactions()),
+    ) {
+        run_and_assert_universe(universe, transactions)?;
+    }
 }
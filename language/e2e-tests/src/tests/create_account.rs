@@ -2,11 +2,19 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    account::{self, Account},
+    account::{self, decode_role, Account, AccountData, AccountRoleSpecifier, Role},
     common_transactions::create_account_txn,
     executor::FakeExecutor,
+    gas_costs,
+    keygen::KeyGen,
 };
-use libra_types::{account_config, transaction::TransactionStatus, vm_status::VMStatus};
+use compiled_stdlib::transaction_scripts::StdlibScript;
+use libra_types::{
+    account_config::{self, AccountResource, BalanceResource, COIN1_NAME, LBR_NAME},
+    transaction::{authenticator::AuthenticationKey, TransactionArgument, TransactionStatus},
+    vm_status::VMStatus,
+};
+use transaction_builder::encode_create_parent_vasp_account_script;
 
 #[test]
 fn create_account() {
@@ -15,6 +23,8 @@ fn create_account() {
     let sender = Account::new_libra_root();
     let new_account = Account::new();
 
+    executor.assert_no_resource::<AccountResource>(new_account.address());
+
     // define the arguments to the create account transaction
     let initial_amount = 0;
     let txn = create_account_txn(
@@ -32,6 +42,15 @@ fn create_account() {
         &TransactionStatus::Keep(VMStatus::Executed)
     );
     executor.apply_write_set(output.write_set());
+    executor.assert_resource_exists::<AccountResource>(new_account.address());
+    assert!(executor.exists_resource_at::<BalanceResource>(
+        new_account.address(),
+        vec![account_config::lbr_type_tag()]
+    ));
+    assert!(!executor.exists_resource_at::<BalanceResource>(
+        new_account.address(),
+        vec![account_config::coin1_tag()]
+    ));
 
     // check that numbers in stored DB are correct
     let updated_sender = executor
@@ -43,4 +62,193 @@ fn create_account() {
         .expect("receiver balance must exist");
     assert_eq!(initial_amount, updated_receiver_balance.coin());
     assert_eq!(2, updated_sender.sequence_number());
+    assert_eq!(
+        executor.read_role(&new_account),
+        Some(Role::Known(AccountRoleSpecifier::Unhosted))
+    );
+    executor.assert_currencies(new_account.address(), &["LBR"]);
+}
+
+#[test]
+fn create_account_at_derived_address() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = Account::new_libra_root();
+    let (_, public_key) = KeyGen::from_seed([9u8; 32]).generate_keypair();
+    let auth_key = AuthenticationKey::ed25519(&public_key);
+
+    // the address is known before the account is created
+    let address = account::derive_address(&auth_key);
+    executor.assert_no_resource::<AccountResource>(&address);
+    let output = executor.execute_transaction(
+        sender.create_signed_txn_with_args(
+            StdlibScript::CreateTestingAccount
+                .compiled_bytes()
+                .into_vec(),
+            vec![account_config::lbr_type_tag()],
+            vec![
+                TransactionArgument::Address(address),
+                TransactionArgument::U8Vector(auth_key.prefix().to_vec()),
+                TransactionArgument::Bool(false),
+            ],
+            1,
+            gas_costs::TXN_RESERVED,
+            0,
+            LBR_NAME.to_owned(),
+        ),
+    );
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::Executed)
+    );
+    executor.apply_write_set(output.write_set());
+
+    // the account created there is authenticated by the key it was derived from
+    let account_resource = executor
+        .read_resource::<AccountResource>(&address)
+        .expect("account must exist at the derived address");
+    assert_eq!(
+        account_resource.authentication_key(),
+        auth_key.to_vec().as_slice()
+    );
+}
+
+#[test]
+fn create_account_currencies() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let libra_root = Account::new_libra_root();
+    let single_currency = Account::new();
+    let all_currencies = Account::new();
+    let (_, compliance_public_key) = KeyGen::from_seed([9u8; 32]).generate_keypair();
+
+    for (seq_num, (account, add_all_currencies)) in
+        [(&single_currency, false), (&all_currencies, true)]
+            .iter()
+            .enumerate()
+    {
+        executor.execute_and_apply(libra_root.signed_script_txn(
+            encode_create_parent_vasp_account_script(
+                account_config::coin1_tag(),
+                *account.address(),
+                account.auth_key_prefix(),
+                vec![],
+                vec![],
+                compliance_public_key.to_bytes().to_vec(),
+                *add_all_currencies,
+            ),
+            seq_num as u64 + 1,
+        ));
+    }
+
+    executor.assert_currencies(single_currency.address(), &["Coin1"]);
+    executor.assert_currencies(all_currencies.address(), &["Coin1", "Coin2", "LBR"]);
+}
+
+#[test]
+fn decode_roles() {
+    let roles = [
+        AccountRoleSpecifier::LibraRoot,
+        AccountRoleSpecifier::TreasuryCompliance,
+        AccountRoleSpecifier::DesignatedDealer,
+        AccountRoleSpecifier::Validator,
+        AccountRoleSpecifier::ValidatorOperator,
+        AccountRoleSpecifier::ParentVASP,
+        AccountRoleSpecifier::ChildVASP,
+        AccountRoleSpecifier::Unhosted,
+    ];
+    for role in roles.iter() {
+        let bytes = lcs::to_bytes(&role.id()).unwrap();
+        assert_eq!(decode_role(&bytes), Role::Known(*role));
+    }
+
+    let bytes = lcs::to_bytes(&42u64).unwrap();
+    assert_eq!(decode_role(&bytes), Role::Unknown(42));
+}
+
+#[test]
+fn create_accounts_grows_state_proportionally() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = Account::new_libra_root();
+    let create_accounts = |count: u64, first_seq_num: u64| -> Vec<_> {
+        (0..count)
+            .map(|i| {
+                create_account_txn(
+                    &sender,
+                    &Account::new(),
+                    first_seq_num + i,
+                    0,
+                    account_config::lbr_type_tag(),
+                )
+            })
+            .collect()
+    };
+
+    let growth_1 = executor.run_and_measure_growth(create_accounts(2, 1));
+    let growth_2 = executor.run_and_measure_growth(create_accounts(4, 3));
+
+    assert!(growth_1.resources_added > 0);
+    assert!(growth_1.bytes_added > 0);
+    assert_eq!(growth_2.resources_added, 2 * growth_1.resources_added);
+    assert_eq!(growth_2.bytes_added, 2 * growth_1.bytes_added);
+}
+
+#[test]
+fn created_accounts_are_exactly_the_new_ones() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = Account::new_libra_root();
+    let new_account = Account::new();
+
+    let txn = create_account_txn(&sender, &new_account, 1, 0, account_config::lbr_type_tag());
+    let output = executor.execute_transaction(txn);
+    // the sender's account resource changes too, but it isn't new
+    assert_eq!(
+        executor.created_accounts(&output),
+        vec![*new_account.address()]
+    );
+    executor.assert_created_accounts(&output, &[*new_account.address()]);
+    executor.apply_write_set(output.write_set());
+
+    // once applied, the same write set creates nothing
+    assert!(executor.created_accounts(&output).is_empty());
+}
+
+#[test]
+#[should_panic(expected = "extra [")]
+fn assert_created_accounts_reports_extra_accounts() {
+    let executor = FakeExecutor::from_genesis_file();
+    let sender = Account::new_libra_root();
+
+    let txn = create_account_txn(
+        &sender,
+        &Account::new(),
+        1,
+        0,
+        account_config::lbr_type_tag(),
+    );
+    let output = executor.execute_transaction(txn);
+    executor.assert_created_accounts(&output, &[]);
+}
+
+#[test]
+fn account_data_builder() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let account = AccountData::builder()
+        .balance_in(COIN1_NAME, 500)
+        .balance_in(LBR_NAME, 1_000)
+        .role(AccountRoleSpecifier::ChildVASP)
+        .sequence_number(3)
+        .build();
+    executor.add_account_data(&account);
+
+    assert_eq!(executor.sequence_number(account.address()), Some(3));
+    executor.assert_balance(account.account(), COIN1_NAME, 500);
+    executor.assert_balance(account.account(), LBR_NAME, 1_000);
+    assert_eq!(
+        executor.read_role(account.account()),
+        Some(Role::Known(AccountRoleSpecifier::ChildVASP))
+    );
+
+    // without any balance, an account holds no LBR
+    let empty = AccountData::builder().build();
+    assert_eq!(empty.balance(&account::lbr_currency_code()), 0);
+    assert_eq!(empty.account_role(), AccountRoleSpecifier::ParentVASP);
 }
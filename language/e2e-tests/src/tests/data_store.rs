@@ -1,16 +1,68 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{account::AccountData, compile::compile_script_with_address, executor::FakeExecutor};
+use crate::{
+    account::{self, AccountData},
+    assert_kept_with,
+    common_transactions::peer_to_peer_txn,
+    compile::compile_script_with_address,
+    executor::{FakeExecutor, STATE_FILE_VERSION},
+    remote_data_store::RemoteDataStore,
+    transaction_log::{LogEntry, TransactionLog},
+    transaction_status_eq,
+};
 use bytecode_verifier::verify_module;
 use compiler::Compiler;
+use libra_state_view::StateView;
 use libra_types::{
-    account_config::LBR_NAME,
+    access_path::AccessPath,
+    account_config::{
+        coin1_tag, from_currency_code_string, lbr_type_tag, libra_root_address,
+        type_tag_for_currency_code, AccountResource, BalanceResource, COIN1_NAME, COIN2_NAME,
+        LBR_NAME,
+    },
+    libra_timestamp::LibraTimestampResource,
     transaction::{Module, SignedTransaction, Transaction, TransactionPayload, TransactionStatus},
-    vm_status::{StatusCode, VMStatus},
+    vm_status::{AbortLocation, StatusCode, VMStatus},
+    write_set::{WriteOp, WriteSetMut},
 };
+use move_core_types::move_resource::MoveResource;
+use std::{env, fs, panic, process};
 use vm::CompiledModule;
 
+#[test]
+fn snapshot_and_restore() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    // publish a module so the snapshot covers code as well as resources
+    let (_, txn) = add_module_txn(&sender, 10);
+    executor.execute_and_apply(txn);
+    let snapshot = executor.snapshot();
+    let state_hash = executor.state_hash();
+
+    // try two different continuations from the same state
+    for amount in &[1_000, 2_000] {
+        executor.new_block();
+        executor.execute_and_apply(peer_to_peer_txn(
+            sender.account(),
+            receiver.account(),
+            11,
+            *amount,
+        ));
+        let receiver_balance = executor
+            .read_balance_resource(receiver.account(), account::lbr_currency_code())
+            .expect("receiver balance must exist");
+        assert_eq!(receiver_balance.coin(), 100_000 + amount);
+
+        executor.restore(snapshot.clone());
+        assert_eq!(executor.state_hash(), state_hash);
+    }
+}
+
 #[test]
 fn move_from_across_blocks() {
     let mut executor = FakeExecutor::from_genesis_file();
@@ -360,3 +412,379 @@ fn change_resource_txn(
         LBR_NAME.to_owned(),
     )
 }
+
+#[test]
+fn balances_in_every_currency() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let account = AccountData::new_multi(
+        &[(1_000, LBR_NAME), (2_000, COIN1_NAME), (3_000, COIN2_NAME)],
+        10,
+    );
+    assert_eq!(account.balance_of(COIN2_NAME), Some(3_000));
+    executor.add_account_data(&account);
+
+    // every balance is published, and read back the same
+    assert_eq!(executor.balances(account.address()), account.balances());
+    executor.assert_currencies(account.address(), &[LBR_NAME, COIN1_NAME, COIN2_NAME]);
+
+    let single = AccountData::new(1_000, 10);
+    assert_eq!(single.balance_of(COIN1_NAME), None);
+    executor.add_account_data(&single);
+    assert_eq!(executor.balances(single.address()).len(), 1);
+}
+
+#[test]
+fn read_resources_by_type() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let account = AccountData::new_multi(&[(1_000, LBR_NAME), (2_000, COIN1_NAME)], 10);
+    executor.add_account_data(&account);
+
+    let lbr_balance = executor
+        .read_resource_at::<BalanceResource>(account.address(), vec![lbr_type_tag()])
+        .expect("the LBR balance must be published");
+    assert_eq!(lbr_balance.coin(), 1_000);
+    let coin1_balance = executor
+        .read_resource_at::<BalanceResource>(account.address(), vec![coin1_tag()])
+        .expect("the Coin1 balance must be published");
+    assert_eq!(coin1_balance.coin(), 2_000);
+    assert!(executor
+        .read_resource_at::<BalanceResource>(
+            account.address(),
+            vec![type_tag_for_currency_code(
+                from_currency_code_string(COIN2_NAME).unwrap(),
+            )]
+        )
+        .is_none());
+
+    assert!(executor
+        .read_resource::<LibraTimestampResource>(&libra_root_address())
+        .is_some());
+    assert!(executor
+        .read_resource::<LibraTimestampResource>(account.address())
+        .is_none());
+}
+
+#[test]
+fn save_and_load_state_file() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+    executor.execute_and_apply(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        10,
+        1_000,
+    ));
+    executor.new_block();
+
+    let path = env::temp_dir().join(format!("e2e-state-{}.bin", process::id()));
+    executor.save_state(&path);
+    let mut loaded = FakeExecutor::from_state_file(&path);
+    assert_eq!(loaded.state_hash(), executor.state_hash());
+    assert_eq!(loaded.get_block_time(), executor.get_block_time());
+
+    // the loaded executor keeps going from the saved state
+    let output = loaded.execute_and_apply(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        11,
+        1_000,
+    ));
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::Executed)
+    );
+
+    // files in another version of the format are rejected
+    fs::write(
+        &path,
+        lcs::to_bytes(&(STATE_FILE_VERSION + 1, Vec::<u8>::new())).unwrap(),
+    )
+    .unwrap();
+    let err = FakeExecutor::try_from_state_file(&path).expect_err("the version must be checked");
+    assert!(err.to_string().contains("version"));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn compare_executor_states() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+    let snapshot = executor.snapshot();
+
+    let mut other = FakeExecutor::from_genesis_file();
+    other.add_account_data(&sender);
+    other.add_account_data(&receiver);
+    executor.assert_state_eq(&other);
+
+    // the differences point to the resources that changed
+    executor.execute_and_apply(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        10,
+        1_000,
+    ));
+    let diff = other.diff_state(&executor);
+    let lbr_balance = BalanceResource::struct_tag_for_currency(lbr_type_tag());
+    let changed = diff.changed_resources();
+    assert!(changed.contains(&(*sender.address(), lbr_balance.clone())));
+    assert!(changed.contains(&(*receiver.address(), lbr_balance)));
+    assert!(diff.to_string().contains("::LibraAccount::Balance<"));
+
+    executor.restore(snapshot);
+    executor.assert_state_eq(&other);
+}
+
+#[test]
+#[should_panic(expected = "executors have different states")]
+fn assert_state_eq_reports_differences() {
+    let executor = FakeExecutor::from_genesis_file();
+    let mut other = FakeExecutor::from_genesis_file();
+    other.add_account_data(&AccountData::new(1_000, 0));
+    executor.assert_state_eq(&other);
+}
+
+#[test]
+fn record_and_replay_transaction_log() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    executor.start_recording();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+    executor.execute_and_apply(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        10,
+        1_000,
+    ));
+    // a transaction that gets discarded is recorded along with its status
+    executor.execute_transaction(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        5,
+        1_000,
+    ));
+    let log = executor
+        .stop_recording()
+        .expect("the executor was recording");
+    assert!(executor.transaction_log().is_none());
+
+    let path = env::temp_dir().join(format!("e2e-txn-log-{}.bin", process::id()));
+    log.save(&path);
+    assert_eq!(TransactionLog::load(&path), log);
+    let replayed = FakeExecutor::replay_from(&path);
+    assert_eq!(replayed.state_hash(), executor.state_hash());
+    fs::remove_file(&path).unwrap();
+
+    // replaying fails as soon as a status doesn't match the recorded one
+    let mut tampered = log;
+    let statuses = tampered
+        .entries
+        .iter_mut()
+        .find_map(|entry| match entry {
+            LogEntry::Block(_, statuses) => Some(statuses),
+            LogEntry::WriteSet(_) => None,
+        })
+        .expect("a block was recorded");
+    statuses[0] = TransactionStatus::Discard(VMStatus::Error(StatusCode::SEQUENCE_NUMBER_TOO_OLD));
+    assert!(panic::catch_unwind(|| FakeExecutor::replay(&tampered)).is_err());
+}
+
+#[test]
+fn replay_transaction_log_across_restore() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+    executor.start_recording();
+
+    // the first branch is rolled back, so only the second one's sequence number is valid
+    let snapshot = executor.snapshot();
+    executor.execute_and_apply(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        10,
+        1_000,
+    ));
+    executor.restore(snapshot);
+    executor.execute_and_apply(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        10,
+        2_000,
+    ));
+
+    let log = executor
+        .stop_recording()
+        .expect("the executor was recording");
+    let replayed = FakeExecutor::replay(&log);
+    assert_eq!(replayed.state_hash(), executor.state_hash());
+}
+
+#[test]
+fn add_funded_and_generated_accounts() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let accounts = executor.add_funded_accounts(20, 5_000);
+    assert_eq!(accounts.len(), 20);
+    for account in &accounts {
+        let balance = executor
+            .read_balance_resource(account.account(), account::lbr_currency_code())
+            .expect("the account must be funded");
+        assert_eq!(balance.coin(), 5_000);
+    }
+
+    // generated accounts only depend on the seed
+    let addresses = |accounts: Vec<account::Account>| -> Vec<_> {
+        accounts.iter().map(|account| *account.address()).collect()
+    };
+    let generated = addresses(account::gen_accounts(10));
+    assert_eq!(generated, addresses(account::gen_accounts(10)));
+    assert_eq!(generated[..5], addresses(account::gen_accounts(5))[..]);
+    assert_ne!(
+        generated,
+        addresses(account::gen_accounts_from_seed([7u8; 32], 10))
+    );
+    let distinct: std::collections::BTreeSet<_> = generated.iter().collect();
+    assert_eq!(distinct.len(), 10);
+}
+
+#[test]
+fn remote_data_store_reads_local_data_first() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&sender);
+    let mut store = RemoteDataStore::offline(executor.get_state_view().clone());
+
+    let access_path = AccessPath::new(*sender.address(), AccountResource::resource_path());
+    let unknown = AccountData::new(0, 0);
+    let unknown_path = AccessPath::new(*unknown.address(), AccountResource::resource_path());
+    let blobs = store.multi_get(&[access_path.clone()]).unwrap();
+    assert_eq!(blobs, vec![StateView::get(&store, &access_path).unwrap()]);
+    assert!(blobs[0].is_some());
+    assert!(store
+        .multi_get(&[access_path.clone(), unknown_path])
+        .is_err());
+
+    // Deleting locally hides the data, rather than reading it again.
+    let write_set = WriteSetMut::new(vec![(access_path.clone(), WriteOp::Deletion)])
+        .freeze()
+        .unwrap();
+    store.add_write_set(&write_set);
+    assert_eq!(StateView::get(&store, &access_path).unwrap(), None);
+}
+
+#[test]
+fn remote_data_store_offline() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+    let store = RemoteDataStore::offline(executor.get_state_view().clone());
+    assert!(store.is_offline());
+
+    // A transfer between known accounts runs the same as it does on the executor.
+    let txn = peer_to_peer_txn(sender.account(), receiver.account(), 10, 1_000);
+    let output = store.execute_transaction(txn.clone());
+    let expected = executor.execute_transaction(txn);
+    assert_eq!(output.status(), expected.status());
+    assert_eq!(output.write_set(), expected.write_set());
+
+    // Reading from an unknown account is an error instead of a fetch.
+    let unknown = AccountData::new(0, 0);
+    let access_path = AccessPath::new(*unknown.address(), AccountResource::resource_path());
+    let err = StateView::get(&store, &access_path).unwrap_err();
+    assert!(err.to_string().contains("not available offline"));
+
+    let copy = FakeExecutor::from_data_store(store.to_data_store());
+    assert_eq!(copy.state_hash(), executor.state_hash());
+}
+
+#[test]
+fn remote_data_store_block_sees_earlier_writes() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+    let store = RemoteDataStore::offline(executor.get_state_view().clone());
+
+    // The second transfer only runs if it sees the sequence number bumped by the first.
+    let txns = vec![
+        peer_to_peer_txn(sender.account(), receiver.account(), 10, 1_000),
+        peer_to_peer_txn(sender.account(), receiver.account(), 11, 1_000),
+    ];
+    let outputs = store.execute_block(txns.clone()).unwrap();
+    let expected = executor.execute_block(txns).unwrap();
+    assert_eq!(outputs.len(), 2);
+    for (output, expected) in outputs.iter().zip(&expected) {
+        assert_eq!(
+            output.status(),
+            &TransactionStatus::Keep(VMStatus::Executed)
+        );
+        assert_eq!(output.status(), expected.status());
+        assert_eq!(output.write_set(), expected.write_set());
+    }
+}
+
+#[test]
+fn update_and_remove_resource() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    // Leave the sender just short of the amount it is about to send.
+    executor.update_resource(sender.address(), BalanceResource::new(1_999));
+    executor.assert_balance(sender.account(), LBR_NAME, 1_999);
+    let output = executor.execute_transaction(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        10,
+        2_000,
+    ));
+    // 5 means the balance was insufficient while trying to transfer.
+    assert!(transaction_status_eq(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::MoveAbort(AbortLocation::Script, 5)),
+    ));
+
+    executor.update_resource_at(
+        sender.address(),
+        vec![lbr_type_tag()],
+        BalanceResource::new(2_000),
+    );
+    let output = executor.execute_and_apply(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        10,
+        2_000,
+    ));
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::Executed)
+    );
+    executor.assert_balance(sender.account(), LBR_NAME, 0);
+
+    // Without an LBR balance, the receiver can't be paid in LBR anymore.
+    executor.update_resource(sender.address(), BalanceResource::new(5_000));
+    executor.remove_resource::<BalanceResource>(receiver.address());
+    assert!(executor
+        .read_balance_resource(receiver.account(), account::lbr_currency_code())
+        .is_none());
+    let output = executor.execute_transaction(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        11,
+        1_000,
+    ));
+    assert_kept_with(&output, StatusCode::MISSING_DATA);
+}
@@ -0,0 +1,196 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    account::AccountData,
+    assert_discarded_with,
+    executor::{FakeExecutor, GENESIS_BLOCK_TIME},
+    gas_costs,
+};
+use compiler::Compiler;
+use libra_crypto::HashValue;
+use libra_types::{
+    account_config::{self, LBR_NAME},
+    block_metadata::{BlockMetadata, NewBlockEvent},
+    libra_timestamp::LibraTimestampResource,
+    transaction::{Script, TransactionArgument, TransactionPayload, TransactionStatus},
+    vm_status::{StatusCode, VMStatus},
+};
+use transaction_builder::encode_peer_to_peer_with_metadata_script;
+
+#[test]
+fn empty_block_is_noop() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    executor.assert_empty_block_noop(false);
+}
+
+#[test]
+fn empty_block_after_prologue_is_noop() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let state_hash = executor.state_hash();
+    executor.assert_empty_block_noop(true);
+    // the prologue itself updates the block time
+    assert_ne!(executor.state_hash(), state_hash);
+}
+
+#[test]
+fn advance_many_blocks() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    executor.new_block();
+    let start = executor.get_block_time();
+
+    executor.advance_blocks(100, 1_000);
+    assert_eq!(executor.get_block_time(), start + 100 * 1_000);
+
+    // the on-chain clock follows the block time
+    let timestamp = executor
+        .read_resource::<LibraTimestampResource>(&account_config::libra_root_address())
+        .expect("the timestamp must be published");
+    assert_eq!(timestamp.libra_timestamp.microseconds, start + 100 * 1_000);
+}
+
+#[test]
+fn block_time_and_height_seen_by_transactions() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    assert_eq!(executor.get_block_time(), GENESIS_BLOCK_TIME);
+    let sender = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&sender);
+
+    let program = "
+        import 0x1.LibraBlock;
+        import 0x1.LibraTimestamp;
+
+        main(account: &signer, time: u64, height: u64) {
+            assert(LibraTimestamp.now_microseconds() == move(time), 77);
+            assert(LibraBlock.get_current_block_height() == move(height), 78);
+            return;
+        }
+    ";
+    let script = Compiler {
+        address: *sender.address(),
+        ..Compiler::default()
+    }
+    .into_script_blob("file_name", program)
+    .expect("Script compilation failed");
+    let txn = sender.account().signed_script_txn(
+        Script::new(
+            script,
+            vec![],
+            vec![
+                TransactionArgument::U64(5_000_000),
+                TransactionArgument::U64(42),
+            ],
+        ),
+        10,
+    );
+
+    executor.set_block_time(5_000_000);
+    executor.set_block_height(41);
+    // a NIL block keeps the time, so the transaction sees the one just set
+    let metadata = BlockMetadata::new(
+        HashValue::zero(),
+        0,
+        5_000_000,
+        vec![],
+        account_config::reserved_vm_address(),
+    );
+    let outputs = executor
+        .execute_block_with_metadata(vec![txn], metadata)
+        .expect("The VM should not fail to startup");
+    assert_eq!(outputs.len(), 2);
+    assert_eq!(
+        outputs[1].status(),
+        &TransactionStatus::Keep(VMStatus::Executed)
+    );
+
+    executor.apply_write_set(outputs[0].write_set());
+    assert_eq!(executor.get_block_height(), 42);
+}
+
+#[test]
+fn transactions_see_the_block_time_last_set() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&sender);
+
+    let program = "
+        import 0x1.LibraTimestamp;
+
+        main(account: &signer, time: u64) {
+            assert(LibraTimestamp.now_microseconds() == move(time), 77);
+            return;
+        }
+    ";
+    let script = Compiler {
+        address: *sender.address(),
+        ..Compiler::default()
+    }
+    .into_script_blob("file_name", program)
+    .expect("Script compilation failed");
+    let txn = |time, seq_num| {
+        sender.account().signed_script_txn(
+            Script::new(script.clone(), vec![], vec![TransactionArgument::U64(time)]),
+            seq_num,
+        )
+    };
+
+    executor.set_block_time(5_000_000);
+    let output = executor.execute_and_apply(txn(5_000_000, 10));
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::Executed)
+    );
+
+    executor.set_block_time(7_000_000);
+    let output = executor.execute_and_apply(txn(7_000_000, 11));
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::Executed)
+    );
+}
+
+#[test]
+fn apply_block_with_metadata_advances_time() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    let txn = sender.account().create_signed_txn_with_expiration(
+        TransactionPayload::Script(encode_peer_to_peer_with_metadata_script(
+            account_config::lbr_type_tag(),
+            *receiver.address(),
+            1_000,
+            vec![],
+            vec![],
+        )),
+        10,
+        gas_costs::TXN_RESERVED,
+        0,
+        LBR_NAME.to_owned(),
+        150,
+    );
+    // a NIL block, proposed by the VM rather than a validator, at 200 seconds
+    let metadata = BlockMetadata::new(
+        HashValue::zero(),
+        0,
+        200_000_000,
+        vec![],
+        account_config::reserved_vm_address(),
+    );
+    let outputs = executor.apply_block_with_metadata(vec![txn], metadata);
+    assert_eq!(outputs.len(), 2);
+
+    let event = lcs::from_bytes::<NewBlockEvent>(outputs[0].events()[0].event_data())
+        .expect("the prologue must emit a new block event");
+    assert_eq!(event.proposer(), account_config::reserved_vm_address());
+    // the transaction runs after the prologue, so it sees the new time
+    assert_discarded_with(&outputs[1], StatusCode::TRANSACTION_EXPIRED);
+
+    assert_eq!(executor.get_block_time(), 200_000_000);
+    let timestamp = executor
+        .read_resource::<LibraTimestampResource>(&account_config::libra_root_address())
+        .expect("the timestamp must be published");
+    assert_eq!(timestamp.libra_timestamp.microseconds, 200_000_000);
+}
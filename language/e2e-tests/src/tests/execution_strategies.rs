@@ -2,8 +2,8 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    account::Account,
-    common_transactions::create_account_txn,
+    account::{self, Account, AccountData},
+    common_transactions::{create_account_txn, peer_to_peer_txn},
     execution_strategies::{
         basic_strategy::BasicExecutor,
         guided_strategy::{
@@ -11,11 +11,21 @@ use crate::{
             UnPartitionedGuidedStrategy,
         },
         multi_strategy::MultiExecutor,
+        parallel_strategy::ParallelEquivalenceExecutor,
         random_strategy::RandomExecutor,
-        types::Executor,
+        shuffle_strategy::{
+            permutations, PermutationExecutor, RandomOrderExecutionStrategy, RandomOrderExecutor,
+            SeededFailure, SeededShuffleStrategy, ShuffleExecutor,
+        },
+        types::{Executor, PartitionStrategy},
     },
+    executor::FakeExecutor,
+};
+use libra_types::{
+    account_config,
+    transaction::SignedTransaction,
+    vm_status::{StatusCode, VMStatus},
 };
-use libra_types::{account_config, transaction::SignedTransaction, vm_status::VMStatus};
 
 fn txn(seq_num: u64) -> SignedTransaction {
     let account = Account::new();
@@ -118,3 +128,139 @@ fn test_execution_strategies() {
         exec.execute_block(block).unwrap();
     }
 }
+
+#[test]
+fn seeded_shuffle_is_reproducible() {
+    let block: Vec<_> = (0..10).map(txn).collect();
+    let mut strategy = SeededShuffleStrategy::new(42);
+    let shuffled = strategy.partition(block.clone());
+    assert_eq!(shuffled.len(), 1);
+    assert_eq!(
+        shuffled,
+        SeededShuffleStrategy::new(strategy.seed()).partition(block.clone())
+    );
+
+    // every transaction is executed exactly once
+    let mut sorted = shuffled[0].clone();
+    sorted.sort_by_key(|txn| txn.sequence_number());
+    assert_eq!(sorted, block);
+
+    let outputs = ShuffleExecutor::from_seed(42)
+        .execute_block(block.clone())
+        .unwrap();
+    assert_eq!(
+        outputs,
+        ShuffleExecutor::from_seed(42).execute_block(block).unwrap()
+    );
+}
+
+#[test]
+fn random_seed_is_reported() {
+    let block: Vec<_> = (0..10).map(txn).collect();
+    let mut strategy = SeededShuffleStrategy::from_os_rng();
+    assert_eq!(
+        strategy.partition(block.clone()),
+        SeededShuffleStrategy::new(strategy.seed()).partition(block)
+    );
+
+    let failure = SeededFailure {
+        seed: 42,
+        error: VMStatus::Error(StatusCode::UNKNOWN_INVARIANT_VIOLATION_ERROR),
+    };
+    assert!(failure.to_string().contains("seed 42"));
+}
+
+#[test]
+fn permutation_executor_runs_every_order() {
+    assert_eq!(permutations(&[1, 2, 3]).len(), 6);
+    assert_eq!(permutations::<u8>(&[]), vec![Vec::<u8>::new()]);
+
+    let block: Vec<_> = (0..3).map(txn).collect();
+    let results = PermutationExecutor::default()
+        .execute_permutations(block)
+        .unwrap();
+    assert_eq!(results.len(), 6);
+    assert_eq!(results[0].0, vec![0, 1, 2]);
+    for (order, outputs) in &results {
+        assert_eq!(outputs.len(), order.len());
+    }
+}
+
+#[test]
+fn parallel_execution_matches_sequential() {
+    // transactions from the same sender conflict
+    let block: Vec<_> = (0..10).map(txn).collect();
+    let outputs = ParallelEquivalenceExecutor::default()
+        .execute_block(block)
+        .unwrap();
+    assert_eq!(outputs.len(), 10);
+
+    let mut executor = FakeExecutor::from_genesis_file();
+    let accounts: Vec<_> = (0..4)
+        .map(|_| {
+            let account = AccountData::new(1_000_000, 0);
+            executor.add_account_data(&account);
+            account
+        })
+        .collect();
+    let mut exec = ParallelEquivalenceExecutor::new(executor);
+    // two independent transfers, then one that reads what both of them wrote
+    let block = vec![
+        peer_to_peer_txn(accounts[0].account(), accounts[1].account(), 0, 1_000),
+        peer_to_peer_txn(accounts[2].account(), accounts[3].account(), 0, 1_000),
+        peer_to_peer_txn(accounts[1].account(), accounts[3].account(), 0, 500),
+    ];
+    exec.execute_block(block).unwrap();
+    // the next block starts from the state the first one left
+    let block = vec![peer_to_peer_txn(
+        accounts[0].account(),
+        accounts[2].account(),
+        1,
+        1_000,
+    )];
+    exec.execute_block(block).unwrap();
+    let balance = exec
+        .executor()
+        .read_balance_resource(accounts[2].account(), account::lbr_currency_code())
+        .unwrap()
+        .coin();
+    assert_eq!(balance, 1_000_000);
+}
+
+#[test]
+fn random_orders_leave_the_same_state() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let accounts: Vec<_> = (0..4)
+        .map(|_| {
+            let account = AccountData::new(1_000_000, 0);
+            executor.add_account_data(&account);
+            account
+        })
+        .collect();
+    let block = vec![
+        peer_to_peer_txn(accounts[0].account(), accounts[1].account(), 0, 1_000),
+        peer_to_peer_txn(accounts[2].account(), accounts[3].account(), 0, 2_000),
+        peer_to_peer_txn(accounts[0].account(), accounts[3].account(), 1, 3_000),
+        peer_to_peer_txn(accounts[1].account(), accounts[2].account(), 0, 4_000),
+    ];
+
+    // the transactions of a sender keep their order
+    let mut strategy = RandomOrderExecutionStrategy::new(42, 10);
+    let orders = strategy.orders(&block);
+    assert_eq!(orders.len(), 11);
+    assert_eq!(orders[0], vec![0, 1, 2, 3]);
+    for order in &orders {
+        let position = |idx| order.iter().position(|i| *i == idx).unwrap();
+        assert!(position(0) < position(2));
+    }
+
+    let mut exec = RandomOrderExecutor::new(executor, RandomOrderExecutionStrategy::new(42, 10));
+    let outputs = exec.execute_block(block).unwrap();
+    assert_eq!(outputs.len(), 4);
+    let balance = exec
+        .executor()
+        .read_balance_resource(accounts[3].account(), account::lbr_currency_code())
+        .unwrap()
+        .coin();
+    assert_eq!(balance, 1_005_000);
+}
@@ -6,7 +6,13 @@ use crate::{
     common_transactions::peer_to_peer_txn,
     executor::FakeExecutor,
 };
-use libra_types::vm_status::{StatusCode, VMStatus};
+use compiled_stdlib::transaction_scripts::StdlibScript;
+use libra_types::{
+    account_config::{lbr_type_tag, LBR_NAME},
+    transaction::TransactionArgument,
+    vm_status::{StatusCode, VMStatus},
+    write_set::WriteSet,
+};
 use libra_vm::{data_cache::StateViewCache, transaction_metadata::TransactionMetadata, LibraVM};
 use move_core_types::gas_schedule::{GasAlgebra, GasPrice, GasUnits};
 use move_vm_types::gas_schedule::zero_cost_schedule;
@@ -85,3 +91,78 @@ fn non_existent_sender() {
         StatusCode::SENDING_ACCOUNT_DOES_NOT_EXIST,
     );
 }
+
+#[test]
+fn failed_epilogue_rolls_back_body() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    // the payment leaves the sender a single coin, which can't pay for the gas it used
+    let txn = sender.account().create_signed_txn_with_args(
+        StdlibScript::PeerToPeerWithMetadata
+            .compiled_bytes()
+            .into_vec(),
+        vec![lbr_type_tag()],
+        vec![
+            TransactionArgument::Address(*receiver.address()),
+            TransactionArgument::U64(999_999),
+            TransactionArgument::U8Vector(vec![]),
+            TransactionArgument::U8Vector(vec![]),
+        ],
+        10,
+        100_000,
+        1,
+        LBR_NAME.to_owned(),
+    );
+    let staged = executor.execute_transaction_staged(txn);
+    let touches_receiver = |write_set: &WriteSet| {
+        write_set
+            .iter()
+            .any(|(access_path, _)| access_path.address == *receiver.address())
+    };
+
+    // the payment goes through, but the epilogue then fails to charge for gas
+    let body = staged
+        .body
+        .expect("the body should run")
+        .expect("the payment should succeed");
+    assert!(touches_receiver(&body));
+    assert!(staged.epilogue.expect("the epilogue should run").is_err());
+
+    // so the VM rolls the payment back and only charges the sender for gas
+    assert!(!staged.output.status().is_discarded());
+    assert!(!staged.output.write_set().is_empty());
+    assert!(!touches_receiver(staged.output.write_set()));
+}
+
+#[test]
+fn out_of_gas_only_keeps_gas_charge() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    let txn = peer_to_peer_txn(sender.account(), receiver.account(), 10, 1_000);
+    let gas_used = executor.simulate(txn.clone()).gas_used();
+
+    // with exactly the gas it needs, the payment goes through
+    let output = executor.execute_with_gas_limit(sender.account(), txn.clone(), gas_used);
+    assert_eq!(output.status().vm_status(), VMStatus::Executed);
+
+    // with one unit less, it runs out of gas and only the sender's account is charged and bumped
+    let output = executor.execute_with_gas_limit(sender.account(), txn, gas_used - 1);
+    assert_eq!(
+        output.status().vm_status().status_code(),
+        StatusCode::OUT_OF_GAS
+    );
+    assert!(output
+        .write_set()
+        .iter()
+        .all(|(access_path, _)| access_path.address != *receiver.address()));
+    executor.apply_write_set(output.write_set());
+    assert_eq!(executor.sequence_number(sender.address()), Some(11));
+}
@@ -0,0 +1,135 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::{
+    account::AccountData,
+    assert_gas_used,
+    common_transactions::peer_to_peer_txn,
+    executor::FakeExecutor,
+    gas_costs,
+    gas_snapshot::{GasSnapshot, UPDATE_BASELINE},
+};
+use std::{env, fs, panic, process};
+
+#[test]
+fn record_peer_to_peer_gas() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    let txn = peer_to_peer_txn(sender.account(), receiver.account(), 10, 1_000);
+    assert_gas_used(&executor.execute_transaction(txn), *gas_costs::PEER_TO_PEER);
+
+    let mut snapshot = GasSnapshot::new(0);
+    for seq_num in 10..12 {
+        let txn = peer_to_peer_txn(sender.account(), receiver.account(), seq_num, 1_000);
+        snapshot.record(
+            format!("peer_to_peer_{}", seq_num),
+            executor.execute_and_record_gas(txn),
+        );
+    }
+    let expected = format!(
+        r#"{{ "peer_to_peer_10": {gas}, "peer_to_peer_11": {gas} }}"#,
+        gas = *gas_costs::PEER_TO_PEER
+    );
+    snapshot.assert_matches(&expected);
+}
+
+#[test]
+fn gas_snapshot_diff() {
+    let mut snapshot = GasSnapshot::new(10);
+    snapshot.record("unchanged", 100);
+    snapshot.record("within_tolerance", 110);
+    snapshot.record("drifted", 111);
+    snapshot.record("new", 5);
+
+    let expected = r#"{ "unchanged": 100, "within_tolerance": 100, "drifted": 100, "removed": 1 }"#;
+    assert_eq!(
+        snapshot.diff(expected),
+        vec![
+            "drifted: expected 100, got 111 (+11)".to_string(),
+            "removed: expected 1, not recorded".to_string(),
+            "new: got 5, not in snapshot".to_string(),
+        ]
+    );
+
+    // a snapshot round-trips through its own JSON
+    assert!(snapshot.diff(&snapshot.to_json()).is_empty());
+}
+
+#[test]
+fn expected_gas_costs_report_drift() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    let costs = gas_costs::expected();
+    let peer_to_peer = costs.peer_to_peer;
+    assert_eq!(peer_to_peer, *gas_costs::PEER_TO_PEER);
+
+    // the snapshot of the costs names each one after its field
+    let costs = GasSnapshot::from(&costs);
+    assert_eq!(costs.get("peer_to_peer"), Some(peer_to_peer));
+    assert_eq!(costs.get("no_such_operation"), None);
+
+    let txn = peer_to_peer_txn(sender.account(), receiver.account(), 10, 1_000);
+    let output = executor.execute_transaction(txn);
+    costs.assert_gas_used("peer_to_peer", &output);
+
+    let mut drifted = GasSnapshot::new(0);
+    drifted.record("peer_to_peer", peer_to_peer - 3);
+    let drift = drifted
+        .drift("peer_to_peer", &output)
+        .expect("the gas used must have drifted");
+    assert!(
+        drift.starts_with(&format!(
+            "peer_to_peer: expected {}, got {} (+3)",
+            peer_to_peer - 3,
+            peer_to_peer
+        )),
+        "{}",
+        drift
+    );
+
+    // drifts within the tolerance are accepted
+    let mut tolerant = GasSnapshot::new(3);
+    tolerant.record("peer_to_peer", peer_to_peer - 3);
+    tolerant.assert_gas_used("peer_to_peer", &output);
+}
+
+#[test]
+fn gas_baseline_reports_drift() {
+    // while updating baselines, drifts are accepted rather than reported
+    if env::var(UPDATE_BASELINE).is_ok() {
+        return;
+    }
+    let path = env::temp_dir().join(format!("e2e-gas-baseline-{}.json", process::id()));
+    let _ = fs::remove_file(&path);
+    let mut snapshot = GasSnapshot::new(0);
+    snapshot.record("peer_to_peer", 100);
+
+    // a missing baseline is reported rather than written
+    let message = panic::catch_unwind(|| snapshot.assert_matches_baseline(&path))
+        .expect_err("the missing baseline must be reported")
+        .downcast::<String>()
+        .expect("the panic message must be formatted");
+    assert!(message.contains(UPDATE_BASELINE));
+    assert!(!path.exists());
+
+    fs::write(&path, snapshot.to_json()).unwrap();
+    snapshot.assert_matches_baseline(&path);
+
+    let mut drifted = GasSnapshot::new(0);
+    drifted.record("peer_to_peer", 103);
+    let message = panic::catch_unwind(|| drifted.assert_matches_baseline(&path))
+        .expect_err("the drift must be reported")
+        .downcast::<String>()
+        .expect("the panic message must be formatted");
+    assert!(message.contains("peer_to_peer: expected 100, got 103 (+3)"));
+    assert!(message.contains(UPDATE_BASELINE));
+    fs::remove_file(&path).unwrap();
+}
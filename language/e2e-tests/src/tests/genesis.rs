@@ -2,10 +2,24 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    account::AccountData, common_transactions::peer_to_peer_txn, data_store::GENESIS_CHANGE_SET,
-    executor::FakeExecutor,
+    account::{self, AccountData},
+    common_transactions::peer_to_peer_txn,
+    compile::compile_module_with_address,
+    data_store::GENESIS_CHANGE_SET,
+    executor::{stdlib_modules_with, FakeExecutor},
 };
-use libra_types::transaction::{Transaction, TransactionStatus};
+use compiled_stdlib::{stdlib_modules, transaction_scripts::StdlibScript, StdLibOptions};
+use compiler::Compiler;
+use libra_types::{
+    account_config::{lbr_type_tag, CORE_CODE_ADDRESS, LBR_NAME},
+    on_chain_config::{OnChainConfig, VMPublishingOption, ValidatorSet},
+    transaction::{
+        Script, Transaction, TransactionArgument, TransactionPayload, TransactionStatus,
+    },
+    vm_status::VMStatus,
+};
+use std::collections::BTreeSet;
+use vm::access::ModuleAccess;
 
 #[test]
 fn execute_genesis_write_set() {
@@ -35,3 +49,177 @@ fn execute_genesis_and_drop_other_transaction() {
     assert_eq!(output.len(), 2);
     assert_eq!(output.pop().unwrap().status(), &TransactionStatus::Retry)
 }
+
+#[test]
+fn minimal_genesis_supports_transfers() {
+    let mut executor = FakeExecutor::minimal();
+    let validator_set =
+        ValidatorSet::fetch_config(executor.get_state_view()).expect("validator set must exist");
+    assert_eq!(validator_set.payload().len(), 1);
+
+    // modules genesis doesn't depend on aren't published
+    let published: BTreeSet<_> = executor
+        .published_modules(&CORE_CODE_ADDRESS)
+        .iter()
+        .map(|module| module.self_id().name().as_str().to_owned())
+        .collect();
+    assert!(published.contains("LibraAccount"));
+    assert!(!published.contains("RecoveryAddress"));
+    assert!(published.len() < stdlib_modules(StdLibOptions::Compiled).len());
+
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    let txn = sender.account().create_signed_txn_impl(
+        *sender.address(),
+        TransactionPayload::Script(Script::new(
+            StdlibScript::PeerToPeerWithMetadata
+                .compiled_bytes()
+                .into_vec(),
+            vec![lbr_type_tag()],
+            vec![
+                TransactionArgument::Address(*receiver.address()),
+                TransactionArgument::U64(1_000),
+                TransactionArgument::U8Vector(vec![]),
+                TransactionArgument::U8Vector(vec![]),
+            ],
+        )),
+        10,
+        100_000,
+        0,
+        LBR_NAME.to_owned(),
+    );
+    let output = executor.execute_and_apply(txn);
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::Executed)
+    );
+
+    let receiver_balance = executor
+        .read_balance_resource(receiver.account(), account::lbr_currency_code())
+        .expect("receiver balance must exist");
+    assert_eq!(receiver_balance.coin(), 101_000);
+}
+
+#[test]
+fn custom_genesis_with_extra_module() {
+    let stdlib = stdlib_modules(StdLibOptions::Compiled).to_vec();
+    let extra = Compiler {
+        address: CORE_CODE_ADDRESS,
+        extra_deps: stdlib,
+        ..Compiler::default()
+    }
+    .into_compiled_module(
+        "file_name",
+        "
+        module Extra {
+            import 0x1.LibraTimestamp;
+
+            public now(): u64 {
+                return LibraTimestamp.now_microseconds();
+            }
+        }
+        ",
+    )
+    .expect("Module compilation failed");
+
+    let mut executor = FakeExecutor::custom_genesis(
+        stdlib_modules_with(vec![extra]),
+        None,
+        VMPublishingOption::open(),
+    );
+    assert!(executor
+        .published_modules(&CORE_CODE_ADDRESS)
+        .iter()
+        .any(|module| module.self_id().name().as_str() == "Extra"));
+
+    // the usual accounts are still set up
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+    let output = executor.execute_transaction(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        10,
+        1_000,
+    ));
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::Executed)
+    );
+}
+
+#[test]
+fn custom_genesis_missing_dependency() {
+    let modules: Vec<_> = stdlib_modules(StdLibOptions::Compiled)
+        .iter()
+        .filter(|module| module.self_id().name().as_str() != "LibraTimestamp")
+        .cloned()
+        .collect();
+    let err = FakeExecutor::try_custom_genesis(modules, None, VMPublishingOption::open())
+        .expect_err("genesis without LibraTimestamp must fail");
+    assert!(err.to_string().contains(&format!(
+        "depends on missing module {}::LibraTimestamp",
+        CORE_CODE_ADDRESS
+    )));
+
+    let modules: Vec<_> = stdlib_modules(StdLibOptions::Compiled)
+        .iter()
+        .filter(|module| module.self_id().name().as_str() != "Genesis")
+        .cloned()
+        .collect();
+    let err = FakeExecutor::try_custom_genesis(modules, None, VMPublishingOption::open())
+        .expect_err("genesis without the Genesis module must fail");
+    assert!(err.to_string().contains("Genesis"));
+}
+
+#[test]
+fn published_module_blobs_after_genesis_and_publish() {
+    let mut executor = FakeExecutor::from_genesis_with_options(VMPublishingOption::open());
+
+    // genesis publishes the whole standard library
+    let genesis_ids: BTreeSet<_> = executor
+        .published_module_blobs()
+        .map(|(id, _)| id)
+        .collect();
+    let stdlib_ids: BTreeSet<_> = stdlib_modules(StdLibOptions::Compiled)
+        .iter()
+        .map(|module| module.self_id())
+        .collect();
+    assert_eq!(genesis_ids, stdlib_ids);
+    for (id, blob) in executor.published_module_blobs() {
+        let module = vm::CompiledModule::deserialize(blob).expect("blob must deserialize");
+        assert_eq!(module.self_id(), id);
+    }
+
+    // publishing a module adds exactly that module
+    let sender = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&sender);
+    let payload = compile_module_with_address(
+        sender.address(),
+        "file_name",
+        "
+        module M {
+        }
+        ",
+    );
+    executor.execute_and_apply(sender.account().create_signed_txn_impl(
+        *sender.address(),
+        payload,
+        10,
+        100_000,
+        0,
+        LBR_NAME.to_owned(),
+    ));
+    let ids: BTreeSet<_> = executor
+        .published_module_blobs()
+        .map(|(id, _)| id)
+        .collect();
+    let added: Vec<_> = ids.difference(&genesis_ids).collect();
+    assert_eq!(added.len(), 1);
+    assert_eq!(added[0].address(), sender.address());
+    assert_eq!(added[0].name().as_str(), "M");
+}
@@ -0,0 +1,30 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+use crate::keygen::KeyGen;
+
+#[test]
+fn keygen_is_reproducible_from_seed() {
+    let mut keygen = KeyGen::from_os_rng();
+    let mut replay = KeyGen::from_seed(keygen.seed());
+    for _ in 0..5 {
+        let (privkey, pubkey) = keygen.generate_keypair();
+        let (replayed_privkey, replayed_pubkey) = replay.generate_keypair();
+        assert_eq!(privkey, replayed_privkey);
+        assert_eq!(pubkey, replayed_pubkey);
+    }
+
+    let (_, first) = KeyGen::from_seed([1u8; 32]).generate_keypair();
+    let (_, other) = KeyGen::from_seed([2u8; 32]).generate_keypair();
+    assert_ne!(first, other);
+}
+
+#[test]
+fn keygens_with_the_same_seed_return_the_same_keypairs() {
+    let mut keygen = KeyGen::from_seed([7u8; 32]);
+    let mut other = KeyGen::from_seed([7u8; 32]);
+    let keypairs: Vec<_> = (0..5).map(|_| keygen.next_keypair()).collect();
+    let other_keypairs: Vec<_> = (0..5).map(|_| other.next_keypair()).collect();
+    assert_eq!(keypairs, other_keypairs);
+    assert_ne!(keypairs[0], keypairs[1]);
+}
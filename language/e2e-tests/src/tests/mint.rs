@@ -2,14 +2,14 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    account::{self, Account},
+    account::{self, Account, AccountData},
     executor::FakeExecutor,
     gas_costs::TXN_RESERVED,
     keygen::KeyGen,
     transaction_status_eq,
 };
 use libra_types::{
-    account_config,
+    account_config::{self, AccountResource, CurrencyInfoResource},
     transaction::TransactionStatus,
     vm_status::{AbortLocation, StatusCode, VMStatus},
 };
@@ -181,3 +181,82 @@ fn tiered_update_exchange_rate() {
         .expect("blessed executed txn");
     assert_eq!(1, post_update.sequence_number());
 }
+
+#[test]
+fn register_currency_at_runtime() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    executor
+        .register_currency("Coin3", 1_000_000, 100)
+        .expect("registering a new currency should work");
+
+    let currency_code = account_config::from_currency_code_string("Coin3").unwrap();
+    let currency_info = executor
+        .read_resource_at::<CurrencyInfoResource>(
+            &account_config::libra_root_address(),
+            vec![account_config::type_tag_for_currency_code(
+                currency_code.clone(),
+            )],
+        )
+        .expect("the currency info must be published");
+    assert_eq!(currency_info.currency_code(), currency_code.as_ident_str());
+    assert_eq!(currency_info.scaling_factor(), 1_000_000);
+    assert_eq!(currency_info.fractional_part(), 100);
+
+    // accounts can hold and transfer the new currency
+    let sender = AccountData::with_balance_for_currency(1_000_000, "Coin3", 0);
+    let receiver = AccountData::with_balance_for_currency(0, "Coin3", 0);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+    let output = executor.execute_and_apply(sender.account().signed_script_txn(
+        encode_peer_to_peer_with_metadata_script(
+            account_config::type_tag_for_currency_code(currency_code.clone()),
+            *receiver.address(),
+            1_000,
+            vec![],
+            vec![],
+        ),
+        0,
+    ));
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::Executed)
+    );
+    let balance = executor
+        .read_balance_resource(receiver.account(), currency_code)
+        .expect("receiver balance must exist");
+    assert_eq!(balance.coin(), 1_000);
+
+    // registering a currency twice fails in the VM
+    assert!(executor.register_currency("Coin3", 1_000_000, 100).is_err());
+    assert!(executor.register_currency("Coin1", 1_000_000, 100).is_err());
+}
+
+#[test]
+fn fund_account_after_genesis() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let account = Account::new();
+    executor.assert_no_resource::<AccountResource>(account.address());
+
+    // funding an account that doesn't exist yet creates it
+    executor.fund_account(&account, 1_000, "Coin1");
+    executor.assert_resource_exists::<AccountResource>(account.address());
+    let balance = |executor: &FakeExecutor, currency_code| {
+        executor
+            .read_balance_resource(&account, currency_code)
+            .expect("the account must hold the currency")
+            .coin()
+    };
+    assert_eq!(balance(&executor, account::coin1_currency_code()), 1_000);
+
+    // funding it again adds to its balance
+    executor.fund_account(&account, 500, "Coin1");
+    assert_eq!(balance(&executor, account::coin1_currency_code()), 1_500);
+
+    // funding it in another currency adds a balance in that currency
+    assert!(executor
+        .read_balance_resource(&account, account::coin2_currency_code())
+        .is_none());
+    executor.fund_account(&account, 200, "Coin2");
+    assert_eq!(balance(&executor, account::coin2_currency_code()), 200);
+    assert_eq!(balance(&executor, account::coin1_currency_code()), 1_500);
+}
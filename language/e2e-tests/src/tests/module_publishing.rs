@@ -3,17 +3,88 @@
 
 use crate::{
     account::{Account, AccountData},
-    assert_prologue_parity, assert_status_eq,
-    compile::compile_module_with_address,
+    assert_kept_with, assert_prologue_parity, assert_status_eq, assert_status_type,
+    common_transactions::peer_to_peer_txn,
+    compile::{
+        compile_module_bundle, compile_module_upgrade, compile_module_with_address, module_abi,
+        try_compile_module_bundle, try_compile_module_with_address, FunctionABI, StructABI,
+    },
     executor::FakeExecutor,
     transaction_status_eq,
 };
 use libra_types::{
+    account_address::AccountAddress,
     account_config::{self, LBR_NAME},
     on_chain_config::VMPublishingOption,
-    transaction::TransactionStatus,
+    transaction::{Module, TransactionPayload, TransactionStatus},
     vm_status::{StatusCode, StatusType, VMStatus},
 };
+use move_core_types::{identifier::Identifier, language_storage::ModuleId};
+use vm::{
+    access::ModuleAccess,
+    file_format::{FunctionSignature, SignatureToken},
+    CompiledModule,
+};
+
+// Deep chains of module dependencies should be accepted at publishing time
+#[test]
+fn publish_deep_module_dependency_chain() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let account = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&account);
+
+    // the VM doesn't configure a publish-time dependency depth limit, so no depth is rejected
+    assert_eq!(
+        executor.find_module_dependency_depth_limit(account.account(), 16),
+        None
+    );
+}
+
+// The public functions of a published module can be listed along with their signatures
+#[test]
+fn published_module_functions() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let account = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&account);
+
+    let module_id = ModuleId::new(*account.address(), Identifier::new("M").unwrap());
+    assert_eq!(executor.module_functions(&module_id), None);
+
+    let program = String::from(
+        "
+        module M {
+            public add(a: u64, b: u64): u64 {
+                return move(a) + move(b);
+            }
+
+            helper() {
+                return;
+            }
+        }
+        ",
+    );
+    let compiled_module = compile_module_with_address(account.address(), "file_name", &program);
+    executor.execute_and_apply(account.account().create_signed_txn_impl(
+        *account.address(),
+        compiled_module,
+        10,
+        100_000,
+        0,
+        LBR_NAME.to_owned(),
+    ));
+
+    assert_eq!(
+        executor.module_functions(&module_id),
+        Some(vec![(
+            Identifier::new("add").unwrap(),
+            FunctionSignature {
+                return_: vec![SignatureToken::U64],
+                parameters: vec![SignatureToken::U64, SignatureToken::U64],
+                type_parameters: vec![],
+            }
+        )])
+    );
+}
 
 // A module with an address different from the sender's address should be rejected
 #[test]
@@ -55,14 +126,8 @@ fn bad_module_address() {
 
     // execute and fail for the same reason
     let output = executor.execute_transaction(txn);
-    let status = match output.status() {
-        TransactionStatus::Keep(status) => {
-            assert!(status.status_type() == StatusType::Verification);
-            status
-        }
-        vm_status => panic!("Unexpected verification status: {:?}", vm_status),
-    };
-    assert!(status.status_code() == StatusCode::MODULE_ADDRESS_DOES_NOT_MATCH_SENDER);
+    assert_kept_with(&output, StatusCode::MODULE_ADDRESS_DOES_NOT_MATCH_SENDER);
+    assert_status_type(&output.status().vm_status(), StatusType::Verification);
 }
 
 // Publishing a module named M under the same address twice should be rejected
@@ -117,6 +182,59 @@ fn duplicate_module() {
     ));
 }
 
+#[test]
+fn module_upgrade_is_rejected() {
+    // This VM has no upgrade policy: republishing a module is rejected whether or not the new
+    // version is compatible with the old one.
+    let old = "
+        module M {
+            resource T { f: u64 }
+            public f() {
+                return;
+            }
+        }
+        ";
+    let upgrades = [
+        // compatible: adds a public function
+        "
+        module M {
+            resource T { f: u64 }
+            public f() {
+                return;
+            }
+            public g() {
+                return;
+            }
+        }
+        ",
+        // breaking: removes a public function
+        "
+        module M {
+            resource T { f: u64 }
+        }
+        ",
+        // breaking: changes the layout of a struct
+        "
+        module M {
+            resource T { f: u64, g: bool }
+            public f() {
+                return;
+            }
+        }
+        ",
+    ];
+
+    for new in upgrades.iter() {
+        let mut executor = FakeExecutor::from_genesis_with_options(VMPublishingOption::open());
+        let account = AccountData::new(1_000_000, 0);
+        executor.add_account_data(&account);
+
+        let versions = compile_module_upgrade(account.address(), "M", old, new);
+        let output = executor.publish_module_upgrade(account.account(), 0, versions);
+        assert_kept_with(&output, StatusCode::DUPLICATE_MODULE_NAME);
+    }
+}
+
 #[test]
 pub fn test_publishing_no_modules_non_whitelist_script() {
     // create a FakeExecutor with a genesis from file
@@ -312,3 +430,269 @@ pub fn test_publishing_allow_modules() {
         &TransactionStatus::Keep(VMStatus::Executed)
     );
 }
+
+// Transactions that aren't publishing anything don't write modules
+#[test]
+fn transfer_publishes_no_module() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    let output = executor.assert_no_module_published(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        10,
+        1_000,
+    ));
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::Executed)
+    );
+}
+
+// Broken source is reported through diagnostics rather than a panic
+#[test]
+fn compile_module_diagnostics() {
+    let address = AccountAddress::random();
+
+    let diagnostics = try_compile_module_with_address(
+        &address,
+        "broken",
+        "module M {\n    public f( { return; }\n}",
+    )
+    .expect_err("a syntax error must not compile");
+    assert!(!diagnostics.is_empty());
+    assert!(diagnostics
+        .iter()
+        .all(|diagnostic| diagnostic.file_name == "broken"));
+    assert!(diagnostics[0].message.starts_with("ParserError"));
+    // the parser points at the brace where the parameters should be
+    assert_eq!(diagnostics[0].line, Some(2));
+    assert_eq!(diagnostics[0].column, Some(15));
+
+    assert!(try_compile_module_with_address(
+        &address,
+        "valid",
+        "module M { public f() { return; } }"
+    )
+    .is_ok());
+}
+
+// Interdependent modules are compiled in dependency order, whatever order they're listed in
+#[test]
+fn compile_and_publish_module_bundle() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let account = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&account);
+    let address = account.address();
+
+    let a = String::from("module A { public f() { return; } }");
+    let b = format!(
+        "module B {{ import 0x{}.A; public g() {{ A.f(); return; }} }}",
+        address
+    );
+    let c = format!(
+        "module C {{ import 0x{}.B; public h() {{ B.g(); return; }} }}",
+        address
+    );
+    let blobs = compile_module_bundle(address, &[("c", &c), ("b", &b), ("a", &a)], vec![]);
+    let names: Vec<_> = blobs
+        .iter()
+        .map(|blob| {
+            CompiledModule::deserialize(blob)
+                .unwrap()
+                .name()
+                .as_str()
+                .to_owned()
+        })
+        .collect();
+    assert_eq!(names, vec!["A", "B", "C"]);
+
+    for (seq_num, blob) in (10..).zip(blobs) {
+        let output = executor.execute_and_apply(account.account().create_signed_txn_impl(
+            *address,
+            TransactionPayload::Module(Module::new(blob)),
+            seq_num,
+            100_000,
+            0,
+            LBR_NAME.to_owned(),
+        ));
+        assert!(transaction_status_eq(
+            &output.status(),
+            &TransactionStatus::Keep(VMStatus::Executed)
+        ));
+    }
+
+    // a later bundle can build on the modules already published
+    let d = format!(
+        "module D {{ import 0x{}.C; public k() {{ C.h(); return; }} }}",
+        address
+    );
+    let published = executor.published_modules(address);
+    assert_eq!(published.len(), 3);
+    assert_eq!(
+        compile_module_bundle(address, &[("d", &d)], published).len(),
+        1
+    );
+}
+
+// Modules importing each other can't be ordered, and are reported instead
+#[test]
+fn compile_cyclic_module_bundle() {
+    let address = AccountAddress::random();
+    let a = format!(
+        "module A {{ import 0x{}.B; public f() {{ B.g(); return; }} }}",
+        address
+    );
+    let b = format!(
+        "module B {{ import 0x{}.A; public g() {{ A.f(); return; }} }}",
+        address
+    );
+
+    let diagnostics = try_compile_module_bundle(&address, &[("a", &a), ("b", &b)], vec![])
+        .expect_err("a cyclic bundle must not compile");
+    let file_names: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| diagnostic.file_name.as_str())
+        .collect();
+    assert_eq!(file_names, vec!["a", "b"]);
+    assert!(diagnostics
+        .iter()
+        .all(|diagnostic| diagnostic.message.contains("cyclic dependency")));
+}
+
+// Errors in a module that can be compiled are reported as the compiler raised them
+#[test]
+fn compile_module_bundle_reports_compiler_errors() {
+    let address = AccountAddress::random();
+    let a = String::from("module A { public f(): u64 { return true; } }");
+    let b = format!(
+        "module B {{ import 0x{}.A; public g(): u64 {{ return A.f(); }} }}",
+        address
+    );
+
+    let diagnostics = try_compile_module_bundle(&address, &[("b", &b), ("a", &a)], vec![])
+        .expect_err("a bundle with a type error must not compile");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].file_name, "a");
+    assert!(!diagnostics[0].message.contains("cyclic"));
+}
+
+#[test]
+fn module_abi_describes_public_interface() {
+    let address = AccountAddress::new([7u8; AccountAddress::LENGTH]);
+    let code = "
+        module M {
+            import 0x1.Signer;
+
+            resource T<V> { v: V, owner: address }
+            struct S { flag: bool, bytes: vector<u8> }
+
+            public make<V: copyable>(account: &signer, v: V): Self.T<V> {
+                return T<V> { v: move(v), owner: Signer.address_of(move(account)) };
+            }
+            public flag(s: &Self.S): bool {
+                return *&move(s).flag;
+            }
+            helper() {
+                return;
+            }
+        }
+        ";
+    let blob = try_compile_module_with_address(&address, "file_name", code)
+        .expect("Module compilation failed");
+    let abi = module_abi(&blob);
+    assert_eq!(abi.address, address);
+    assert_eq!(abi.name, "M");
+
+    let type_name = |name: &str| format!("{}::M::{}", address.short_str(), name);
+    assert_eq!(
+        abi.functions,
+        vec![
+            FunctionABI {
+                name: "make".to_string(),
+                type_parameters: vec!["Copyable".to_string()],
+                parameters: vec!["&signer".to_string(), "T0".to_string()],
+                return_: vec![format!("{}<T0>", type_name("T"))],
+            },
+            FunctionABI {
+                name: "flag".to_string(),
+                type_parameters: vec![],
+                parameters: vec![format!("&{}", type_name("S"))],
+                return_: vec!["bool".to_string()],
+            },
+        ]
+    );
+    assert_eq!(
+        abi.structs,
+        vec![
+            StructABI {
+                name: "T".to_string(),
+                is_resource: true,
+                type_parameters: vec!["All".to_string()],
+                fields: vec![
+                    ("v".to_string(), "T0".to_string()),
+                    ("owner".to_string(), "address".to_string()),
+                ],
+            },
+            StructABI {
+                name: "S".to_string(),
+                is_resource: false,
+                type_parameters: vec![],
+                fields: vec![
+                    ("flag".to_string(), "bool".to_string()),
+                    ("bytes".to_string(), "vector<u8>".to_string()),
+                ],
+            },
+        ]
+    );
+
+    // the ABI serializes, so it can be compared to a checked-in golden
+    let json = serde_json::to_string(&abi).expect("ABI must serialize");
+    assert!(json.contains(r#""return":["bool"]"#));
+}
+
+// A sequence of transactions built from one account keeps its sequence number in lockstep
+#[test]
+fn txn_builder_tracks_sequence_numbers() {
+    let mut executor = FakeExecutor::from_genesis_with_options(VMPublishingOption::open());
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    let module = try_compile_module_with_address(
+        sender.address(),
+        "file_name",
+        "
+        module M {
+            public f() {
+                return;
+            }
+        }
+        ",
+    )
+    .expect("module must compile");
+    let mut builder = sender.account().txn_builder(10);
+    let txns = vec![
+        builder.peer_to_peer(receiver.account(), 1_000),
+        builder.publish_module(module),
+        builder.peer_to_peer(receiver.account(), 1_000),
+    ];
+    assert_eq!(builder.next_sequence_number(), 13);
+
+    let outputs = executor.execute_block(txns).unwrap();
+    for output in outputs {
+        assert_eq!(
+            output.status(),
+            &TransactionStatus::Keep(VMStatus::Executed)
+        );
+        executor.apply_write_set(output.write_set());
+    }
+    assert_eq!(
+        executor.sequence_number(sender.address()),
+        Some(builder.next_sequence_number())
+    );
+}
@@ -3,8 +3,9 @@
 
 use crate::{
     account::{self, Account, AccountData},
-    common_transactions::peer_to_peer_txn,
-    executor::FakeExecutor,
+    common_transactions::{add_to_script_allow_list_txn, peer_to_peer_txn, script_allow_list_hash},
+    compile::{conditional_abort_script, conditional_abort_script_with_arg},
+    executor::{FakeExecutor, GenesisConfigs},
     gas_costs::TXN_RESERVED,
     transaction_status_eq,
 };
@@ -13,9 +14,10 @@ use libra_types::{
     account_config::LBR_NAME,
     on_chain_config::LibraVersion,
     transaction::{TransactionArgument, TransactionStatus},
-    vm_status::VMStatus,
+    vm_status::{StatusCode, VMStatus},
 };
 use libra_vm::LibraVM;
+use move_core_types::gas_schedule::{CostTable, GasAlgebra, GasUnits};
 use transaction_builder::encode_update_dual_attestation_limit_script;
 
 #[test]
@@ -118,3 +120,158 @@ fn updated_limit_allows_txn() {
     assert_eq!(3_999_990, sender_balance.coin());
     assert_eq!(1_000_010, receiver_balance.coin());
 }
+
+#[test]
+fn add_script_to_allow_list() {
+    let mut executor = FakeExecutor::whitelist_genesis();
+    let libra_root = Account::new_libra_root();
+    let sender = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&sender);
+
+    // the script isn't on the allow list yet
+    let txn = sender
+        .account()
+        .signed_script_txn(conditional_abort_script_with_arg(false), 10);
+    assert_eq!(
+        executor.execute_transaction(txn.clone()).status(),
+        &TransactionStatus::Discard(VMStatus::Error(StatusCode::UNKNOWN_SCRIPT))
+    );
+
+    executor.new_block();
+    executor.execute_and_apply(add_to_script_allow_list_txn(
+        &libra_root,
+        &StdlibScript::whitelist(),
+        script_allow_list_hash(&conditional_abort_script()),
+        1,
+    ));
+
+    // after reconfiguration the same transaction runs
+    assert_eq!(
+        executor.execute_transaction(txn).status(),
+        &TransactionStatus::Keep(VMStatus::Executed)
+    );
+}
+
+#[test]
+fn override_gas_schedule() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+    let txn = peer_to_peer_txn(sender.account(), receiver.account(), 10, 1_000);
+
+    let genesis_schedule = executor.read_gas_schedule();
+    let genesis_gas_used = executor.execute_transaction(txn.clone()).gas_used();
+
+    // a dearer schedule is read back as set and charges more for the same transaction
+    executor.set_gas_schedule(scale_instruction_gas(&genesis_schedule, 2));
+    assert_eq!(
+        executor.read_gas_schedule(),
+        scale_instruction_gas(&genesis_schedule, 2)
+    );
+    let output = executor.execute_transaction(txn.clone());
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::Executed)
+    );
+    assert!(output.gas_used() > genesis_gas_used);
+
+    // a transaction that fit in its gas budget before runs out of gas
+    executor.set_gas_schedule(scale_instruction_gas(&genesis_schedule, 1_000_000));
+    let output = executor.execute_transaction(txn);
+    assert!(transaction_status_eq(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::Error(StatusCode::OUT_OF_GAS))
+    ));
+}
+
+#[test]
+fn compare_runs_under_gas_schedules() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let mut other = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    for executor in vec![&mut executor, &mut other] {
+        executor.add_account_data(&sender);
+        executor.add_account_data(&receiver);
+    }
+
+    // under the same configuration, a transaction runs the same
+    let comparison = executor.compare_run(
+        peer_to_peer_txn(sender.account(), receiver.account(), 10, 1_000),
+        &mut other,
+    );
+    assert!(!comparison.diverges());
+    assert_eq!(comparison.to_string(), "");
+
+    // under a much dearer schedule, it runs out of gas and doesn't pay the receiver
+    other.set_gas_schedule(scale_instruction_gas(&other.read_gas_schedule(), 1_000_000));
+    let comparison = executor.compare_run(
+        peer_to_peer_txn(sender.account(), receiver.account(), 11, 1_000),
+        &mut other,
+    );
+    assert!(comparison.status_diverges());
+    assert!(comparison.gas_used_diverges());
+    assert!(comparison
+        .write_set_differences
+        .iter()
+        .any(
+            |(access_path, write, other_write)| access_path.address == *receiver.address()
+                && write.is_some()
+                && other_write.is_none()
+        ));
+    assert!(comparison.to_string().starts_with("status: "));
+}
+
+#[test]
+fn genesis_with_configs() {
+    let genesis_schedule = FakeExecutor::from_genesis_file().read_gas_schedule();
+    let mut executor = FakeExecutor::from_genesis_with_configs(
+        GenesisConfigs::new()
+            .gas_schedule(scale_instruction_gas(&genesis_schedule, 2))
+            .libra_version(7)
+            .registered_currencies(&["LBR", "Coin1"]),
+    );
+
+    assert_eq!(
+        executor.read_gas_schedule(),
+        scale_instruction_gas(&genesis_schedule, 2)
+    );
+    let vm = LibraVM::new(executor.get_state_view());
+    assert_eq!(
+        vm.internals().libra_version().unwrap(),
+        LibraVersion { major: 7 }
+    );
+
+    // balances in currencies that are no longer registered aren't reported
+    let account = AccountData::new_multi(
+        &[(1_000_000, LBR_NAME), (1_000, "Coin1"), (1_000, "Coin2")],
+        10,
+    );
+    executor.add_account_data(&account);
+    assert_eq!(
+        executor
+            .balances(account.address())
+            .keys()
+            .map(String::as_str)
+            .collect::<Vec<_>>(),
+        vec!["Coin1", "LBR"]
+    );
+
+    // configs can still be replaced once the executor is running
+    executor.set_config(&LibraVersion { major: 8 });
+    let vm = LibraVM::new(executor.get_state_view());
+    assert_eq!(
+        vm.internals().libra_version().unwrap(),
+        LibraVersion { major: 8 }
+    );
+}
+
+fn scale_instruction_gas(schedule: &CostTable, factor: u64) -> CostTable {
+    let mut schedule = schedule.clone();
+    for cost in &mut schedule.instruction_table {
+        cost.instruction_gas = cost.instruction_gas.mul(GasUnits::new(factor));
+    }
+    schedule
+}
@@ -3,21 +3,33 @@
 
 use crate::{
     account::{self, Account, AccountData},
+    assert_event_emitted, assert_storage_footprint,
     common_transactions::peer_to_peer_txn,
-    executor::FakeExecutor,
+    decode_events,
+    executor::{filter_events, find_event, FakeExecutor, StorageFootprint},
     gas_costs, transaction_status_eq,
+    write_set_diff::{Change, StateKey, WriteSetDiff},
 };
 use compiled_stdlib::transaction_scripts::StdlibScript;
 use libra_types::{
-    account_config::{self, ReceivedPaymentEvent, SentPaymentEvent, LBR_NAME},
+    account_config::{
+        self, BalanceResource, ReceivedPaymentEvent, SentPaymentEvent, COIN1_NAME, LBR_NAME,
+    },
+    event::EventKey,
     on_chain_config::VMPublishingOption,
     transaction::{
         Script, SignedTransaction, TransactionArgument, TransactionOutput, TransactionPayload,
         TransactionStatus,
     },
-    vm_status::{AbortLocation, VMStatus},
+    vm_status::{AbortLocation, StatusCode, VMStatus},
+};
+use move_core_types::value::{MoveStruct, MoveValue};
+use std::{
+    convert::TryFrom,
+    panic::{self, AssertUnwindSafe},
+    time::{Duration, Instant},
 };
-use std::{convert::TryFrom, time::Instant};
+use transaction_builder::encode_peer_to_peer_with_metadata_script;
 use vm::file_format::{Bytecode, CompiledScript};
 
 #[test]
@@ -76,6 +88,243 @@ fn single_peer_to_peer_with_event() {
     }
 }
 
+#[test]
+fn peer_to_peer_collect_events() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    let (status, events) = executor.execute_and_collect_events(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        10,
+        1_000,
+    ));
+    assert_eq!(status, TransactionStatus::Keep(VMStatus::Executed));
+    let sent_event: SentPaymentEvent = find_event(&events).expect("no sent payment event");
+    assert_eq!(sent_event.amount(), 1_000);
+    assert_eq!(sent_event.receiver(), *receiver.address());
+    let received_event: ReceivedPaymentEvent =
+        find_event(&events).expect("no received payment event");
+    assert_eq!(received_event.sender(), *sender.address());
+
+    // replaying the transaction gets it discarded, without any events
+    let (status, events) = executor.execute_and_collect_events(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        10,
+        1_000,
+    ));
+    assert!(status.is_discarded());
+    assert!(events.is_empty());
+    assert!(find_event::<SentPaymentEvent>(&events).is_none());
+}
+
+#[test]
+fn peer_to_peer_assert_event_emitted() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    let output = executor.execute_transaction(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        10,
+        1_000,
+    ));
+    let sent_key = EventKey::try_from(sender.sent_events_key()).unwrap();
+    let received_key = EventKey::try_from(receiver.received_events_key()).unwrap();
+    let sent = assert_event_emitted!(output, sent_key, SentPaymentEvent, |event| {
+        event.receiver() == *receiver.address()
+    });
+    assert_eq!(sent.amount(), 1_000);
+    assert_event_emitted!(output, received_key, ReceivedPaymentEvent);
+
+    // the sender didn't receive anything
+    let sender_received_key = EventKey::try_from(sender.received_events_key()).unwrap();
+    assert!(decode_events::<ReceivedPaymentEvent>(&output, &sender_received_key).is_empty());
+    let mismatch = panic::catch_unwind(AssertUnwindSafe(|| {
+        assert_event_emitted!(output, sent_key, SentPaymentEvent, |event| event.amount()
+            == 1)
+    }));
+    assert!(mismatch.is_err());
+}
+
+#[test]
+fn peer_to_peer_block_events_in_order() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    let txns = vec![
+        peer_to_peer_txn(sender.account(), receiver.account(), 10, 1_000),
+        peer_to_peer_txn(sender.account(), receiver.account(), 11, 2_000),
+        // a replayed sequence number is discarded, so its events must not show up
+        peer_to_peer_txn(sender.account(), receiver.account(), 11, 5_000),
+        peer_to_peer_txn(sender.account(), receiver.account(), 12, 3_000),
+    ];
+    let (outputs, events) = executor.execute_block_and_collect_events(txns);
+    assert!(outputs[2].status().is_discarded());
+
+    let sent_events = filter_events::<SentPaymentEvent>(&events);
+    let sent: Vec<_> = sent_events
+        .iter()
+        .map(|(event, data)| {
+            assert_eq!(event.key().as_bytes(), sender.sent_events_key());
+            (event.sequence_number(), data.amount())
+        })
+        .collect();
+    assert_eq!(sent, vec![(0, 1_000), (1, 2_000), (2, 3_000)]);
+    assert_eq!(filter_events::<ReceivedPaymentEvent>(&events).len(), 3);
+}
+
+#[test]
+fn peer_to_peer_multi_currency() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new_multi(&[(1_000_000, LBR_NAME), (500_000, COIN1_NAME)], 10);
+    let receiver = AccountData::with_balance_for_currency(100_000, COIN1_NAME, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    executor.execute_and_apply(sender.account().signed_script_txn(
+        encode_peer_to_peer_with_metadata_script(
+            account_config::coin1_tag(),
+            *receiver.address(),
+            1_000,
+            vec![],
+            vec![],
+        ),
+        10,
+    ));
+
+    let balance = |account: &AccountData, currency_code| {
+        executor
+            .read_balance_resource(account.account(), currency_code)
+            .expect("balance must exist")
+            .coin()
+    };
+    assert_eq!(balance(&sender, account::coin1_currency_code()), 499_000);
+    assert_eq!(balance(&sender, account::lbr_currency_code()), 1_000_000);
+    assert_eq!(balance(&receiver, account::coin1_currency_code()), 101_000);
+    assert!(executor
+        .read_balance_resource(receiver.account(), account::lbr_currency_code())
+        .is_none());
+}
+
+#[test]
+fn self_transfer_net_zero() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let account = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&account);
+
+    executor.assert_self_transfer_net_zero(account.account(), 1_000, account::lbr_currency_code());
+    // transferring the whole balance to oneself leaves it unchanged apart from gas
+    let balance = executor
+        .read_balance_resource(account.account(), account::lbr_currency_code())
+        .expect("account balance must exist")
+        .coin();
+    executor.assert_self_transfer_net_zero(
+        account.account(),
+        balance - gas_costs::TXN_RESERVED,
+        account::lbr_currency_code(),
+    );
+}
+
+#[test]
+fn peer_to_peer_is_deterministic() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    let state_hash = executor.state_hash();
+    executor.assert_deterministic(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        10,
+        1_000,
+    ));
+    // the executor's own state is not affected
+    assert_eq!(executor.state_hash(), state_hash);
+}
+
+#[test]
+fn peer_to_peer_timed() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    let txn = peer_to_peer_txn(sender.account(), receiver.account(), 10, 1_000);
+    let (output, timing) = executor.execute_transaction_timed(txn);
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::Executed)
+    );
+    // every phase of the script ran
+    assert!(timing.verification > Duration::from_secs(0));
+    assert!(timing.execution > Duration::from_secs(0));
+    assert!(timing.epilogue > Duration::from_secs(0));
+    assert!(timing.total > Duration::from_secs(0));
+}
+
+#[test]
+fn simulate_peer_to_peer() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    let txn = peer_to_peer_txn(sender.account(), receiver.account(), 10, 1_000);
+    let state_hash = executor.state_hash();
+    let output = executor.simulate(txn.clone());
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::Executed)
+    );
+    assert_eq!(executor.state_hash(), state_hash);
+
+    // the dry run previews exactly what executing the transaction does
+    assert_eq!(executor.execute_and_apply(txn), output);
+    assert_ne!(executor.state_hash(), state_hash);
+}
+
+#[test]
+fn sequence_number_after_mixed_batch() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    // replayed and stale sequence numbers are discarded and don't bump the sequence number
+    let txns = [10, 10, 11, 5, 12]
+        .iter()
+        .map(|seq_num| peer_to_peer_txn(sender.account(), receiver.account(), *seq_num, 1_000))
+        .collect();
+    let outputs = executor
+        .execute_block(txns)
+        .expect("The VM should not fail to startup");
+    for output in &outputs {
+        executor.apply_write_set(output.write_set());
+    }
+    let discarded = outputs
+        .iter()
+        .filter(|output| output.status().is_discarded())
+        .count();
+    assert_eq!(discarded, 2);
+    executor.assert_sequence_after(&sender, &outputs);
+}
+
 #[test]
 fn single_peer_to_peer_with_padding() {
     ::libra_logger::Logger::new().environment_only(true).init();
@@ -583,3 +832,216 @@ fn many_to_one_peer_to_peer() {
     println!("EXECUTION TIME: {}", execution_time);
     print_accounts(&executor, &accounts);
 }
+
+#[test]
+fn parallel_block_matches_sequential() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let accounts: Vec<_> = (0..4)
+        .map(|_| {
+            let account = AccountData::new(1_000_000, 10);
+            executor.add_account_data(&account);
+            account
+        })
+        .collect();
+
+    // the first two transfers touch disjoint accounts, the third reuses the first sender and the
+    // last one has a stale sequence number
+    let txns = vec![
+        peer_to_peer_txn(accounts[0].account(), accounts[1].account(), 10, 1_000),
+        peer_to_peer_txn(accounts[2].account(), accounts[3].account(), 10, 1_000),
+        peer_to_peer_txn(accounts[0].account(), accounts[2].account(), 11, 1_000),
+        peer_to_peer_txn(accounts[3].account(), accounts[0].account(), 5, 1_000),
+    ];
+    let sequential = executor.execute_block(txns.clone()).unwrap();
+    let (parallel, report) = executor
+        .execute_block_parallel_with_report(txns.clone())
+        .unwrap();
+    assert_eq!(sequential, parallel);
+    assert_eq!(executor.execute_block_parallel(txns).unwrap(), sequential);
+
+    assert!(report.serialized.contains(&2));
+    assert!(report.conflicts.contains(&(0, 2)));
+    assert!(!report.serialized.contains(&0));
+    assert!(report
+        .conflicts
+        .iter()
+        .all(|(writer, reader)| writer < reader));
+}
+
+#[test]
+fn peer_to_peer_write_set_diff() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    let output = executor.execute_transaction(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        10,
+        1_000,
+    ));
+    let diff = executor.diff_write_set(output.write_set());
+
+    // every change is to a resource the stdlib declares, and both balances changed
+    assert!(diff
+        .entries
+        .iter()
+        .all(|entry| entry.key != StateKey::Unknown));
+    assert!(diff
+        .entries
+        .iter()
+        .all(|entry| matches!(entry.change, Change::Modified { .. })));
+    let lbr_balance = BalanceResource::struct_tag_for_currency(account_config::lbr_type_tag());
+    let changed = diff.changed_resources();
+    assert!(changed.contains(&(*sender.address(), lbr_balance.clone())));
+    assert!(changed.contains(&(*receiver.address(), lbr_balance.clone())));
+    assert!(diff.to_string().contains("::LibraAccount::Balance<"));
+
+    // balances are decoded from the layout of their type: a `Libra<LBR>` holding the value
+    let receiver_balance = diff
+        .entries
+        .iter()
+        .find(|entry| {
+            entry.access_path.address == *receiver.address()
+                && entry.key == StateKey::Resource(lbr_balance.clone())
+        })
+        .expect("the receiver's balance must change");
+    let coin = |value: u64| {
+        MoveStruct::new(vec![MoveValue::Struct(MoveStruct::new(vec![
+            MoveValue::U64(value),
+        ]))])
+    };
+    assert_eq!(receiver_balance.old_value, Some(coin(100_000)));
+    assert_eq!(receiver_balance.new_value, Some(coin(101_000)));
+    assert!(diff.to_string().contains("{{100000}} -> {{101000}}"));
+
+    // applying the write set leaves nothing more to change
+    executor.apply_write_set(output.write_set());
+    assert_eq!(
+        executor.diff_write_set(output.write_set()),
+        WriteSetDiff::default()
+    );
+}
+
+#[test]
+fn peer_to_peer_storage_footprint() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    let transfer = |seq_num| peer_to_peer_txn(sender.account(), receiver.account(), seq_num, 1_000);
+    let (output, footprint) = executor.execute_transaction_with_footprint(transfer(10));
+    assert_eq!(
+        footprint,
+        StorageFootprint {
+            reads: footprint.reads,
+            bytes_read: footprint.bytes_read,
+            ..StorageFootprint::of_write_set(output.write_set())
+        }
+    );
+    // at least the sender and receiver account and balance resources are written
+    assert!(footprint.writes >= 4);
+    assert!(footprint.reads > footprint.writes);
+    executor.apply_write_set(output.write_set());
+
+    // the same transfer touches the same storage
+    let (_, next) = executor.execute_transaction_with_footprint(transfer(11));
+    assert_storage_footprint(&next, &footprint);
+}
+
+#[test]
+#[should_panic(expected = "storage footprint mismatch: reads: expected 2, got 1, bytes_written")]
+fn storage_footprint_mismatch() {
+    let actual = StorageFootprint {
+        reads: 1,
+        ..StorageFootprint::default()
+    };
+    let expected = StorageFootprint {
+        reads: 2,
+        bytes_written: 8,
+        ..StorageFootprint::default()
+    };
+    assert_storage_footprint(&actual, &expected);
+}
+
+#[test]
+fn assert_balance_in_base_units() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    executor.execute_and_apply(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        10,
+        1_500,
+    ));
+    executor.assert_balance(receiver.account(), LBR_NAME, 101_500);
+    // LBR has a scaling factor of 10^6.
+    assert!((executor.balance_display(receiver.account(), LBR_NAME) - 0.1015).abs() < 1e-9);
+}
+
+#[test]
+#[should_panic(expected = "expected 1 (0.000001 LBR), got 1000000 (1 LBR)")]
+fn assert_balance_reports_display_units() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let account = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&account);
+
+    // 1 LBR in display units is 10^6 base units, not 1.
+    executor.assert_balance(account.account(), LBR_NAME, 1);
+}
+
+#[test]
+fn submit_with_auto_seq() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    let payment = || {
+        TransactionPayload::Script(encode_peer_to_peer_with_metadata_script(
+            account_config::lbr_type_tag(),
+            *receiver.address(),
+            1_000,
+            vec![],
+            vec![],
+        ))
+    };
+    for _ in 0..3 {
+        let output = executor.submit_with_auto_seq(
+            sender.account(),
+            payment(),
+            gas_costs::TXN_RESERVED,
+            0,
+            LBR_NAME,
+        );
+        assert_eq!(
+            output.status(),
+            &TransactionStatus::Keep(VMStatus::Executed)
+        );
+    }
+    assert_eq!(executor.sequence_number(sender.address()), Some(13));
+    executor.assert_balance(receiver.account(), LBR_NAME, 103_000);
+
+    // an account that was never created can't send anything
+    let output = executor.submit_with_auto_seq(
+        &Account::new(),
+        payment(),
+        gas_costs::TXN_RESERVED,
+        0,
+        LBR_NAME,
+    );
+    assert_eq!(
+        output.status().vm_status().status_code(),
+        StatusCode::SENDING_ACCOUNT_DOES_NOT_EXIST,
+    );
+    assert!(output.status().is_discarded());
+}
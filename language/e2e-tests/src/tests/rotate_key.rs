@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    account::{self, AccountData},
+    account::{self, Account, AccountData, AccountRoleSpecifier},
     common_transactions::{raw_rotate_key_txn, rotate_key_txn},
     executor::FakeExecutor,
     keygen::KeyGen,
@@ -67,6 +67,88 @@ fn rotate_ed25519_key() {
     );
 }
 
+// An account whose keys are rotated before it is published starts out with the new key
+#[test]
+fn rotate_ed25519_key_before_publishing() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let mut sender = AccountData::new(1_000_000, 10);
+    let old_account = sender.account().clone();
+    let privkey = Ed25519PrivateKey::generate_for_testing();
+    let pubkey = privkey.public_key();
+    let new_key_hash = AuthenticationKey::ed25519(&pubkey).to_vec();
+    sender.rotate_key(privkey, pubkey);
+    executor.add_account_data(&sender);
+
+    let on_chain = executor
+        .read_account_resource(sender.account())
+        .expect("sender must exist");
+    assert_eq!(new_key_hash, on_chain.authentication_key().to_vec());
+
+    let old_key_output = &executor.execute_transaction(rotate_key_txn(&old_account, vec![], 10));
+    assert_eq!(
+        old_key_output.status(),
+        &TransactionStatus::Discard(VMStatus::Error(StatusCode::INVALID_AUTH_KEY)),
+    );
+    let new_key_output =
+        &executor.execute_transaction(rotate_key_txn(sender.account(), new_key_hash, 10));
+    assert_eq!(
+        new_key_output.status(),
+        &TransactionStatus::Keep(VMStatus::Executed),
+    );
+}
+
+// A K-of-N account accepts any K of its keys, at the positions of the keys that signed
+#[test]
+fn multisig_account_signatures() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let multisig = Account::new_multisig(2, 3);
+    let address = *multisig.address();
+
+    // publish the account under its derived address, then switch it to the multisig policy
+    let sender = AccountData::with_account(
+        Account::new_genesis_account(address),
+        1_000_000,
+        account::lbr_currency_code(),
+        10,
+        AccountRoleSpecifier::default(),
+    );
+    executor.add_account_data(&sender);
+    executor.execute_and_apply(rotate_key_txn(sender.account(), multisig.auth_key(), 10));
+    let on_chain = executor
+        .read_account_resource(sender.account())
+        .expect("sender must exist");
+    assert_eq!(multisig.auth_key(), on_chain.authentication_key().to_vec());
+
+    let output = executor.execute_and_apply(multisig.create_multisig_signed_txn(
+        raw_rotate_key_txn(address, multisig.auth_key(), 11),
+        &[0, 2],
+    ));
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::Executed),
+    );
+
+    // too few signatures
+    let output = executor.execute_transaction(
+        multisig
+            .create_multisig_signed_txn(raw_rotate_key_txn(address, multisig.auth_key(), 12), &[1]),
+    );
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Discard(VMStatus::Error(StatusCode::INVALID_SIGNATURE)),
+    );
+
+    // enough signatures, but the bitmap doesn't match the keys that signed
+    let output = executor.execute_transaction(multisig.create_multisig_signed_txn_with_positions(
+        raw_rotate_key_txn(address, multisig.auth_key(), 12),
+        &[(0, 1), (1, 0)],
+    ));
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Discard(VMStatus::Error(StatusCode::INVALID_SIGNATURE)),
+    );
+}
+
 #[test]
 fn rotate_ed25519_multisig_key() {
     let mut executor = FakeExecutor::from_genesis_file();
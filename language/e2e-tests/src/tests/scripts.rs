@@ -1,15 +1,39 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
-use crate::{account, account::AccountData, executor::FakeExecutor, gas_costs};
+use crate::{
+    account,
+    account::AccountData,
+    assert_aborts_with, assert_status_eq, assert_status_eq_strict, assert_status_type,
+    common_transactions::{script_txn, try_script_txn},
+    compile::{
+        compile_module_from_file, compile_module_with_address, compile_script_from_file,
+        compile_script_with_address, conditional_abort_script_with_arg,
+        event_emitting_script_with_count, script_abi, CONDITIONAL_ABORT_CODE,
+    },
+    executor::FakeExecutor,
+    gas_costs, move_abort, MoveAbortInfo,
+};
+use compiled_stdlib::transaction_scripts::StdlibScript;
+use compiler::Compiler;
 use libra_types::{
-    account_address::AccountAddress, account_config, on_chain_config::VMPublishingOption,
-    transaction::TransactionStatus, vm_status::StatusCode,
+    account_address::AccountAddress,
+    account_config,
+    on_chain_config::VMPublishingOption,
+    transaction::{TransactionArgument, TransactionPayload, TransactionStatus},
+    vm_status::{AbortLocation, StatusCode, StatusType, VMStatus},
+};
+use move_core_types::{
+    identifier::Identifier,
+    language_storage::{ModuleId, TypeTag},
 };
-use move_core_types::identifier::Identifier;
-use vm::file_format::{
-    empty_script, AddressIdentifierIndex, Bytecode, FunctionHandle, FunctionHandleIndex,
-    IdentifierIndex, ModuleHandle, ModuleHandleIndex, SignatureIndex,
+use std::{env, fs, panic, process};
+use vm::{
+    file_format::{
+        empty_script, AddressIdentifierIndex, Bytecode, FunctionHandle, FunctionHandleIndex,
+        IdentifierIndex, ModuleHandle, ModuleHandleIndex, SignatureIndex,
+    },
+    CompiledModule,
 };
 
 #[test]
@@ -277,3 +301,559 @@ fn script_bad_sig_function_dep() {
     assert_eq!(balance, updated_sender_balance.coin());
     assert_eq!(11, updated_sender.sequence_number());
 }
+
+#[test]
+fn script_conditional_abort() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&sender);
+
+    // a false argument takes the success path
+    let output = executor.execute_and_apply(
+        sender
+            .account()
+            .signed_script_txn(conditional_abort_script_with_arg(false), 10),
+    );
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::Executed)
+    );
+
+    // a true argument takes the abort path with the user code
+    let output = executor.execute_transaction(
+        sender
+            .account()
+            .signed_script_txn(conditional_abort_script_with_arg(true), 11),
+    );
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::MoveAbort(
+            AbortLocation::Script,
+            CONDITIONAL_ABORT_CODE
+        ))
+    );
+    if let TransactionStatus::Keep(status) = output.status() {
+        assert_status_type(status, StatusType::Execution);
+    }
+}
+
+#[test]
+fn script_event_emission_gas() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&sender);
+
+    // The gas schedule has no entry for emitting an event: the native is free, so each event costs
+    // only the bytecode executed to emit it.
+    let gas_used: Vec<u64> = (0..3)
+        .map(|count| {
+            let output = executor.execute_and_apply(
+                sender
+                    .account()
+                    .signed_script_txn(event_emitting_script_with_count(count), 10 + count),
+            );
+            assert_eq!(output.events().len() as u64, count);
+            output.gas_used()
+        })
+        .collect();
+    let per_event_cost = gas_used[1] - gas_used[0];
+    assert!(per_event_cost > 0);
+    assert_eq!(gas_used[2] - gas_used[1], per_event_cost);
+}
+
+#[test]
+fn script_decoded_abort_outcome() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&sender);
+
+    // no abort information for a successful transaction
+    let outcome = executor.execute_decoded(
+        sender
+            .account()
+            .signed_script_txn(conditional_abort_script_with_arg(false), 10),
+    );
+    assert_eq!(outcome.status, TransactionStatus::Keep(VMStatus::Executed));
+    assert_eq!(outcome.abort_code, None);
+    assert_eq!(outcome.abort_module, None);
+
+    // an abort in a script has a code but no module
+    let outcome = executor.execute_decoded(
+        sender
+            .account()
+            .signed_script_txn(conditional_abort_script_with_arg(true), 11),
+    );
+    assert_eq!(outcome.abort_code, Some(CONDITIONAL_ABORT_CODE));
+    assert_eq!(outcome.abort_module, None);
+
+    // an abort in a module reports the module and the code as raised
+    let module_code = "
+        module M {
+            public fail() {
+                abort(775);
+            }
+        }
+        ";
+    let module = Compiler {
+        address: *sender.address(),
+        ..Compiler::default()
+    }
+    .into_compiled_module("file_name", module_code)
+    .expect("Module compilation failed");
+    executor.execute_and_apply(sender.account().create_signed_txn_impl(
+        *sender.address(),
+        compile_module_with_address(sender.address(), "file_name", module_code),
+        12,
+        gas_costs::TXN_RESERVED,
+        0,
+        account_config::LBR_NAME.to_owned(),
+    ));
+    let script_code = format!(
+        "
+        import 0x{}.M;
+
+        main() {{
+            M.fail();
+            return;
+        }}
+        ",
+        sender.address(),
+    );
+    let outcome = executor.execute_decoded(sender.account().create_signed_txn_impl(
+        *sender.address(),
+        compile_script_with_address(sender.address(), "file_name", &script_code, vec![module]),
+        13,
+        gas_costs::TXN_RESERVED,
+        0,
+        account_config::LBR_NAME.to_owned(),
+    ));
+    assert_eq!(outcome.abort_code, Some(775));
+    let module_id = ModuleId::new(*sender.address(), Identifier::new("M").unwrap());
+    assert_eq!(outcome.abort_module, Some(module_id.clone()));
+    if let TransactionStatus::Keep(status) = &outcome.status {
+        assert_status_eq_strict(
+            status,
+            &VMStatus::MoveAbort(AbortLocation::Module(module_id), 775),
+        );
+    }
+}
+
+#[test]
+fn strict_status_eq_compares_abort_locations() {
+    let module_id = ModuleId::new(AccountAddress::random(), Identifier::new("M").unwrap());
+    let module_abort = VMStatus::MoveAbort(AbortLocation::Module(module_id), 775);
+    let script_abort = VMStatus::MoveAbort(AbortLocation::Script, 775);
+
+    // the lenient comparison only looks at the codes
+    assert!(assert_status_eq(&module_abort, &script_abort));
+    assert!(assert_status_eq_strict(&module_abort, &module_abort));
+    assert!(
+        std::panic::catch_unwind(|| assert_status_eq_strict(&module_abort, &script_abort)).is_err()
+    );
+}
+
+// The VM only traces instructions in debug builds.
+#[cfg(debug_assertions)]
+#[test]
+fn script_abort_trace() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&sender);
+
+    // a successful transaction has nothing to trace
+    let (output, trace) = executor.execute_transaction_with_trace(
+        sender
+            .account()
+            .signed_script_txn(conditional_abort_script_with_arg(false), 10),
+    );
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::Executed)
+    );
+    assert_eq!(trace.abort_frame, None);
+
+    let module_code = "
+        module M {
+            public fail() {
+                abort(775);
+            }
+        }
+        ";
+    let module = Compiler {
+        address: *sender.address(),
+        ..Compiler::default()
+    }
+    .into_compiled_module("file_name", module_code)
+    .expect("Module compilation failed");
+    executor.execute_and_apply(sender.account().create_signed_txn_impl(
+        *sender.address(),
+        compile_module_with_address(sender.address(), "file_name", module_code),
+        10,
+        gas_costs::TXN_RESERVED,
+        0,
+        account_config::LBR_NAME.to_owned(),
+    ));
+    let script_code = format!(
+        "
+        import 0x{}.M;
+
+        main() {{
+            M.fail();
+            return;
+        }}
+        ",
+        sender.address(),
+    );
+
+    // the trace names the function and offset the abort was raised at
+    let (output, trace) =
+        executor.execute_transaction_with_trace(sender.account().create_signed_txn_impl(
+            *sender.address(),
+            compile_script_with_address(sender.address(), "file_name", &script_code, vec![module]),
+            11,
+            gas_costs::TXN_RESERVED,
+            0,
+            account_config::LBR_NAME.to_owned(),
+        ));
+    assert_eq!(output.status().vm_status().move_abort_code(), Some(775));
+    assert_eq!(
+        trace.abort_frame,
+        Some((format!("0x{}::M::fail", sender.address()), 1))
+    );
+
+    // aborts in the script itself are traced too
+    let (_, trace) = executor.execute_transaction_with_trace(
+        sender
+            .account()
+            .signed_script_txn(conditional_abort_script_with_arg(true), 11),
+    );
+    assert_eq!(
+        trace.abort_frame.map(|(function, _)| function),
+        Some("Script::main".to_string())
+    );
+}
+
+// The VM only traces instructions in debug builds.
+#[cfg(debug_assertions)]
+#[test]
+fn script_instruction_trace() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&sender);
+
+    let (output, instructions) = executor.execute_transaction_with_instructions(
+        sender
+            .account()
+            .signed_script_txn(conditional_abort_script_with_arg(true), 10),
+    );
+    assert_eq!(
+        output.status().vm_status().move_abort_code(),
+        Some(CONDITIONAL_ABORT_CODE)
+    );
+
+    // the prologue runs first, calling into other functions
+    assert_ne!(instructions[0].function, "Script::main");
+    assert!(instructions
+        .iter()
+        .any(|instruction| instruction.call_depth > 0));
+
+    // the script aborts with its code on top of the stack
+    let script: Vec<_> = instructions
+        .iter()
+        .filter(|instruction| instruction.function == "Script::main")
+        .collect();
+    let abort = script.last().expect("the script must be traced");
+    assert_eq!(abort.instruction, Bytecode::Abort);
+    assert_eq!(abort.call_depth, 0);
+    assert_eq!(
+        abort.operand_stack.last(),
+        Some(&format!("U64({})", CONDITIONAL_ABORT_CODE))
+    );
+}
+
+#[test]
+fn script_abi_lists_arguments_in_order() {
+    let code = "
+    main<Token>(account: &signer, payee: address, amount: u64, metadata: vector<u8>) {
+      return;
+    }
+";
+    let compiler = Compiler {
+        address: account_config::CORE_CODE_ADDRESS,
+        ..Compiler::default()
+    };
+    let bytes = compiler
+        .into_script_blob("file_name", code)
+        .expect("Script compilation failed");
+    let abi = script_abi(&bytes);
+    assert_eq!(abi.code(), &bytes[..]);
+    assert_eq!(abi.ty_args().len(), 1);
+    // the signer is skipped, as it isn't passed in the transaction
+    let arg_types: Vec<_> = abi
+        .args()
+        .iter()
+        .map(|arg| arg.type_tag().clone())
+        .collect();
+    assert_eq!(
+        arg_types,
+        vec![
+            TypeTag::Address,
+            TypeTag::U64,
+            TypeTag::Vector(Box::new(TypeTag::U8))
+        ]
+    );
+
+    // the arguments match the ones of the ABI generated from the source of stdlib scripts
+    let script = StdlibScript::PeerToPeerWithMetadata;
+    let abi = script_abi(&script.compiled_bytes().into_vec());
+    let expected = script.abi();
+    assert_eq!(abi.ty_args().len(), expected.ty_args().len());
+    assert!(abi
+        .args()
+        .iter()
+        .map(|arg| arg.type_tag())
+        .eq(expected.args().iter().map(|arg| arg.type_tag())));
+}
+
+#[test]
+fn script_move_abort_info_names_module() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&sender);
+
+    // two modules aborting with the same code
+    let mut modules = vec![];
+    for (idx, name) in ["M", "N"].iter().enumerate() {
+        let module_code = format!(
+            "
+        module {} {{
+            public fail() {{
+                abort(42);
+            }}
+        }}
+        ",
+            name
+        );
+        modules.push(
+            Compiler {
+                address: *sender.address(),
+                ..Compiler::default()
+            }
+            .into_compiled_module("file_name", &module_code)
+            .expect("Module compilation failed"),
+        );
+        executor.execute_and_apply(sender.account().create_signed_txn_impl(
+            *sender.address(),
+            compile_module_with_address(sender.address(), "file_name", &module_code),
+            10 + idx as u64,
+            gas_costs::TXN_RESERVED,
+            0,
+            account_config::LBR_NAME.to_owned(),
+        ));
+    }
+
+    let script_code = format!(
+        "
+        import 0x{}.N;
+
+        main() {{
+            N.fail();
+            return;
+        }}
+        ",
+        sender.address(),
+    );
+    let output = executor.execute_transaction(sender.account().create_signed_txn_impl(
+        *sender.address(),
+        compile_script_with_address(sender.address(), "file_name", &script_code, modules),
+        12,
+        gas_costs::TXN_RESERVED,
+        0,
+        account_config::LBR_NAME.to_owned(),
+    ));
+    let module_id = |name| ModuleId::new(*sender.address(), Identifier::new(name).unwrap());
+    assert_eq!(
+        move_abort(&output),
+        Some(MoveAbortInfo {
+            location: AbortLocation::Module(module_id("N")),
+            abort_code: 42,
+        })
+    );
+    assert_aborts_with(&output, &module_id("N"), 42);
+    assert_ne!(
+        move_abort(&output).map(|info| info.location),
+        Some(AbortLocation::Module(module_id("M")))
+    );
+
+    // no abort information for a successful transaction
+    let output = executor.execute_transaction(
+        sender
+            .account()
+            .signed_script_txn(conditional_abort_script_with_arg(false), 12),
+    );
+    assert_eq!(move_abort(&output), None);
+}
+
+#[test]
+fn compile_module_and_script_from_files() {
+    let mut executor = FakeExecutor::from_genesis_with_options(VMPublishingOption::open());
+    let sender = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&sender);
+
+    let dir = env::temp_dir();
+    let module_path = dir.join(format!("e2e-module-{}.mvir", process::id()));
+    let script_path = dir.join(format!("e2e-script-{}.mvir", process::id()));
+    fs::write(
+        &module_path,
+        "
+        module M {
+            public answer(): u64 {
+                return 42;
+            }
+        }
+        ",
+    )
+    .unwrap();
+    fs::write(
+        &script_path,
+        format!(
+            "
+        import 0x{}.M;
+
+        main() {{
+            assert(M.answer() == 42, 1);
+            return;
+        }}
+        ",
+            sender.address()
+        ),
+    )
+    .unwrap();
+
+    let module_payload = compile_module_from_file(sender.address(), &module_path);
+    let module = match &module_payload {
+        TransactionPayload::Module(module) => {
+            CompiledModule::deserialize(module.code()).expect("module must deserialize")
+        }
+        _ => panic!("expected a module payload"),
+    };
+    executor.execute_and_apply(sender.account().create_signed_txn_impl(
+        *sender.address(),
+        module_payload,
+        10,
+        gas_costs::TXN_RESERVED,
+        0,
+        account_config::LBR_NAME.to_owned(),
+    ));
+    executor.execute_and_apply(sender.account().create_signed_txn_impl(
+        *sender.address(),
+        compile_script_from_file(sender.address(), &script_path, vec![module]),
+        11,
+        gas_costs::TXN_RESERVED,
+        0,
+        account_config::LBR_NAME.to_owned(),
+    ));
+    fs::remove_file(&module_path).unwrap();
+    fs::remove_file(&script_path).unwrap();
+}
+
+#[test]
+#[should_panic(expected = "e2e-broken-module")]
+fn compile_module_from_file_names_file() {
+    let path = env::temp_dir().join(format!("e2e-broken-module-{}.mvir", process::id()));
+    fs::write(&path, "module M { public f() { return 1; } }").unwrap();
+    let result = panic::catch_unwind(|| {
+        compile_module_from_file(&AccountAddress::new([1u8; AccountAddress::LENGTH]), &path);
+    });
+    fs::remove_file(&path).unwrap();
+    if let Err(error) = result {
+        panic::resume_unwind(error);
+    }
+}
+
+#[test]
+fn script_txn_with_typed_arguments() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&sender);
+
+    let code = "
+    import 0x1.Signer;
+
+    main(account: &signer, amount: u64, payee: address, flag: bool) {
+      assert(copy(amount) == 42, 1);
+      assert(Signer.address_of(move(account)) == copy(payee), 2);
+      assert(copy(flag), 3);
+      return;
+    }
+";
+    let script = Compiler {
+        address: account_config::CORE_CODE_ADDRESS,
+        ..Compiler::default()
+    }
+    .into_script_blob("file_name", code)
+    .expect("Script compilation failed");
+
+    let args = vec![
+        42u64.into(),
+        TransactionArgument::from(*sender.address()),
+        true.into(),
+    ];
+    executor.execute_and_apply(script_txn(
+        sender.account(),
+        script.clone(),
+        vec![],
+        args.clone(),
+        10,
+    ));
+
+    // mismatches are caught before the transaction is built
+    let err = try_script_txn(
+        sender.account(),
+        script.clone(),
+        vec![],
+        args[..2].to_vec(),
+        11,
+    )
+    .expect_err("the number of arguments must be checked");
+    assert!(err.to_string().contains("takes 3 arguments, got 2"));
+    let err = try_script_txn(
+        sender.account(),
+        script.clone(),
+        vec![],
+        vec![42u8.into(), args[1].clone(), args[2].clone()],
+        11,
+    )
+    .expect_err("the argument types must be checked");
+    assert!(err.to_string().contains("argument 0"));
+    let err = try_script_txn(sender.account(), script, vec![TypeTag::U64], args, 11)
+        .expect_err("the number of type arguments must be checked");
+    assert!(err.to_string().contains("type arguments"));
+}
+
+#[test]
+fn script_txn_with_unpassable_argument() {
+    let sender = AccountData::new(1_000_000, 10);
+    let code = "
+    main<T: copyable>(value: T) {
+      return;
+    }
+";
+    let script = Compiler {
+        address: account_config::CORE_CODE_ADDRESS,
+        ..Compiler::default()
+    }
+    .into_script_blob("file_name", code)
+    .expect("Script compilation failed");
+
+    // no transaction argument can have the type of a type parameter
+    let err = try_script_txn(
+        sender.account(),
+        script,
+        vec![TypeTag::U64],
+        vec![42u64.into()],
+        10,
+    )
+    .expect_err("arguments that can't be passed must be reported");
+    assert!(err.to_string().contains("can't be passed in a transaction"));
+}
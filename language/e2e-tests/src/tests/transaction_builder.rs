@@ -10,16 +10,22 @@
 
 use crate::{
     account::{self, Account, AccountData},
-    common_transactions::{create_account_txn, rotate_key_txn},
+    assert_aborts_with,
+    common_transactions::{
+        create_account_txn, p2p_with_metadata_and_signature, p2p_with_metadata_and_wrong_signature,
+        peer_to_peer_with_metadata_txn, rotate_key_txn,
+    },
     executor::FakeExecutor,
     keygen::KeyGen,
 };
 use libra_crypto::{ed25519::Ed25519PrivateKey, traits::SigningKey, PrivateKey, Uniform};
 use libra_types::{
-    account_config,
+    account_config::{self, ChildVASP, Limit, ParentVASP, COIN1_NAME},
+    on_chain_config::VMPublishingOption,
     transaction::{authenticator::AuthenticationKey, TransactionOutput, TransactionStatus},
     vm_status::{StatusCode, VMStatus},
 };
+use move_core_types::{identifier::Identifier, language_storage::ModuleId};
 use transaction_builder::*;
 
 const COIN1_THRESHOLD: u64 = 10_000_000_000 / 5;
@@ -271,6 +277,204 @@ fn create_child_vasp_with_balance() {
     );
 }
 
+#[test]
+fn dual_attestation_signature_builders() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let payment_sender = Account::new();
+    let payment_receiver = Account::new();
+    let libra_root = Account::new_libra_root();
+    let dd = Account::new_genesis_account(account_config::testnet_dd_account_address());
+    let mut keygen = KeyGen::from_seed([9u8; 32]);
+    let (_, sender_compliance_public_key) = keygen.generate_keypair();
+    let (receiver_compliance_private_key, receiver_compliance_public_key) =
+        keygen.generate_keypair();
+
+    for (seq_num, (account, compliance_public_key)) in [
+        (&payment_sender, sender_compliance_public_key),
+        (&payment_receiver, receiver_compliance_public_key),
+    ]
+    .iter()
+    .enumerate()
+    {
+        executor.execute_and_apply(libra_root.signed_script_txn(
+            encode_create_parent_vasp_account_script(
+                account_config::coin1_tag(),
+                *account.address(),
+                account.auth_key_prefix(),
+                vec![],
+                vec![],
+                compliance_public_key.to_bytes().to_vec(),
+                false,
+            ),
+            seq_num as u64 + 1,
+        ));
+    }
+    // DD -> VASP payments are subject to the travel rule, so mint below the threshold twice
+    for seq_num in 0..2 {
+        executor.execute_and_apply(dd.signed_script_txn(
+            encode_testnet_mint_script(
+                account_config::coin1_tag(),
+                *payment_sender.address(),
+                COIN1_THRESHOLD - 1,
+            ),
+            seq_num,
+        ));
+    }
+
+    // a signature over the right message from the receiver's compliance key is accepted
+    let output = executor.execute_and_apply(p2p_with_metadata_and_signature(
+        &payment_sender,
+        &payment_receiver,
+        0,
+        COIN1_THRESHOLD,
+        lcs::to_bytes(&7777u64).unwrap(),
+        &receiver_compliance_private_key,
+    ));
+    assert_eq!(
+        output.status().vm_status().status_code(),
+        StatusCode::EXECUTED
+    );
+
+    // a well formed signature over the wrong message is rejected
+    let output = executor.execute_transaction(p2p_with_metadata_and_wrong_signature(
+        &payment_sender,
+        &payment_receiver,
+        1,
+        COIN1_THRESHOLD,
+        lcs::to_bytes(&9999u64).unwrap(),
+        &receiver_compliance_private_key,
+    ));
+    assert_eq!(
+        output.status().vm_status().move_abort_code(),
+        Some(MISMATCHED_METADATA_SIGNATURE_ERROR_CODE)
+    );
+
+    // an empty signature is rejected before being checked
+    let output = executor.execute_transaction(peer_to_peer_with_metadata_txn(
+        &payment_sender,
+        &payment_receiver,
+        1,
+        COIN1_THRESHOLD,
+        lcs::to_bytes(&9999u64).unwrap(),
+        vec![],
+    ));
+    assert_eq!(
+        output.status().vm_status().move_abort_code(),
+        Some(BAD_METADATA_SIGNATURE_ERROR_CODE)
+    );
+
+    // below the threshold, no signature is needed at all
+    let output = executor.execute_and_apply(peer_to_peer_with_metadata_txn(
+        &payment_sender,
+        &payment_receiver,
+        1,
+        COIN1_THRESHOLD / 2,
+        vec![],
+        vec![],
+    ));
+    assert_eq!(
+        output.status().vm_status().status_code(),
+        StatusCode::EXECUTED
+    );
+}
+
+#[test]
+fn dual_attestation_with_configured_limit() {
+    let micro_lbr_limit = 1_000;
+    let mut executor = FakeExecutor::from_genesis_with_dual_attestation_limit(
+        micro_lbr_limit,
+        VMPublishingOption::open(),
+    );
+    assert_eq!(
+        executor.read_resource::<Limit>(&account_config::libra_root_address()),
+        Some(Limit { micro_lbr_limit })
+    );
+
+    let payment_sender = Account::new();
+    let payment_receiver = Account::new();
+    let libra_root = Account::new_libra_root();
+    let dd = Account::new_genesis_account(account_config::testnet_dd_account_address());
+    let mut keygen = KeyGen::from_seed([9u8; 32]);
+    let (_, sender_compliance_public_key) = keygen.generate_keypair();
+    let (receiver_compliance_private_key, receiver_compliance_public_key) =
+        keygen.generate_keypair();
+    for (seq_num, (account, compliance_public_key)) in [
+        (&payment_sender, sender_compliance_public_key),
+        (&payment_receiver, receiver_compliance_public_key),
+    ]
+    .iter()
+    .enumerate()
+    {
+        executor.execute_and_apply(libra_root.signed_script_txn(
+            encode_create_parent_vasp_account_script(
+                account_config::coin1_tag(),
+                *account.address(),
+                account.auth_key_prefix(),
+                vec![],
+                vec![],
+                compliance_public_key.to_bytes().to_vec(),
+                false,
+            ),
+            seq_num as u64 + 1,
+        ));
+    }
+    // COIN1_THRESHOLD is the value in Coin1 of the genesis limit of 1_000_000_000 micro LBR
+    let threshold = micro_lbr_limit * COIN1_THRESHOLD / 1_000_000_000;
+    // testnet mints are held to the limit too, so mint just below it a few times
+    for seq_num in 0..4 {
+        executor.execute_and_apply(dd.signed_script_txn(
+            encode_testnet_mint_script(
+                account_config::coin1_tag(),
+                *payment_sender.address(),
+                threshold - 1,
+            ),
+            seq_num,
+        ));
+    }
+
+    // at the limit, a payment without a signature is rejected
+    let output = executor.execute_transaction(peer_to_peer_with_metadata_txn(
+        &payment_sender,
+        &payment_receiver,
+        0,
+        threshold,
+        lcs::to_bytes(&7777u64).unwrap(),
+        vec![],
+    ));
+    assert_eq!(
+        output.status().vm_status().move_abort_code(),
+        Some(BAD_METADATA_SIGNATURE_ERROR_CODE)
+    );
+
+    // with a signature from the payee's compliance key, it goes through
+    let output = executor.execute_and_apply(p2p_with_metadata_and_signature(
+        &payment_sender,
+        &payment_receiver,
+        0,
+        threshold,
+        lcs::to_bytes(&7777u64).unwrap(),
+        &receiver_compliance_private_key,
+    ));
+    assert_eq!(
+        output.status().vm_status().status_code(),
+        StatusCode::EXECUTED
+    );
+
+    // below the limit, no signature is needed
+    let output = executor.execute_and_apply(peer_to_peer_with_metadata_txn(
+        &payment_sender,
+        &payment_receiver,
+        1,
+        threshold - 1,
+        vec![],
+        vec![],
+    ));
+    assert_eq!(
+        output.status().vm_status().status_code(),
+        StatusCode::EXECUTED
+    );
+}
+
 #[test]
 fn dual_attestation_payment() {
     let mut executor = FakeExecutor::from_genesis_file();
@@ -1228,8 +1432,7 @@ fn account_limits() {
 
         // Reset the window
         let prev_block_time = executor.get_block_time();
-        executor.set_block_time(prev_block_time + window_micros);
-        executor.new_block();
+        executor.set_block_time(prev_block_time + window_micros + 1);
 
         // DD deposit now succeeds since window is reset
         let output = executor.execute_transaction(
@@ -1372,8 +1575,7 @@ fn account_limits() {
 
         // update block time
         let prev_block_time = executor.get_block_time();
-        executor.set_block_time(prev_block_time + window_micros);
-        executor.new_block();
+        executor.set_block_time(prev_block_time + window_micros + 1);
 
         let output = executor.execute_transaction(
             vasp_a_child
@@ -1556,8 +1758,7 @@ fn account_limits() {
 
         // Reset window
         let prev_block_time = executor.get_block_time();
-        executor.set_block_time(prev_block_time + window_micros);
-        executor.new_block();
+        executor.set_block_time(prev_block_time + window_micros + 1);
 
         // DD deposit fails since vasp A is at holding limit
         // and because holdings are not reset from one window to the next.
@@ -1744,3 +1945,72 @@ fn add_child_currencies() {
             .sign(),
     );
 }
+
+#[test]
+fn create_child_vasp_with_executor_helpers() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let parent = executor.create_parent_vasp(COIN1_NAME);
+    let other_parent = executor.create_parent_vasp(COIN1_NAME);
+    executor.fund_account(&parent, 1_000_000, COIN1_NAME);
+
+    let child = executor.create_child_vasp(&parent, 100_000, COIN1_NAME, 0);
+    let child_vasp = executor
+        .read_resource::<ChildVASP>(child.address())
+        .expect("the child must have a ChildVASP resource");
+    assert_eq!(child_vasp.parent_vasp_addr(), *parent.address());
+    let parent_vasp = executor
+        .read_resource::<ParentVASP>(parent.address())
+        .expect("the parent must have a ParentVASP resource");
+    assert_eq!(parent_vasp.num_children(), 1);
+    executor.assert_balance(&child, COIN1_NAME, 100_000);
+    executor.assert_balance(&parent, COIN1_NAME, 900_000);
+
+    // With a low dual attestation limit, payments between two VASPs need a signature from the
+    // payee, but payments within a VASP don't.
+    executor.update_resource(
+        &account_config::libra_root_address(),
+        Limit {
+            micro_lbr_limit: 1_000,
+        },
+    );
+    let output = executor.execute_and_apply(peer_to_peer_with_metadata_txn(
+        &child,
+        &parent,
+        0,
+        50_000,
+        vec![],
+        vec![],
+    ));
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::Executed)
+    );
+    executor.assert_balance(&parent, COIN1_NAME, 950_000);
+    let output = executor.execute_transaction(peer_to_peer_with_metadata_txn(
+        &parent,
+        &other_parent,
+        1,
+        50_000,
+        vec![],
+        vec![],
+    ));
+    let dual_attestation = ModuleId::new(
+        account_config::CORE_CODE_ADDRESS,
+        Identifier::new("DualAttestation").unwrap(),
+    );
+    // EMALFORMED_METADATA_SIGNATURE
+    assert_aborts_with(&output, &dual_attestation, 6);
+
+    // An account with the parent VASP role but no ParentVASP resource can't have children.
+    let not_a_vasp = AccountData::new(1_000_000, 0);
+    executor.add_account_data(&not_a_vasp);
+    let output = executor
+        .try_create_child_vasp(not_a_vasp.account(), 0, account_config::LBR_NAME, 0)
+        .expect_err("only parent VASPs can create children");
+    let vasp = ModuleId::new(
+        account_config::CORE_CODE_ADDRESS,
+        Identifier::new("VASP").unwrap(),
+    );
+    // ENOT_A_PARENT_VASP
+    assert_aborts_with(&output, &vasp, 4);
+}
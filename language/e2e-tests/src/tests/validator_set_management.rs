@@ -2,12 +2,13 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    account::Account,
+    account::{Account, AccountData},
     common_transactions::{
         add_validator_txn, create_validator_account_txn, create_validator_operator_account_txn,
-        reconfigure_txn, set_validator_config_txn, set_validator_operator_txn,
+        peer_to_peer_txn, reconfigure_txn, set_validator_config_txn, set_validator_operator_txn,
     },
     executor::FakeExecutor,
+    reconfiguration_event,
 };
 use libra_types::{
     on_chain_config::new_epoch_event_key, transaction::TransactionStatus, vm_status::VMStatus,
@@ -235,3 +236,56 @@ fn validator_set_operator_set_key_reconfigure() {
         .iter()
         .any(|e| e.key() == &new_epoch_event_key()));
 }
+
+#[test]
+fn validator_add_bumps_epoch() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let libra_root_account = Account::new_libra_root();
+    let validator_account = Account::new();
+    let epoch = executor.epoch();
+
+    let txn = create_validator_account_txn(&libra_root_account, &validator_account, 1);
+    let output = executor.execute_and_apply(txn);
+    assert!(reconfiguration_event(&output).is_none());
+    executor.new_block();
+
+    let txn = set_validator_config_txn(
+        &validator_account,
+        &validator_account,
+        [
+            0xd7, 0x5a, 0x98, 0x01, 0x82, 0xb1, 0x0a, 0xb7, 0xd5, 0x4b, 0xfe, 0xd3, 0xc9, 0x64,
+            0x07, 0x3a, 0x0e, 0xe1, 0x72, 0xf3, 0xda, 0xa6, 0x23, 0x25, 0xaf, 0x02, 0x1a, 0x68,
+            0xf7, 0x07, 0x51, 0x1a,
+        ]
+        .to_vec(),
+        vec![254; 32],
+        vec![],
+        vec![253; 32],
+        vec![],
+        0,
+    );
+    executor.execute_and_apply(txn);
+    assert_eq!(executor.epoch(), epoch);
+
+    // adding the validator reconfigures once, into the next epoch
+    let txn = add_validator_txn(&libra_root_account, &validator_account, 2);
+    let output = executor.execute_and_apply(txn);
+    let event = reconfiguration_event(&output).expect("adding a validator must reconfigure");
+    assert_eq!(event.epoch(), epoch + 1);
+    assert_eq!(executor.epoch(), epoch + 1);
+
+    // ordinary transactions don't
+    executor.new_block();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+    let output = executor.execute_and_apply(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        10,
+        1_000,
+    ));
+    assert!(reconfiguration_event(&output).is_none());
+    assert_eq!(executor.epoch(), epoch + 1);
+}
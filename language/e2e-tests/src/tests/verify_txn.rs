@@ -2,15 +2,22 @@
 // SPDX-License-Identifier: Apache-2.0
 
 use crate::{
-    account::{Account, AccountData},
-    assert_prologue_disparity, assert_prologue_parity, assert_status_eq,
-    compile::compile_module_with_address,
+    account::{lbr_currency_code, Account, AccountData, AccountRoleSpecifier},
+    assert_discarded_with, assert_kept_with, assert_prologue_disparity, assert_prologue_parity,
+    assert_status_eq, assert_status_type,
+    common_transactions::{
+        malformed_module_txn, malformed_script_txn, oversized_module_txn, peer_to_peer_txn,
+    },
+    compile::{compile_module_with_address, conditional_abort_script_with_arg},
     executor::FakeExecutor,
+    gas_costs,
+    proptest_types::{arb_gas_params, arb_transaction_payload},
     transaction_status_eq,
 };
 use compiled_stdlib::transaction_scripts::StdlibScript;
 use compiler::Compiler;
 use libra_crypto::{ed25519::Ed25519PrivateKey, PrivateKey, Uniform};
+use libra_logger::Level;
 use libra_types::{
     account_config::{self, lbr_type_tag, LBR_NAME},
     on_chain_config::VMPublishingOption,
@@ -21,8 +28,93 @@ use libra_types::{
     },
     vm_status::{StatusCode, StatusType, VMStatus},
 };
-use move_core_types::gas_schedule::{GasAlgebra, GasConstants};
-use transaction_builder::encode_peer_to_peer_with_metadata_script;
+use move_core_types::{
+    account_address::AccountAddress,
+    gas_schedule::{GasAlgebra, GasConstants, GasPrice},
+};
+use proptest::prelude::*;
+use std::time::Duration;
+use transaction_builder::{
+    encode_create_testing_account_script, encode_peer_to_peer_with_metadata_script,
+};
+
+/// The address of the ordinary account that fuzzed payloads are sent from, which has to be known
+/// before the payloads are generated.
+const FUZZED_SENDER: AccountAddress = AccountAddress::new([0x42; AccountAddress::LENGTH]);
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn verify_gas_params(
+        gas_params in arb_gas_params(FakeExecutor::from_genesis_file().read_gas_constants()),
+    ) {
+        let mut executor = FakeExecutor::from_genesis_file();
+        // Enough to cover the largest possible gas deposit.
+        let sender = AccountData::new(1_000_000_000_000, 10);
+        executor.add_account_data(&sender);
+
+        let txn = sender.account().create_signed_txn_with_args(
+            StdlibScript::PeerToPeerWithMetadata
+                .compiled_bytes()
+                .into_vec(),
+            vec![lbr_type_tag()],
+            vec![
+                TransactionArgument::Address(*sender.address()),
+                TransactionArgument::U64(1),
+                TransactionArgument::U8Vector(vec![]),
+                TransactionArgument::U8Vector(vec![]),
+            ],
+            10,
+            gas_params.max_gas_amount,
+            gas_params.gas_unit_price,
+            LBR_NAME.to_owned(),
+        );
+        prop_assert_eq!(
+            executor.verify_transaction(txn).status(),
+            gas_params.expected_error.map(VMStatus::Error)
+        );
+    }
+
+    #[test]
+    fn arbitrary_payloads_never_violate_invariants(
+        (from_libra_root, payload) in any::<bool>().prop_flat_map(|from_libra_root| {
+            let sender = if from_libra_root {
+                account_config::libra_root_address()
+            } else {
+                FUZZED_SENDER
+            };
+            (Just(from_libra_root), arb_transaction_payload(sender))
+        }),
+    ) {
+        let mut executor = FakeExecutor::from_genesis_file();
+        let sender = if from_libra_root {
+            Account::new_libra_root()
+        } else {
+            let sender = AccountData::with_account(
+                Account::new_genesis_account(FUZZED_SENDER),
+                1_000_000,
+                lbr_currency_code(),
+                0,
+                AccountRoleSpecifier::default(),
+            );
+            executor.add_account_data(&sender);
+            sender.into_account()
+        };
+        let txn = sender
+            .transaction()
+            .payload(payload)
+            .sequence_number(executor.sequence_number(sender.address()).unwrap())
+            .sign();
+
+        // Panicking, including on an error from the VM itself, fails the test as well.
+        let output = executor.execute_transaction(txn);
+        prop_assert_ne!(
+            output.status().vm_status().status_type(),
+            StatusType::InvariantViolation
+        );
+    }
+}
 
 #[test]
 fn verify_signature() {
@@ -53,6 +145,153 @@ fn verify_signature() {
     );
 }
 
+#[test]
+fn verify_signature_with_wrong_key() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(900_000, 10);
+    executor.add_account_data(&sender);
+    let wrong_key = Ed25519PrivateKey::generate_for_testing();
+    let signed_txn = sender.account().create_signed_txn_with_wrong_key(
+        TransactionPayload::Script(encode_peer_to_peer_with_metadata_script(
+            lbr_type_tag(),
+            *sender.address(),
+            100,
+            vec![],
+            vec![],
+        )),
+        10,
+        gas_costs::TXN_RESERVED,
+        0,
+        LBR_NAME.to_owned(),
+        &wrong_key,
+    );
+    assert_eq!(signed_txn.sender(), *sender.address());
+
+    assert_prologue_parity!(
+        executor.verify_transaction(signed_txn.clone()).status(),
+        executor.execute_transaction(signed_txn).status(),
+        VMStatus::Error(StatusCode::INVALID_SIGNATURE)
+    );
+}
+
+#[test]
+fn verify_gas_price_below_min_bound() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+    let libra_root = Account::new_libra_root();
+    let libra_root_seq_num = executor
+        .sequence_number(libra_root.address())
+        .expect("Libra root must exist after genesis");
+
+    // Both transactions are free.
+    let p2p_txn = sender.account().signed_script_txn(
+        encode_peer_to_peer_with_metadata_script(
+            lbr_type_tag(),
+            *receiver.address(),
+            100,
+            vec![],
+            vec![],
+        ),
+        10,
+    );
+    let new_account = Account::new();
+    let create_txn = libra_root.signed_script_txn(
+        encode_create_testing_account_script(
+            lbr_type_tag(),
+            *new_account.address(),
+            new_account.auth_key_prefix(),
+            false,
+        ),
+        libra_root_seq_num,
+    );
+    assert_eq!(p2p_txn.gas_unit_price(), 0);
+    assert_eq!(create_txn.gas_unit_price(), 0);
+
+    // The genesis minimum price is 0, so a free transaction goes through whoever sends it.
+    assert_eq!(
+        executor.read_gas_constants().min_price_per_gas_unit.get(),
+        0
+    );
+    assert_eq!(executor.verify_transaction(p2p_txn.clone()).status(), None);
+    assert_kept_with(
+        &executor.execute_transaction(p2p_txn.clone()),
+        StatusCode::EXECUTED,
+    );
+    assert_eq!(
+        executor.verify_transaction(create_txn.clone()).status(),
+        None
+    );
+    assert_kept_with(
+        &executor.execute_transaction(create_txn.clone()),
+        StatusCode::EXECUTED,
+    );
+
+    // Once the minimum is raised, the VM discards free transactions before running the prologue.
+    // The check only looks at the gas constants, so Libra root gets no exemption.
+    let mut gas_schedule = executor.read_gas_schedule();
+    gas_schedule.gas_constants.min_price_per_gas_unit = GasPrice::new(1);
+    executor.set_gas_schedule(gas_schedule);
+    assert_prologue_parity!(
+        executor.verify_transaction(p2p_txn.clone()).status(),
+        executor.execute_transaction(p2p_txn).status(),
+        VMStatus::Error(StatusCode::GAS_UNIT_PRICE_BELOW_MIN_BOUND)
+    );
+    assert_prologue_parity!(
+        executor.verify_transaction(create_txn.clone()).status(),
+        executor.execute_transaction(create_txn).status(),
+        VMStatus::Error(StatusCode::GAS_UNIT_PRICE_BELOW_MIN_BOUND)
+    );
+}
+
+#[test]
+fn execute_transaction_with_logs() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    let txn = sender.account().create_signed_txn_with_args(
+        StdlibScript::PeerToPeerWithMetadata
+            .compiled_bytes()
+            .into_vec(),
+        vec![lbr_type_tag()],
+        vec![
+            TransactionArgument::Address(*receiver.address()),
+            TransactionArgument::U64(100),
+            TransactionArgument::U8Vector(vec![]),
+            TransactionArgument::U8Vector(vec![]),
+        ],
+        10,
+        1_000_000,
+        GasConstants::default().max_price_per_gas_unit.get() + 1,
+        LBR_NAME.to_owned(),
+    );
+    let (output, logs) = executor.execute_transaction_with_logs(txn);
+    assert_discarded_with(&output, StatusCode::GAS_UNIT_PRICE_ABOVE_MAX_BOUND);
+    assert!(
+        logs.iter()
+            .any(|entry| entry.level == Level::Warn && entry.message.contains("Gas unit error")),
+        "the VM should have warned about the gas price, got {:?}",
+        logs,
+    );
+
+    // Only what is logged while executing a transaction is returned with it.
+    let (output, logs) = executor.execute_transaction_with_logs(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        10,
+        100,
+    ));
+    assert_kept_with(&output, StatusCode::EXECUTED);
+    assert!(!logs
+        .iter()
+        .any(|entry| entry.message.contains("Gas unit error")));
+}
+
 #[test]
 fn verify_reserved_sender() {
     let mut executor = FakeExecutor::from_genesis_file();
@@ -143,6 +382,10 @@ fn verify_simple_payment() {
         1,
         LBR_NAME.to_owned(),
     );
+    assert_status_type(
+        &executor.verify_transaction(txn.clone()).status().unwrap(),
+        StatusType::Validation,
+    );
     assert_prologue_parity!(
         executor.verify_transaction(txn.clone()).status(),
         executor.execute_transaction(txn).status(),
@@ -229,21 +472,8 @@ fn verify_simple_payment() {
         VMStatus::Error(StatusCode::GAS_UNIT_PRICE_ABOVE_MAX_BOUND)
     );
 
-    // Note: We can't test this at the moment since MIN_PRICE_PER_GAS_UNIT is set to 0 for
-    // testnet. Uncomment this test once we have a non-zero MIN_PRICE_PER_GAS_UNIT.
-    // let txn = sender.account().create_signed_txn_with_args(
-    //     p2p_script.clone(),
-    //     args.clone(),
-    //     10,
-    //     1_000_000,
-    //     gas_schedule::MIN_PRICE_PER_GAS_UNIT - 1,
-    // );
-    // assert_eq!(
-    //     executor.verify_transaction(txn).status(),
-    //     Some(VMStatus::Error(
-    //         StatusCode::GAS_UNIT_PRICE_BELOW_MIN_BOUND
-    //     ))
-    // );
+    // GAS_UNIT_PRICE_BELOW_MIN_BOUND can't happen with the genesis gas schedule, whose minimum
+    // price is 0; it is tested in `verify_gas_price_below_min_bound`.
 
     let txn = sender.account().create_signed_txn_with_args(
         p2p_script.clone(),
@@ -341,6 +571,35 @@ fn verify_simple_payment() {
     );
 }
 
+#[test]
+fn verify_max_transaction_size_boundary() {
+    let mut executor = FakeExecutor::from_genesis_with_options(VMPublishingOption::open());
+    let sender = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&sender);
+    let max_size = executor.read_gas_constants().max_transaction_size_in_bytes as usize;
+
+    // one byte over the limit is rejected before anything runs
+    let txn = oversized_module_txn(sender.account(), 10, max_size + 1);
+    assert_eq!(txn.raw_txn_bytes_len(), max_size + 1);
+    assert_prologue_parity!(
+        executor.verify_transaction(txn.clone()).status(),
+        executor.execute_transaction(txn).status(),
+        VMStatus::Error(StatusCode::EXCEEDED_MAX_TRANSACTION_SIZE)
+    );
+
+    // the limit is inclusive: a transaction of exactly that size is accepted, as is a smaller one
+    let txn = oversized_module_txn(sender.account(), 10, max_size - 1);
+    assert_eq!(txn.raw_txn_bytes_len(), max_size - 1);
+    assert_eq!(executor.verify_transaction(txn).status(), None);
+    let txn = oversized_module_txn(sender.account(), 10, max_size);
+    assert_eq!(txn.raw_txn_bytes_len(), max_size);
+    assert_eq!(executor.verify_transaction(txn.clone()).status(), None);
+    assert_eq!(
+        executor.execute_transaction(txn).status(),
+        &TransactionStatus::Keep(VMStatus::Executed)
+    );
+}
+
 #[test]
 pub fn test_whitelist() {
     // create a FakeExecutor with a genesis from file
@@ -400,6 +659,30 @@ pub fn test_arbitrary_script_execution() {
     );
 }
 
+#[test]
+pub fn test_malformed_payload() {
+    let mut executor = FakeExecutor::from_genesis_with_options(VMPublishingOption::open());
+    let sender = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&sender);
+
+    // Malformed code passes the prologue and fails when it is deserialized, so the transaction is
+    // kept to charge gas rather than discarded.
+    let txn = malformed_script_txn(sender.account(), 10);
+    assert_eq!(executor.verify_transaction(txn.clone()).status(), None);
+    let status = executor.execute_transaction(txn).status().clone();
+    assert!(!status.is_discarded());
+    assert_eq!(
+        status.vm_status().status_code(),
+        StatusCode::CODE_DESERIALIZATION_ERROR
+    );
+
+    let txn = malformed_module_txn(sender.account(), 10);
+    assert_eq!(executor.verify_transaction(txn.clone()).status(), None);
+    let status = executor.execute_transaction(txn).status().clone();
+    assert!(!status.is_discarded());
+    assert_status_type(&status.vm_status(), StatusType::Deserialization);
+}
+
 #[test]
 pub fn test_publish_from_libra_root() {
     // create a FakeExecutor with a genesis from file
@@ -634,11 +917,163 @@ fn test_dependency_fails_verification() {
     );
     // As of now, we don't verify dependencies in verify_transaction.
     assert_eq!(executor.verify_transaction(txn.clone()).status(), None);
-    match executor.execute_transaction(txn).status() {
-        TransactionStatus::Keep(status) => {
-            assert!(status.status_type() == StatusType::Verification);
-            assert!(status.status_code() == StatusCode::INVALID_RESOURCE_FIELD);
-        }
-        _ => panic!("Failed to find missing dependency in bytecode verifier"),
-    }
+    let output = executor.execute_transaction(txn);
+    assert_kept_with(&output, StatusCode::INVALID_RESOURCE_FIELD);
+    assert_status_type(&output.status().vm_status(), StatusType::Verification);
+}
+
+#[test]
+fn assert_discarded_and_kept_with_status_codes() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    let output = executor.execute_transaction(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        10,
+        1_000,
+    ));
+    assert_kept_with(&output, StatusCode::EXECUTED);
+
+    let output = executor.execute_transaction(peer_to_peer_txn(
+        sender.account(),
+        receiver.account(),
+        9,
+        1_000,
+    ));
+    assert_discarded_with(&output, StatusCode::SEQUENCE_NUMBER_TOO_OLD);
+}
+
+#[test]
+fn verify_block_tracks_sequence_numbers() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    let txn = |seq_num| peer_to_peer_txn(sender.account(), receiver.account(), seq_num, 1_000);
+    let statuses = executor.verify_block(vec![
+        txn(10),
+        txn(11),
+        peer_to_peer_txn(receiver.account(), sender.account(), 10, 1_000),
+        txn(11),
+        txn(13),
+        txn(12),
+        txn(9),
+    ]);
+    assert_eq!(
+        statuses,
+        vec![
+            None,
+            None,
+            None,
+            Some(VMStatus::Error(StatusCode::SEQUENCE_NUMBER_TOO_OLD)),
+            Some(VMStatus::Error(StatusCode::SEQUENCE_NUMBER_TOO_NEW)),
+            None,
+            Some(VMStatus::Error(StatusCode::SEQUENCE_NUMBER_TOO_OLD)),
+        ]
+    );
+}
+
+#[test]
+fn sequence_number_after_kept_and_discarded() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    executor.add_account_data(&sender);
+    assert_eq!(executor.sequence_number(sender.address()), Some(10));
+    assert_eq!(executor.sequence_number(Account::new().address()), None);
+
+    // a transaction that aborts is kept and still bumps the sequence number
+    let output = executor.execute_transaction(
+        sender
+            .account()
+            .signed_script_txn(conditional_abort_script_with_arg(true), 10),
+    );
+    assert_kept_with(&output, StatusCode::ABORTED);
+    executor.apply_write_set(output.write_set());
+    assert_eq!(executor.sequence_number(sender.address()), Some(11));
+
+    // a discarded transaction doesn't
+    let output = executor.execute_transaction(
+        sender
+            .account()
+            .signed_script_txn(conditional_abort_script_with_arg(false), 10),
+    );
+    assert_discarded_with(&output, StatusCode::SEQUENCE_NUMBER_TOO_OLD);
+    executor.apply_write_set(output.write_set());
+    assert_eq!(executor.sequence_number(sender.address()), Some(11));
+}
+
+#[test]
+fn transaction_expiration_boundary() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    // the on-chain time is now exactly 100 seconds
+    executor.set_block_time(100_000_000);
+
+    let txn = |expiration_secs| {
+        sender.account().create_signed_txn_with_expiration(
+            TransactionPayload::Script(encode_peer_to_peer_with_metadata_script(
+                lbr_type_tag(),
+                *receiver.address(),
+                1_000,
+                vec![],
+                vec![],
+            )),
+            10,
+            gas_costs::TXN_RESERVED,
+            0,
+            LBR_NAME.to_owned(),
+            expiration_secs,
+        )
+    };
+
+    let output = executor.execute_transaction(txn(99));
+    assert_discarded_with(&output, StatusCode::TRANSACTION_EXPIRED);
+    // a transaction expiring right now is expired too
+    let output = executor.execute_transaction(txn(100));
+    assert_discarded_with(&output, StatusCode::TRANSACTION_EXPIRED);
+    let output = executor.execute_transaction(txn(101));
+    assert_kept_with(&output, StatusCode::EXECUTED);
+}
+
+#[test]
+fn transaction_expires_as_time_advances() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+    executor.set_block_time(100_000_000);
+
+    let txn = sender.account().create_signed_txn_with_expiration(
+        TransactionPayload::Script(encode_peer_to_peer_with_metadata_script(
+            lbr_type_tag(),
+            *receiver.address(),
+            1_000,
+            vec![],
+            vec![],
+        )),
+        10,
+        gas_costs::TXN_RESERVED,
+        0,
+        LBR_NAME.to_owned(),
+        160,
+    );
+
+    executor.advance_time(Duration::from_secs(59));
+    let output = executor.execute_transaction(txn.clone());
+    assert_kept_with(&output, StatusCode::EXECUTED);
+
+    executor.advance_time(Duration::from_secs(1));
+    let output = executor.execute_transaction(txn);
+    assert_discarded_with(&output, StatusCode::TRANSACTION_EXPIRED);
 }
@@ -3,24 +3,26 @@
 
 use crate::{
     account::{self, Account, AccountData},
-    common_transactions::rotate_key_txn,
+    assert_prologue_parity,
+    common_transactions::{peer_to_peer_txn, rotate_key_txn, write_set_txn},
     executor::FakeExecutor,
+    transaction_status_eq,
 };
 use libra_crypto::{ed25519::Ed25519PrivateKey, PrivateKey, Uniform};
 use libra_types::{
     access_path::AccessPath,
-    account_config::{lbr_type_tag, CORE_CODE_ADDRESS, LBR_NAME},
+    account_config::{lbr_type_tag, AccountResource, CORE_CODE_ADDRESS, LBR_NAME},
     contract_event::ContractEvent,
     on_chain_config::new_epoch_event_key,
     transaction::{
         authenticator::AuthenticationKey, ChangeSet, TransactionPayload, TransactionStatus,
     },
-    vm_status::{StatusCode, VMStatus},
+    vm_status::{AbortLocation, StatusCode, VMStatus},
     write_set::{WriteOp, WriteSetMut},
 };
 use move_core_types::{
     identifier::Identifier,
-    language_storage::{ResourceKey, StructTag},
+    language_storage::{ModuleId, ResourceKey, StructTag},
 };
 
 #[test]
@@ -304,3 +306,60 @@ fn transfer_and_execute_writeset() {
 
     executor.apply_write_set(output.write_set());
 }
+
+#[test]
+fn write_set_txn_only_from_libra_root() {
+    let mut executor = FakeExecutor::from_genesis_file();
+    let libra_root = Account::new_libra_root();
+    executor.new_block();
+
+    let sender = AccountData::new(1_000_000, 10);
+    let receiver = AccountData::new(100_000, 10);
+    executor.add_account_data(&sender);
+    executor.add_account_data(&receiver);
+
+    // The write set creates an account that no script has ever touched.
+    let new_account_data = AccountData::new(10_000, 0);
+    let write_set = new_account_data.to_writeset();
+
+    // An ordinary account can't send a write set, even one changing nothing but new state.
+    let txn = write_set_txn(sender.account(), write_set.clone(), 10);
+    let write_set_manager = ModuleId::new(
+        CORE_CODE_ADDRESS,
+        Identifier::new("LibraWriteSetManager").unwrap(),
+    );
+    // EINVALID_WRITESET_SENDER in LibraWriteSetManager
+    assert_prologue_parity!(
+        executor.verify_transaction(txn.clone()).status(),
+        executor.execute_transaction(txn).status(),
+        VMStatus::MoveAbort(AbortLocation::Module(write_set_manager), 33)
+    );
+    executor.assert_no_resource::<AccountResource>(new_account_data.address());
+
+    let seq_num = executor
+        .sequence_number(libra_root.address())
+        .expect("Libra root must exist after genesis");
+    let txn = write_set_txn(&libra_root, write_set, seq_num);
+    assert!(executor.verify_transaction(txn.clone()).status().is_none());
+    let output = executor.execute_and_apply(txn);
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::Executed)
+    );
+
+    // Scripts see the account and its balance as if it had been created by one.
+    let output = executor.execute_and_apply(peer_to_peer_txn(
+        new_account_data.account(),
+        receiver.account(),
+        0,
+        1_000,
+    ));
+    assert_eq!(
+        output.status(),
+        &TransactionStatus::Keep(VMStatus::Executed)
+    );
+    let receiver_balance = executor
+        .read_balance_resource(receiver.account(), account::lbr_currency_code())
+        .expect("receiver balance must exist");
+    assert_eq!(101_000, receiver_balance.coin());
+}
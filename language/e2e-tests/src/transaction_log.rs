@@ -0,0 +1,82 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! A log of what was run against a [`FakeExecutor`][crate::executor::FakeExecutor], to replay it
+//! later as a regression test.
+
+use libra_types::{
+    access_path::AccessPath,
+    transaction::{SignedTransaction, TransactionStatus},
+    write_set::WriteSet,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// The version of the format written by [`TransactionLog::save`]. Bump it whenever that format
+/// changes, so that older logs fail to load.
+pub const TRANSACTION_LOG_VERSION: u32 = 1;
+
+/// Something that happened to an executor while it was recording.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum LogEntry {
+    /// A block of transactions was executed, with the given statuses.
+    Block(Vec<SignedTransaction>, Vec<TransactionStatus>),
+    /// A write set was applied to the state, either from a transaction output or to set up
+    /// accounts and modules directly.
+    WriteSet(WriteSet),
+}
+
+/// The transactions executed and the write sets applied by an executor since it started recording,
+/// along with the state it started from.
+///
+/// Started by [`FakeExecutor::start_recording`][crate::executor::FakeExecutor::start_recording]
+/// and replayed by [`FakeExecutor::replay`][crate::executor::FakeExecutor::replay].
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TransactionLog {
+    pub initial_block_time: u64,
+    pub initial_state: BTreeMap<AccessPath, Vec<u8>>,
+    pub entries: Vec<LogEntry>,
+}
+
+impl TransactionLog {
+    pub fn new(initial_block_time: u64, initial_state: BTreeMap<AccessPath, Vec<u8>>) -> Self {
+        Self {
+            initial_block_time,
+            initial_state,
+            entries: vec![],
+        }
+    }
+
+    /// Reads a log written by [`TransactionLog::save`]. Panics if the file can't be read or was
+    /// written in another version of the format.
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        Self::try_load(path)
+            .unwrap_or_else(|err| panic!("unable to load transaction log {:?}: {:#}", path, err))
+    }
+
+    /// Reads a log written by [`TransactionLog::save`], returning an error if the file can't be
+    /// read or was written in another version of the format.
+    pub fn try_load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let bytes = fs::read(path)?;
+        // The version is encoded on its own so it can be checked before decoding the rest.
+        let (version, log): (u32, Vec<u8>) = lcs::from_bytes(&bytes)?;
+        anyhow::ensure!(
+            version == TRANSACTION_LOG_VERSION,
+            "transaction log has version {}, expected {}",
+            version,
+            TRANSACTION_LOG_VERSION
+        );
+        Ok(lcs::from_bytes(&log)?)
+    }
+
+    /// Writes this log to `path`.
+    pub fn save(&self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        let log = lcs::to_bytes(self).expect("transaction log must serialize");
+        let bytes = lcs::to_bytes(&(TRANSACTION_LOG_VERSION, log))
+            .expect("transaction log file must serialize");
+        fs::write(path, bytes)
+            .unwrap_or_else(|err| panic!("unable to write transaction log {:?}: {}", path, err));
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Captures what the VM logs while executing, to surface it in tests.
+
+use libra_logger::log::{self, Level, LevelFilter, Log, Metadata, Record};
+use once_cell::sync::Lazy;
+use std::{cell::RefCell, sync::Mutex};
+
+/// A message logged through `libra_logger`, e.g. by the VM with `warn!` or `crit!`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VMLogEntry {
+    /// The level the message was logged at.
+    pub level: Level,
+    /// The message itself.
+    pub message: String,
+    /// The Rust module the message was logged from.
+    pub module: Option<String>,
+    /// The file and line the message was logged from.
+    pub location: Option<String>,
+}
+
+thread_local! {
+    /// The messages logged on this thread since capturing started, if it did.
+    static CAPTURED: RefCell<Option<Vec<VMLogEntry>>> = RefCell::new(None);
+}
+
+/// Keeps the messages logged on the threads that are capturing, and ignores any other.
+struct CapturingLogger;
+
+impl Log for CapturingLogger {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        CAPTURED.with(|captured| captured.borrow().is_some())
+    }
+
+    fn log(&self, record: &Record) {
+        CAPTURED.with(|captured| {
+            if let Some(entries) = captured.borrow_mut().as_mut() {
+                entries.push(VMLogEntry {
+                    level: record.level(),
+                    message: record.args().to_string(),
+                    module: record.module_path().map(String::from),
+                    location: record
+                        .file()
+                        .map(|file| format!("{}:{}", file, record.line().unwrap_or_default())),
+                });
+            }
+        })
+    }
+
+    fn flush(&self) {}
+}
+
+static CAPTURING_LOGGER: CapturingLogger = CapturingLogger;
+
+/// Whether `CAPTURING_LOGGER` is the `log` logger. There can only be one per process, so it is
+/// installed the first time logs are captured, and stays inert outside of captures.
+static INSTALLED: Lazy<bool> = Lazy::new(|| log::set_logger(&CAPTURING_LOGGER).is_ok());
+
+/// The number of captures running, and the maximum log level to restore once none are.
+static CAPTURES: Lazy<Mutex<(usize, LevelFilter)>> =
+    Lazy::new(|| Mutex::new((0, LevelFilter::Off)));
+
+/// Lets messages of every level through while it lives, and stops capturing on this thread once
+/// dropped, even if the captured code panicked.
+struct CaptureGuard;
+
+impl CaptureGuard {
+    fn start() -> Self {
+        let mut captures = CAPTURES.lock().expect("capture count poisoned");
+        if captures.0 == 0 {
+            captures.1 = log::max_level();
+            log::set_max_level(LevelFilter::Trace);
+        }
+        captures.0 += 1;
+        CAPTURED.with(|captured| *captured.borrow_mut() = Some(vec![]));
+        CaptureGuard
+    }
+
+    fn entries(&self) -> Vec<VMLogEntry> {
+        CAPTURED
+            .with(|captured| captured.borrow_mut().take())
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for CaptureGuard {
+    fn drop(&mut self) {
+        CAPTURED.with(|captured| *captured.borrow_mut() = None);
+        let mut captures = CAPTURES.lock().expect("capture count poisoned");
+        captures.0 -= 1;
+        if captures.0 == 0 {
+            log::set_max_level(captures.1);
+        }
+    }
+}
+
+/// Runs `f` and returns what it logged on this thread along with its result. Messages logged by
+/// other threads, e.g. other tests running concurrently, aren't included.
+///
+/// Messages are read from the `log` facade, which `libra_logger`'s macros always write to, so
+/// structured logging stays as configured. Outside of captures, the maximum log level is left as
+/// it was. Changing it during a capture, as `libra_logger::Logger::init` does, can drop messages.
+///
+/// Panics if another `log` logger has been installed in this process, e.g. by
+/// `libra_logger::Logger::init` with `RUST_LOG` set.
+pub fn capture_logs<T>(f: impl FnOnce() -> T) -> (T, Vec<VMLogEntry>) {
+    assert!(
+        *INSTALLED,
+        "another logger is installed, so logs can't be captured"
+    );
+    let guard = CaptureGuard::start();
+    let result = f();
+    let entries = guard.entries();
+    (result, entries)
+}
@@ -0,0 +1,426 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Support for describing what a write set changes in a data store.
+
+use crate::data_store::FakeDataStore;
+use libra_state_view::StateView;
+use libra_types::{
+    access_path::AccessPath,
+    account_address::AccountAddress,
+    account_config::AccountResource,
+    write_set::{WriteOp, WriteSet, WriteSetMut},
+};
+use move_core_types::{
+    identifier::IdentStr,
+    language_storage::{ModuleId, StructTag, TypeTag, CODE_TAG, RESOURCE_TAG},
+    move_resource::MoveResource,
+    value::{MoveStruct, MoveStructLayout, MoveTypeLayout, MoveValue},
+};
+use std::{collections::HashMap, fmt};
+use vm::{
+    access::ModuleAccess,
+    file_format::{SignatureToken, StructFieldInformation, StructHandleIndex},
+    CompiledModule,
+};
+
+/// What is stored at an access path, as far as it can be told from the path and the data.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum StateKey {
+    /// A published module.
+    Module(ModuleId),
+    /// A resource of a type declared by a module in the data store. Generic resources are only
+    /// recognized when instantiated with a single non-generic struct, like `Balance<Coin1>`.
+    Resource(StructTag),
+    /// A path that couldn't be decoded.
+    Unknown,
+}
+
+impl fmt::Display for StateKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StateKey::Module(module_id) => write!(f, "module {}", module_id),
+            StateKey::Resource(struct_tag) => write!(f, "resource {}", struct_tag),
+            StateKey::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// How a write set changes the data at an access path.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Change {
+    Created { new: Vec<u8> },
+    Modified { old: Vec<u8>, new: Vec<u8> },
+    Deleted { old: Vec<u8> },
+}
+
+/// The change a write set makes at one access path.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DiffEntry {
+    pub access_path: AccessPath,
+    pub key: StateKey,
+    pub change: Change,
+    /// The resource before the change, if there was one and its layout is known.
+    pub old_value: Option<MoveStruct>,
+    /// The resource after the change, if there is one and its layout is known.
+    pub new_value: Option<MoveStruct>,
+}
+
+/// The changes a write set makes to a data store, in write set order.
+///
+/// Writes that leave the data as it was, and deletions of data that doesn't exist, aren't changes
+/// and are left out. The [`Display`](fmt::Display) implementation prints one line per change,
+/// with the values of the resources involved where they could be decoded.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct WriteSetDiff {
+    pub entries: Vec<DiffEntry>,
+}
+
+impl WriteSetDiff {
+    /// Returns the resources changed by the write set, with the addresses they're stored under.
+    pub fn changed_resources(&self) -> Vec<(AccountAddress, StructTag)> {
+        self.entries
+            .iter()
+            .filter_map(|entry| match &entry.key {
+                StateKey::Resource(struct_tag) => {
+                    Some((entry.access_path.address, struct_tag.clone()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the addresses of the accounts created by the write set, i.e. where a `LibraAccount`
+    /// resource is published that didn't exist before, in write set order.
+    pub fn created_accounts(&self) -> Vec<AccountAddress> {
+        let account_path = AccessPath::resource_access_vec(&AccountResource::struct_tag());
+        self.entries
+            .iter()
+            .filter(|entry| entry.access_path.path == account_path)
+            .filter(|entry| matches!(entry.change, Change::Created { .. }))
+            .map(|entry| entry.access_path.address)
+            .collect()
+    }
+}
+
+impl fmt::Display for WriteSetDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in &self.entries {
+            let key = match &entry.key {
+                StateKey::Unknown => entry.access_path.to_string(),
+                key => key.to_string(),
+            };
+            let old = entry.old_value.as_ref().map(format_struct);
+            let new = entry.new_value.as_ref().map(format_struct);
+            match (&entry.change, old, new) {
+                (Change::Created { .. }, _, Some(new)) => {
+                    writeln!(f, "+ {} at {}: {}", key, entry.access_path.address, new)?
+                }
+                (Change::Created { new }, _, None) => writeln!(
+                    f,
+                    "+ {} at {}: {} bytes",
+                    key,
+                    entry.access_path.address,
+                    new.len()
+                )?,
+                (Change::Modified { .. }, Some(old), Some(new)) => writeln!(
+                    f,
+                    "~ {} at {}: {} -> {}",
+                    key, entry.access_path.address, old, new
+                )?,
+                (Change::Modified { old, new }, _, _) => writeln!(
+                    f,
+                    "~ {} at {}: {} -> {} bytes",
+                    key,
+                    entry.access_path.address,
+                    old.len(),
+                    new.len()
+                )?,
+                (Change::Deleted { .. }, Some(old), _) => {
+                    writeln!(f, "- {} at {}: {}", key, entry.access_path.address, old)?
+                }
+                (Change::Deleted { old }, None, _) => writeln!(
+                    f,
+                    "- {} at {}: {} bytes",
+                    key,
+                    entry.access_path.address,
+                    old.len()
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Describes the changes `write_set` makes when applied on top of `before`.
+pub fn diff_write_sets(before: &FakeDataStore, write_set: &WriteSet) -> WriteSetDiff {
+    let modules = known_modules(before, write_set);
+    let resource_tags = known_resource_tags(&modules);
+    let decoder = ResourceDecoder::new(modules);
+    let entries = write_set
+        .iter()
+        .filter_map(|(access_path, write_op)| {
+            let old = StateView::get(before, access_path).expect("data store reads can't fail");
+            let change = match (old, write_op) {
+                (None, WriteOp::Value(new)) => Change::Created { new: new.clone() },
+                (Some(old), WriteOp::Value(new)) if &old != new => Change::Modified {
+                    old,
+                    new: new.clone(),
+                },
+                (Some(old), WriteOp::Deletion) => Change::Deleted { old },
+                _ => return None,
+            };
+            let key = match access_path.path.first() {
+                Some(&CODE_TAG) => module_key(&change),
+                Some(&RESOURCE_TAG) => resource_tags
+                    .get(&access_path.path)
+                    .cloned()
+                    .map_or(StateKey::Unknown, StateKey::Resource),
+                _ => StateKey::Unknown,
+            };
+            let (old_value, new_value) = match &key {
+                StateKey::Resource(struct_tag) => {
+                    let decode = |blob: &Vec<u8>| decoder.decode(struct_tag, blob);
+                    match &change {
+                        Change::Created { new } => (None, decode(new)),
+                        Change::Modified { old, new } => (decode(old), decode(new)),
+                        Change::Deleted { old } => (decode(old), None),
+                    }
+                }
+                _ => (None, None),
+            };
+            Some(DiffEntry {
+                access_path: access_path.clone(),
+                key,
+                change,
+                old_value,
+                new_value,
+            })
+        })
+        .collect();
+    WriteSetDiff { entries }
+}
+
+/// Describes the changes that turn the data in `before` into the data in `after`.
+pub fn diff_data_stores(before: &FakeDataStore, after: &FakeDataStore) -> WriteSetDiff {
+    diff_write_sets(before, &write_set_between(before, after))
+}
+
+/// Returns the write set that turns the data in `from` into the data in `to`.
+pub fn write_set_between(from: &FakeDataStore, to: &FakeDataStore) -> WriteSet {
+    let ours = from.to_sorted_map();
+    let theirs = to.to_sorted_map();
+    let mut writes: Vec<_> = theirs
+        .iter()
+        .filter(|(access_path, blob)| ours.get(*access_path) != Some(*blob))
+        .map(|(access_path, blob)| (access_path.clone(), WriteOp::Value(blob.clone())))
+        .collect();
+    writes.extend(
+        ours.keys()
+            .filter(|access_path| !theirs.contains_key(*access_path))
+            .map(|access_path| (access_path.clone(), WriteOp::Deletion)),
+    );
+    WriteSetMut::new(writes)
+        .freeze()
+        .expect("the differences must make a valid write set")
+}
+
+/// Modules are identified by deserializing them, since paths only hold a hash of the module id.
+fn module_key(change: &Change) -> StateKey {
+    let blob = match change {
+        Change::Created { new } | Change::Modified { new, .. } => new,
+        Change::Deleted { old } => old,
+    };
+    CompiledModule::deserialize(blob)
+        .map(|module| StateKey::Module(module.self_id()))
+        .unwrap_or(StateKey::Unknown)
+}
+
+/// Returns the modules in `before` along with the ones published by `write_set`.
+fn known_modules(before: &FakeDataStore, write_set: &WriteSet) -> Vec<CompiledModule> {
+    let mut modules = before.modules();
+    modules.extend(write_set.iter().filter_map(|(access_path, write_op)| {
+        match (access_path.path.first(), write_op) {
+            (Some(&CODE_TAG), WriteOp::Value(blob)) => CompiledModule::deserialize(blob).ok(),
+            _ => None,
+        }
+    }));
+    modules
+}
+
+/// Maps the paths of the resource types declared by `modules` to their struct tags.
+///
+/// Resource paths only hold a hash of the struct tag, so this tries every non-generic struct, and
+/// every struct with one type parameter instantiated with each of those.
+fn known_resource_tags(modules: &[CompiledModule]) -> HashMap<Vec<u8>, StructTag> {
+    let mut plain = vec![];
+    let mut generic = vec![];
+    for module in modules {
+        let module_id = module.self_id();
+        for struct_def in module.struct_defs() {
+            let handle = module.struct_handle_at(struct_def.struct_handle);
+            let struct_tag = StructTag {
+                address: *module_id.address(),
+                module: module_id.name().to_owned(),
+                name: module.identifier_at(handle.name).to_owned(),
+                type_params: vec![],
+            };
+            match handle.type_parameters.len() {
+                0 => plain.push(struct_tag),
+                1 => generic.push(struct_tag),
+                _ => (),
+            }
+        }
+    }
+
+    let mut tags: Vec<StructTag> = generic
+        .iter()
+        .flat_map(|generic_tag| {
+            plain.iter().map(move |param| StructTag {
+                type_params: vec![TypeTag::Struct(param.clone())],
+                ..generic_tag.clone()
+            })
+        })
+        .collect();
+    tags.extend(plain);
+    tags.into_iter()
+        .map(|struct_tag| (struct_tag.access_vector(), struct_tag))
+        .collect()
+}
+
+/// Decodes resources using the layouts of the structs declared by a set of modules.
+struct ResourceDecoder {
+    modules: HashMap<ModuleId, CompiledModule>,
+}
+
+impl ResourceDecoder {
+    fn new(modules: Vec<CompiledModule>) -> Self {
+        Self {
+            modules: modules
+                .into_iter()
+                .map(|module| (module.self_id(), module))
+                .collect(),
+        }
+    }
+
+    /// Returns the value of a resource of type `struct_tag`, or `None` if its layout isn't known
+    /// or the blob doesn't match it.
+    fn decode(&self, struct_tag: &StructTag, blob: &[u8]) -> Option<MoveStruct> {
+        let layout = self.tag_layout(&TypeTag::Struct(struct_tag.clone()))?;
+        match layout {
+            MoveTypeLayout::Struct(layout) => MoveStruct::simple_deserialize(blob, &layout).ok(),
+            _ => None,
+        }
+    }
+
+    fn tag_layout(&self, type_tag: &TypeTag) -> Option<MoveTypeLayout> {
+        Some(match type_tag {
+            TypeTag::Bool => MoveTypeLayout::Bool,
+            TypeTag::U8 => MoveTypeLayout::U8,
+            TypeTag::U64 => MoveTypeLayout::U64,
+            TypeTag::U128 => MoveTypeLayout::U128,
+            TypeTag::Address => MoveTypeLayout::Address,
+            TypeTag::Signer => MoveTypeLayout::Signer,
+            TypeTag::Vector(inner) => MoveTypeLayout::Vector(Box::new(self.tag_layout(inner)?)),
+            TypeTag::Struct(struct_tag) => {
+                let type_args = struct_tag
+                    .type_params
+                    .iter()
+                    .map(|type_param| self.tag_layout(type_param))
+                    .collect::<Option<Vec<_>>>()?;
+                let module_id = ModuleId::new(struct_tag.address, struct_tag.module.clone());
+                MoveTypeLayout::Struct(self.struct_layout(
+                    &module_id,
+                    &struct_tag.name,
+                    &type_args,
+                )?)
+            }
+        })
+    }
+
+    fn struct_layout(
+        &self,
+        module_id: &ModuleId,
+        name: &IdentStr,
+        type_args: &[MoveTypeLayout],
+    ) -> Option<MoveStructLayout> {
+        let module = self.modules.get(module_id)?;
+        let struct_def = module.struct_defs().iter().find(|struct_def| {
+            module.identifier_at(module.struct_handle_at(struct_def.struct_handle).name) == name
+        })?;
+        let fields = match &struct_def.field_information {
+            StructFieldInformation::Declared(fields) => fields,
+            StructFieldInformation::Native => return None,
+        };
+        fields
+            .iter()
+            .map(|field| self.token_layout(module, &field.signature.0, type_args))
+            .collect::<Option<Vec<_>>>()
+            .map(MoveStructLayout::new)
+    }
+
+    fn token_layout(
+        &self,
+        module: &CompiledModule,
+        token: &SignatureToken,
+        type_args: &[MoveTypeLayout],
+    ) -> Option<MoveTypeLayout> {
+        Some(match token {
+            SignatureToken::Bool => MoveTypeLayout::Bool,
+            SignatureToken::U8 => MoveTypeLayout::U8,
+            SignatureToken::U64 => MoveTypeLayout::U64,
+            SignatureToken::U128 => MoveTypeLayout::U128,
+            SignatureToken::Address => MoveTypeLayout::Address,
+            SignatureToken::Signer => MoveTypeLayout::Signer,
+            SignatureToken::Vector(inner) => {
+                MoveTypeLayout::Vector(Box::new(self.token_layout(module, inner, type_args)?))
+            }
+            SignatureToken::Struct(handle_idx) => {
+                MoveTypeLayout::Struct(self.handle_layout(module, *handle_idx, &[])?)
+            }
+            SignatureToken::StructInstantiation(handle_idx, tokens) => {
+                let args = tokens
+                    .iter()
+                    .map(|token| self.token_layout(module, token, type_args))
+                    .collect::<Option<Vec<_>>>()?;
+                MoveTypeLayout::Struct(self.handle_layout(module, *handle_idx, &args)?)
+            }
+            SignatureToken::TypeParameter(idx) => type_args.get(*idx as usize)?.clone(),
+            // Fields can't hold references.
+            SignatureToken::Reference(_) | SignatureToken::MutableReference(_) => return None,
+        })
+    }
+
+    fn handle_layout(
+        &self,
+        module: &CompiledModule,
+        handle_idx: StructHandleIndex,
+        type_args: &[MoveTypeLayout],
+    ) -> Option<MoveStructLayout> {
+        let handle = module.struct_handle_at(handle_idx);
+        let module_id = module.module_id_for_handle(module.module_handle_at(handle.module));
+        self.struct_layout(&module_id, module.identifier_at(handle.name), type_args)
+    }
+}
+
+/// Formats a struct as its fields between braces, e.g. `{1000, true}`.
+fn format_struct(value: &MoveStruct) -> String {
+    let fields: Vec<_> = value.fields().iter().map(format_value).collect();
+    format!("{{{}}}", fields.join(", "))
+}
+
+fn format_value(value: &MoveValue) -> String {
+    match value {
+        MoveValue::U8(n) => format!("{}u8", n),
+        MoveValue::U64(n) => n.to_string(),
+        MoveValue::U128(n) => format!("{}u128", n),
+        MoveValue::Bool(b) => b.to_string(),
+        MoveValue::Address(address) => address.to_string(),
+        MoveValue::Signer(address) => format!("signer({})", address),
+        MoveValue::Vector(values) => {
+            let elements: Vec<_> = values.iter().map(format_value).collect();
+            format!("[{}]", elements.join(", "))
+        }
+        MoveValue::Struct(value) => format_struct(value),
+    }
+}
@@ -116,6 +116,7 @@ pub mod transaction_metadata;
 pub mod libra_transaction_executor;
 pub mod libra_transaction_validator;
 pub mod system_module_names;
+pub mod transaction_phases;
 
 pub use crate::{
     libra_transaction_executor::LibraVM, libra_transaction_validator::LibraVMValidator,
@@ -10,6 +10,7 @@ use crate::{
     },
     system_module_names::*,
     transaction_metadata::TransactionMetadata,
+    transaction_phases::{run_phase, TransactionPhase},
     VMExecutor,
 };
 use libra_logger::prelude::*;
@@ -67,12 +68,16 @@ impl LibraVM {
         let mut session = self.0.new_session(remote_cache);
         match TransactionStatus::from(error_code) {
             TransactionStatus::Keep(status) => {
-                if let Err(e) = self.0.run_failure_epilogue(
-                    &mut session,
-                    &mut cost_strategy,
-                    txn_data,
-                    account_currency_symbol,
-                ) {
+                if let Err(e) =
+                    run_phase(TransactionPhase::FailureEpilogue, &mut session, |session| {
+                        self.0.run_failure_epilogue(
+                            session,
+                            &mut cost_strategy,
+                            txn_data,
+                            account_currency_symbol,
+                        )
+                    })
+                {
                     return discard_error_output(e);
                 }
                 get_transaction_output(&mut (), session, &cost_strategy, txn_data, status)
@@ -92,12 +97,14 @@ impl LibraVM {
         account_currency_symbol: &IdentStr,
     ) -> Result<TransactionOutput, VMStatus> {
         let mut cost_strategy = CostStrategy::system(gas_schedule, gas_left);
-        self.0.run_success_epilogue(
-            &mut session,
-            &mut cost_strategy,
-            txn_data,
-            account_currency_symbol,
-        )?;
+        run_phase(TransactionPhase::SuccessEpilogue, &mut session, |session| {
+            self.0.run_success_epilogue(
+                session,
+                &mut cost_strategy,
+                txn_data,
+                account_currency_symbol,
+            )
+        })?;
 
         Ok(get_transaction_output(
             &mut (),
@@ -123,32 +130,32 @@ impl LibraVM {
         {
             cost_strategy.disable_metering();
             let _timer = TXN_VERIFICATION_SECONDS.start_timer();
-            self.0.check_gas(txn_data)?;
-            self.0.is_allowed_script(script)?;
-            self.0.run_prologue(
-                &mut session,
-                cost_strategy,
-                &txn_data,
-                account_currency_symbol,
-            )?;
+            run_phase(TransactionPhase::Prologue, &mut session, |session| {
+                self.0.check_gas(txn_data)?;
+                self.0.is_allowed_script(script)?;
+                self.0
+                    .run_prologue(session, cost_strategy, &txn_data, account_currency_symbol)
+            })?;
         }
 
         // Run the execution logic
         {
             let _timer = TXN_EXECUTION_SECONDS.start_timer();
             cost_strategy.enable_metering();
-            cost_strategy
-                .charge_intrinsic_gas(txn_data.transaction_size())
-                .map_err(|e| e.into_vm_status())?;
-            session
-                .execute_script(
-                    script.code().to_vec(),
-                    script.ty_args().to_vec(),
-                    convert_txn_args(script.args()),
-                    txn_data.sender(),
-                    cost_strategy,
-                )
-                .map_err(|e| e.into_vm_status())?;
+            run_phase(TransactionPhase::Payload, &mut session, |session| {
+                cost_strategy
+                    .charge_intrinsic_gas(txn_data.transaction_size())
+                    .map_err(|e| e.into_vm_status())?;
+                session
+                    .execute_script(
+                        script.code().to_vec(),
+                        script.ty_args().to_vec(),
+                        convert_txn_args(script.args()),
+                        txn_data.sender(),
+                        cost_strategy,
+                    )
+                    .map_err(|e| e.into_vm_status())
+            })?;
 
             cost_strategy.disable_metering();
             self.success_transaction_cleanup(
@@ -174,14 +181,12 @@ impl LibraVM {
 
         // Run validation logic
         cost_strategy.disable_metering();
-        self.0.check_gas(txn_data)?;
-        self.0.is_allowed_module(txn_data, remote_cache)?;
-        self.0.run_prologue(
-            &mut session,
-            cost_strategy,
-            txn_data,
-            account_currency_symbol,
-        )?;
+        run_phase(TransactionPhase::Prologue, &mut session, |session| {
+            self.0.check_gas(txn_data)?;
+            self.0.is_allowed_module(txn_data, remote_cache)?;
+            self.0
+                .run_prologue(session, cost_strategy, txn_data, account_currency_symbol)
+        })?;
 
         // Publish the module
         let module_address = if self.0.on_chain_config()?.publishing_option.is_open_module() {
@@ -191,12 +196,14 @@ impl LibraVM {
         };
 
         cost_strategy.enable_metering();
-        cost_strategy
-            .charge_intrinsic_gas(txn_data.transaction_size())
-            .map_err(|e| e.into_vm_status())?;
-        session
-            .publish_module(module.code().to_vec(), module_address, cost_strategy)
-            .map_err(|e| e.into_vm_status())?;
+        run_phase(TransactionPhase::Payload, &mut session, |session| {
+            cost_strategy
+                .charge_intrinsic_gas(txn_data.transaction_size())
+                .map_err(|e| e.into_vm_status())?;
+            session
+                .publish_module(module.code().to_vec(), module_address, cost_strategy)
+                .map_err(|e| e.into_vm_status())
+        })?;
 
         self.success_transaction_cleanup(
             session,
@@ -0,0 +1,97 @@
+// Copyright (c) The Libra Core Contributors
+// SPDX-License-Identifier: Apache-2.0
+
+//! Hooks into the phases the VM runs a user transaction in, for tests and tools that need to see
+//! where each phase starts and ends.
+
+use crate::libra_vm::txn_effects_to_writeset_and_events;
+use libra_types::{vm_status::VMStatus, write_set::WriteSet};
+use move_vm_runtime::{data_cache::RemoteCache, session::Session};
+use std::{
+    cell::RefCell,
+    time::{Duration, Instant},
+};
+
+/// A phase of running a user transaction.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TransactionPhase {
+    /// Checking the gas parameters and the payload, and running the prologue.
+    Prologue,
+    /// Charging the intrinsic gas, then running the script or publishing the module.
+    Payload,
+    /// Running the epilogue of a transaction whose payload succeeded.
+    SuccessEpilogue,
+    /// Running the epilogue of a transaction that failed but is kept.
+    FailureEpilogue,
+}
+
+/// A phase that just ended, as passed to a hook installed with [`with_phase_hook`].
+#[derive(Clone, Debug)]
+pub struct PhaseEvent {
+    pub phase: TransactionPhase,
+    /// How long the phase took.
+    pub duration: Duration,
+    /// The error the phase failed with, if it did.
+    pub error: Option<VMStatus>,
+    /// What the session the phase ran in would write if it finished now, including the writes of
+    /// the phases that ran before in the same session. `None` if the phase failed.
+    ///
+    /// The failure epilogue runs in a session of its own, so its write set only holds its writes.
+    pub write_set: Option<WriteSet>,
+}
+
+type PhaseHook = Box<dyn FnMut(&PhaseEvent)>;
+
+thread_local! {
+    static PHASE_HOOK: RefCell<Option<PhaseHook>> = RefCell::new(None);
+}
+
+/// Runs `f` with `hook` called at the end of every phase of the user transactions the VM executes
+/// on this thread.
+///
+/// Write sets are only computed for the hook, so execution doesn't pay for them otherwise.
+pub fn with_phase_hook<T>(hook: impl FnMut(&PhaseEvent) + 'static, f: impl FnOnce() -> T) -> T {
+    let previous = PHASE_HOOK.with(|phase_hook| phase_hook.replace(Some(Box::new(hook))));
+    let result = f();
+    PHASE_HOOK.with(|phase_hook| *phase_hook.borrow_mut() = previous);
+    result
+}
+
+/// Runs `f` as `phase` of a transaction in `session`, reporting it to the hook installed on this
+/// thread, if any.
+pub(crate) fn run_phase<'r, 'l, R: RemoteCache, T>(
+    phase: TransactionPhase,
+    session: &mut Session<'r, 'l, R>,
+    f: impl FnOnce(&mut Session<'r, 'l, R>) -> Result<T, VMStatus>,
+) -> Result<T, VMStatus> {
+    if PHASE_HOOK.with(|phase_hook| phase_hook.borrow().is_none()) {
+        return f(session);
+    }
+    let start = Instant::now();
+    let result = f(session);
+    let duration = start.elapsed();
+    let event = match &result {
+        Ok(_) => PhaseEvent {
+            phase,
+            duration,
+            error: None,
+            write_set: session
+                .effects()
+                .ok()
+                .and_then(|effects| txn_effects_to_writeset_and_events(effects).ok())
+                .map(|(write_set, _)| write_set),
+        },
+        Err(err) => PhaseEvent {
+            phase,
+            duration,
+            error: Some(err.clone()),
+            write_set: None,
+        },
+    };
+    PHASE_HOOK.with(|phase_hook| {
+        if let Some(hook) = phase_hook.borrow_mut().as_mut() {
+            hook(&event)
+        }
+    });
+    result
+}
@@ -29,3 +29,39 @@ impl fmt::Debug for TransactionArgument {
         }
     }
 }
+
+impl From<u8> for TransactionArgument {
+    fn from(value: u8) -> Self {
+        TransactionArgument::U8(value)
+    }
+}
+
+impl From<u64> for TransactionArgument {
+    fn from(value: u64) -> Self {
+        TransactionArgument::U64(value)
+    }
+}
+
+impl From<u128> for TransactionArgument {
+    fn from(value: u128) -> Self {
+        TransactionArgument::U128(value)
+    }
+}
+
+impl From<AccountAddress> for TransactionArgument {
+    fn from(address: AccountAddress) -> Self {
+        TransactionArgument::Address(address)
+    }
+}
+
+impl From<Vec<u8>> for TransactionArgument {
+    fn from(vector: Vec<u8>) -> Self {
+        TransactionArgument::U8Vector(vector)
+    }
+}
+
+impl From<bool> for TransactionArgument {
+    fn from(boolean: bool) -> Self {
+        TransactionArgument::Bool(boolean)
+    }
+}
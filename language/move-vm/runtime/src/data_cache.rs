@@ -13,7 +13,7 @@ use move_core_types::{
 use move_vm_types::{
     data_store::DataStore,
     loaded_data::runtime_types::Type,
-    values::{GlobalValue, Value},
+    values::{GlobalValue, StructRef, Value},
 };
 use std::collections::btree_map::BTreeMap;
 use vm::{errors::*, file_format::CompiledModule};
@@ -135,6 +135,54 @@ impl<'r, 'l, R: RemoteCache> TransactionDataCache<'r, 'l, R> {
         })
     }
 
+    /// Like [`TransactionDataCache::into_effects`], but copies the updates made so far instead of
+    /// consuming the cache, so that the transaction can go on afterwards.
+    pub(crate) fn effects(&self) -> PartialVMResult<TransactionEffects> {
+        let mut modules = vec![];
+        let mut resources = vec![];
+        for (addr, account_cache) in &self.account_map {
+            let mut vals = vec![];
+            for (ty, gv_opt) in &account_cache.data_map {
+                match gv_opt {
+                    None => {
+                        let ty_tag = self.loader.type_to_type_tag(ty)?;
+                        vals.push((ty_tag, None));
+                    }
+                    Some(gv) => {
+                        if gv.is_dirty()? {
+                            let ty_tag = self.loader.type_to_type_tag(ty)?;
+                            let ty_layout = self.loader.type_to_type_layout(ty)?;
+                            let val = gv.borrow_global()?.value_as::<StructRef>()?.read_ref()?;
+                            vals.push((ty_tag, Some((ty_layout, val))));
+                        }
+                    }
+                };
+            }
+            if !vals.is_empty() {
+                resources.push((*addr, vals));
+            }
+            modules.extend(
+                account_cache
+                    .module_map
+                    .values()
+                    .map(|(blob, m)| (m.self_id(), blob.clone())),
+            );
+        }
+
+        let mut events = vec![];
+        for (guid, seq_num, ty, val) in &self.event_data {
+            let ty_tag = self.loader.type_to_type_tag(ty)?;
+            let ty_layout = self.loader.type_to_type_layout(ty)?;
+            events.push((guid.clone(), *seq_num, ty_tag, ty_layout, val.copy_value()?))
+        }
+
+        Ok(TransactionEffects {
+            resources,
+            modules,
+            events,
+        })
+    }
+
     fn get_mut_or_insert_with<'a, K, V, F>(map: &'a mut BTreeMap<K, V>, k: &K, gen: F) -> &'a mut V
     where
         F: FnOnce() -> (K, V),
@@ -656,7 +656,13 @@ impl Frame {
         let code = self.function.code();
         loop {
             for instruction in &code[self.pc as usize..] {
-                trace!(self.function.pretty_string(), self.pc, instruction);
+                trace!(
+                    self.function.pretty_string(),
+                    self.pc,
+                    instruction,
+                    interpreter.call_stack.0.len(),
+                    &interpreter.operand_stack.0
+                );
                 self.pc += 1;
 
                 match instruction {
@@ -21,4 +21,4 @@ mod native_functions;
 mod runtime;
 pub mod session;
 #[macro_use]
-mod tracing;
+pub mod tracing;
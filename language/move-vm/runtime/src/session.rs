@@ -66,6 +66,13 @@ impl<'r, 'l, R: RemoteCache> Session<'r, 'l, R> {
             .publish_module(module, sender, &mut self.data_cache, cost_strategy)
     }
 
+    /// Returns the effects of the session so far, without finishing it.
+    pub fn effects(&self) -> VMResult<TransactionEffects> {
+        self.data_cache
+            .effects()
+            .map_err(|e| e.finish(Location::Undefined))
+    }
+
     pub fn finish(self) -> VMResult<TransactionEffects> {
         self.data_cache
             .into_effects()
@@ -1,8 +1,10 @@
 // Copyright (c) The Libra Core Contributors
 // SPDX-License-Identifier: Apache-2.0
 
+use move_vm_types::values::Value;
 #[cfg(debug_assertions)]
 use once_cell::sync::Lazy;
+use std::cell::RefCell;
 #[cfg(debug_assertions)]
 use std::{
     env,
@@ -10,7 +12,6 @@ use std::{
     io::Write,
     sync::Mutex,
 };
-#[cfg(debug_assertions)]
 use vm::file_format::Bytecode;
 
 #[cfg(debug_assertions)]
@@ -36,20 +37,67 @@ static LOGGING_FILE: Lazy<Mutex<File>> = Lazy::new(|| {
     )
 });
 
+/// An instruction about to be executed, as passed to a hook installed with [`with_trace_hook`].
+pub struct TraceEvent<'a> {
+    /// The function being executed, e.g. `0x1::LibraAccount::pay_from`.
+    pub function: &'a str,
+    /// The offset of the instruction in the function.
+    pub pc: u16,
+    pub instruction: &'a Bytecode,
+    /// The number of frames under the one of `function` on the call stack.
+    pub call_depth: usize,
+    /// The operand stack, bottom first.
+    pub operand_stack: &'a [Value],
+}
+
+type TraceHook = Box<dyn FnMut(&TraceEvent)>;
+
+thread_local! {
+    static TRACE_HOOK: RefCell<Option<TraceHook>> = RefCell::new(None);
+}
+
+/// Runs `f` with `hook` called before every instruction the VM executes on this thread.
+///
+/// Instructions are only traced in debug builds, like with the `MOVE_VM_TRACE` environment
+/// variable: in release builds the hook is never called.
+pub fn with_trace_hook<T>(hook: impl FnMut(&TraceEvent) + 'static, f: impl FnOnce() -> T) -> T {
+    let previous = TRACE_HOOK.with(|trace_hook| trace_hook.replace(Some(Box::new(hook))));
+    let result = f();
+    TRACE_HOOK.with(|trace_hook| *trace_hook.borrow_mut() = previous);
+    result
+}
+
 // Only include in debug builds
 #[cfg(debug_assertions)]
-pub fn trace(function_desc: &str, pc: u16, instr: &Bytecode) {
+pub fn trace(
+    function_desc: &str,
+    pc: u16,
+    instr: &Bytecode,
+    call_depth: usize,
+    operand_stack: &[Value],
+) {
     if *TRACING_ENABLED {
         let f = &mut *LOGGING_FILE.lock().unwrap();
         writeln!(f, "{},{},{:?}", function_desc, pc, instr).unwrap();
     }
+    TRACE_HOOK.with(|trace_hook| {
+        if let Some(hook) = trace_hook.borrow_mut().as_mut() {
+            hook(&TraceEvent {
+                function: function_desc,
+                pc,
+                instruction: instr,
+                call_depth,
+                operand_stack,
+            })
+        }
+    });
 }
 
 #[macro_export]
 macro_rules! trace {
-    ($function_desc:expr, $pc:expr, $instr:tt) => {
+    ($function_desc:expr, $pc:expr, $instr:tt, $call_depth:expr, $operand_stack:expr) => {
         // Only include this code in debug releases
         #[cfg(debug_assertions)]
-        crate::tracing::trace(&$function_desc, $pc, &$instr)
+        crate::tracing::trace(&$function_desc, $pc, &$instr, $call_depth, $operand_stack)
     };
 }